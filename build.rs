@@ -0,0 +1,47 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Embed the short git commit hash as `QBIT_GIT_HASH` when building from a
+/// checkout; source tarballs without git simply omit it.
+fn main() {
+    watch_git_head();
+
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty());
+
+    if let Some(hash) = hash {
+        println!("cargo:rustc-env=QBIT_GIT_HASH={hash}");
+    }
+}
+
+/// Rerun when HEAD moves: on checkout (`.git/HEAD`) and on commit (the branch
+/// ref HEAD points to, or `packed-refs` once git packs it). Without a
+/// `.git/HEAD` only build.rs is watched, so tarball builds don't rerun on
+/// every source change.
+fn watch_git_head() {
+    let git = Path::new(".git");
+    let head = git.join("HEAD");
+    let Ok(content) = fs::read_to_string(&head) else {
+        println!("cargo:rerun-if-changed=build.rs");
+        return;
+    };
+    println!("cargo:rerun-if-changed={}", head.display());
+
+    if let Some(reference) = content.strip_prefix("ref:").map(str::trim) {
+        let ref_path = git.join(reference);
+        if ref_path.exists() {
+            println!("cargo:rerun-if-changed={}", ref_path.display());
+        }
+        let packed = git.join("packed-refs");
+        if packed.exists() {
+            println!("cargo:rerun-if-changed={}", packed.display());
+        }
+    }
+}
@@ -7,10 +7,39 @@ use std::time::{Duration, Instant};
 
 use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 
+#[path = "../utils/glob.rs"]
+mod glob;
+
+use glob::IgnoreFilter;
+
 const CONFIG_FILES: &[&str] = &["qbit.yml", "qbit.yaml", "qbit.toml"];
+const DEFAULT_SANDBOX: &str = "dev-sandbox";
+const DEFAULT_TARGET_DIR: &str = "target/qbit-dev";
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+const HISTORY_FILE: &str = ".qbit-dev-history";
 
 fn sandbox_dir() -> PathBuf {
-    PathBuf::from("dev-sandbox")
+    std::env::var("QBIT_DEV_SANDBOX")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SANDBOX))
+}
+
+/// Cargo target directory for dev builds, kept apart from the workspace's
+/// `target/` so an outer `cargo build` doesn't hold the build lock. Absolute,
+/// because `cargo run` executes from inside the sandbox.
+fn dev_target_dir() -> PathBuf {
+    let dir = std::env::var("QBIT_DEV_TARGET_DIR")
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_TARGET_DIR));
+    std::env::current_dir()
+        .map(|cwd| cwd.join(&dir))
+        .unwrap_or(dir)
 }
 
 fn ensure_sandbox() -> std::io::Result<()> {
@@ -30,7 +59,99 @@ fn reset_sandbox() -> std::io::Result<()> {
     ensure_sandbox()
 }
 
-fn run_qbit_in_sandbox(input: &str) -> std::io::Result<i32> {
+fn history_path() -> PathBuf {
+    sandbox_dir().join(HISTORY_FILE)
+}
+
+fn load_history() -> Vec<String> {
+    fs::read_to_string(history_path())
+        .map(|content| {
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn save_history(history: &[String]) -> std::io::Result<()> {
+    let mut content = history.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    fs::write(history_path(), content)
+}
+
+/// Resolve a `!<n>` history reference (1-based, as printed by `:history`).
+fn history_entry<'a>(history: &'a [String], reference: &str) -> Result<&'a str, String> {
+    let index: usize = reference
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid history index `{reference}`"))?;
+    index
+        .checked_sub(1)
+        .and_then(|idx| history.get(idx))
+        .map(String::as_str)
+        .ok_or_else(|| format!("no history entry #{index}"))
+}
+
+fn qbit_binary_path() -> PathBuf {
+    let target_dir = dev_target_dir();
+    let name = if cfg!(windows) {
+        "qbit-cli.exe"
+    } else {
+        "qbit-cli"
+    };
+    target_dir.join("debug").join(name)
+}
+
+/// Build `qbit-cli` once and return the absolute path of the produced binary.
+/// Returns `None` when the build fails or the binary can't be located, so callers
+/// fall back to `cargo run`.
+fn build_qbit_binary() -> Option<PathBuf> {
+    eprintln!("[dev] building qbit-cli...");
+    let status = Command::new("cargo")
+        .arg("build")
+        .arg("--bin")
+        .arg("qbit-cli")
+        .arg("--target-dir")
+        .arg(dev_target_dir())
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status();
+
+    match status {
+        Ok(st) if st.success() => {}
+        Ok(st) => {
+            eprintln!(
+                "[dev] cargo build failed (code {}); falling back to `cargo run`",
+                st.code().unwrap_or(1)
+            );
+            return None;
+        }
+        Err(e) => {
+            eprintln!("[dev] failed to spawn cargo build: {e}; falling back to `cargo run`");
+            return None;
+        }
+    }
+
+    let path = qbit_binary_path();
+    match path.canonicalize() {
+        Ok(abs) => Some(abs),
+        Err(_) => {
+            eprintln!(
+                "[dev] built binary not found at {}; falling back to `cargo run`",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+fn run_qbit_in_sandbox(input: &str, binary: Option<&Path>) -> std::io::Result<i32> {
     let parts: Vec<String> = input.split_whitespace().map(|s| s.to_string()).collect();
     if parts.is_empty() {
         return Ok(0);
@@ -41,12 +162,20 @@ fn run_qbit_in_sandbox(input: &str) -> std::io::Result<i32> {
     let sandbox = sandbox_dir();
     let sandbox_abs = sandbox.canonicalize().unwrap_or_else(|_| sandbox.clone());
 
-    let mut cmd = Command::new("cargo");
-    cmd.arg("run")
-        .arg("--bin")
-        .arg("qbit-cli")
-        .arg("--")
-        .current_dir(&sandbox)
+    let mut cmd = match binary {
+        Some(path) => Command::new(path),
+        None => {
+            let mut cmd = Command::new("cargo");
+            cmd.arg("run")
+                .arg("--bin")
+                .arg("qbit-cli")
+                .arg("--target-dir")
+                .arg(dev_target_dir())
+                .arg("--");
+            cmd
+        }
+    };
+    cmd.current_dir(&sandbox)
         .env("QBIT_PROJECT_ROOT", sandbox_abs)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -70,9 +199,29 @@ struct WatchConfig {
     debounce_ms: u64,
 }
 
+impl WatchConfig {
+    fn from_env() -> Self {
+        let debounce_ms = match std::env::var("QBIT_DEV_DEBOUNCE_MS") {
+            Ok(raw) => match raw.trim().parse::<u64>() {
+                Ok(ms) => ms,
+                Err(_) => {
+                    eprintln!(
+                        "[dev] ignoring QBIT_DEV_DEBOUNCE_MS=`{raw}` (expected a non-negative integer); using {DEFAULT_DEBOUNCE_MS}ms"
+                    );
+                    DEFAULT_DEBOUNCE_MS
+                }
+            },
+            Err(_) => DEFAULT_DEBOUNCE_MS,
+        };
+        Self { debounce_ms }
+    }
+}
+
 struct WatchState {
     enabled: bool,
     last_cmd: Option<String>,
+    history: Vec<String>,
+    binary: Option<PathBuf>,
     _watcher: Option<RecommendedWatcher>, // keep watcher alive
 }
 
@@ -81,12 +230,37 @@ impl WatchState {
         Self {
             enabled: false,
             last_cmd: None,
+            history: Vec::new(),
+            binary: None,
             _watcher: None,
         }
     }
+
+    fn run_entered(&mut self, input: &str) {
+        self.last_cmd = Some(input.to_string());
+        self.history.push(input.to_string());
+        if let Err(e) = save_history(&self.history) {
+            eprintln!("[dev] failed to save history: {e}");
+        }
+        match run_qbit_in_sandbox(input, self.binary.as_deref()) {
+            Ok(code) if code != 0 => eprintln!("[dev] qbit exited with code {code}"),
+            Ok(_) => {}
+            Err(e) => eprintln!("[dev] failed to run qbit: {e}"),
+        }
+    }
 }
 
 fn setup_watcher(tx: Sender<()>) -> notify::Result<RecommendedWatcher> {
+    let root = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_else(|_| PathBuf::from("."));
+    let sandbox = sandbox_dir();
+    let sandbox = sandbox.canonicalize().unwrap_or(sandbox);
+    let target_dir = dev_target_dir();
+    let target_dir = target_dir.canonicalize().unwrap_or(target_dir);
+
+    let mut ignore = IgnoreFilter::new(&root);
+
     // notify v8 style: closure receives Result<Event>
     let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
         if let Ok(ev) = res {
@@ -94,7 +268,11 @@ fn setup_watcher(tx: Sender<()>) -> notify::Result<RecommendedWatcher> {
                 ev.kind,
                 EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
             );
-            if interesting {
+            if interesting
+                && ev.paths.iter().any(|p| {
+                    !ignore.should_ignore(p) && is_watched_path(p, &root, &sandbox, &target_dir)
+                })
+            {
                 let _ = tx.send(());
             }
         }
@@ -116,6 +294,34 @@ fn setup_watcher(tx: Sender<()>) -> notify::Result<RecommendedWatcher> {
     Ok(watcher)
 }
 
+/// Decide whether a change at `path` should trigger a re-run. Build output
+/// (including the dev target dir, wherever it lives), VCS metadata, and the
+/// sandbox itself are ignored; only sources, the manifest/lockfile, and qbit
+/// config files count.
+fn is_watched_path(path: &Path, root: &Path, sandbox: &Path, target_dir: &Path) -> bool {
+    if path.starts_with(sandbox) || path.starts_with(target_dir) {
+        return false;
+    }
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    let rel = rel.strip_prefix(".").unwrap_or(rel);
+
+    let Some(first) = rel.components().next() else {
+        return false;
+    };
+    let first = first.as_os_str();
+    if first == "target" || first == ".git" {
+        return false;
+    }
+    if first == "src" {
+        return true;
+    }
+
+    rel.components().count() == 1
+        && (first == "Cargo.toml"
+            || first == "Cargo.lock"
+            || CONFIG_FILES.iter().any(|file| first == *file))
+}
+
 fn sync_config_files() -> std::io::Result<()> {
     let sandbox = sandbox_dir();
     for file in CONFIG_FILES {
@@ -146,9 +352,14 @@ fn print_usage() {
     println!(
         "qbit-dev usage:
   cargo dev                        start interactive dev mode
-  cargo dev-clean                  clear and recreate dev-sandbox
+  cargo dev-clean                  clear and recreate the sandbox
   cargo dev -- --reset-sandbox     same as above
-  cargo dev -- --help              show this message"
+  cargo dev -- --help              show this message
+
+environment:
+  QBIT_DEV_SANDBOX                 sandbox directory (default: dev-sandbox)
+  QBIT_DEV_TARGET_DIR              cargo target dir for dev builds (default: target/qbit-dev)
+  QBIT_DEV_DEBOUNCE_MS             watch debounce window in ms (default: 500)"
     );
 }
 
@@ -212,11 +423,15 @@ fn main() {
         "qbit-dev — sandbox ready at: {}",
         sandbox_dir().to_string_lossy()
     );
-    println!("Type ':reset'/'reset', ':exit'/'exit', ':watch [on|off]', ':help'.\n");
+    println!(
+        "Type ':reset'/'reset', ':exit'/'exit', ':watch [on|off]', ':history', ':!<n>', ':help'.\n"
+    );
 
-    let cfg = WatchConfig { debounce_ms: 500 };
+    let cfg = WatchConfig::from_env();
     let (tx, rx) = channel::<()>();
     let mut state = WatchState::new();
+    state.history = load_history();
+    state.binary = build_qbit_binary();
 
     match setup_watcher(tx.clone()) {
         Ok(w) => state._watcher = Some(w),
@@ -226,9 +441,10 @@ fn main() {
     loop {
         if state.enabled && rx.recv_timeout(Duration::from_millis(10)).is_ok() {
             drain_with_debounce(&rx, cfg.debounce_ms);
+            state.binary = build_qbit_binary();
             if let Some(cmd) = state.last_cmd.as_ref() {
                 eprintln!("[dev] change detected — re-running: {cmd}");
-                match run_qbit_in_sandbox(cmd) {
+                match run_qbit_in_sandbox(cmd, state.binary.as_deref()) {
                     Ok(code) if code != 0 => eprintln!("[dev] qbit exited with code {code}"),
                     Ok(_) => {}
                     Err(e) => eprintln!("[dev] failed to run qbit: {e}"),
@@ -263,19 +479,26 @@ fn main() {
                 break;
             }
             "reset" => match reset_sandbox() {
-                Ok(_) => println!("[dev] sandbox reset"),
+                Ok(_) => {
+                    println!("[dev] sandbox reset");
+                    if let Err(e) = save_history(&state.history) {
+                        eprintln!("[dev] failed to save history: {e}");
+                    }
+                }
                 Err(e) => eprintln!("[dev] reset failed: {e}"),
             },
             "help" => {
                 println!(
                     "[dev] commands:
-  - :reset | reset            clear dev-sandbox
+  - :reset | reset            clear the sandbox
   - :exit  | exit | quit      quit
   - :watch                    show watch status
   - :watch on                 enable hot-reload
   - :watch off                disable hot-reload
+  - :history                  list previous commands
+  - :!<n>                     re-run history entry <n>
   - :help  | help             show this help
-  - any other text            run 'qbit <args>' inside dev-sandbox"
+  - any other text            run 'qbit <args>' inside the sandbox"
                 );
             }
             "watch" => println!(
@@ -290,14 +513,118 @@ fn main() {
                 state.enabled = false;
                 println!("[dev] watch OFF");
             }
-            _ => {
-                state.last_cmd = Some(raw_input.to_string());
-                match run_qbit_in_sandbox(raw_input) {
-                    Ok(code) if code != 0 => eprintln!("[dev] qbit exited with code {code}"),
-                    Ok(_) => {}
-                    Err(e) => eprintln!("[dev] failed to run qbit: {e}"),
+            "history" => {
+                if state.history.is_empty() {
+                    println!("[dev] history is empty");
+                }
+                for (idx, entry) in state.history.iter().enumerate() {
+                    println!("{:>4}  {entry}", idx + 1);
                 }
             }
+            other if other.starts_with('!') => match history_entry(&state.history, &other[1..]) {
+                Ok(entry) => {
+                    let entry = entry.to_string();
+                    eprintln!("[dev] re-running: {entry}");
+                    state.run_entered(&entry);
+                }
+                Err(e) => eprintln!("[dev] {e}"),
+            },
+            _ => state.run_entered(raw_input),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_entry_uses_one_based_indices() {
+        let history = vec!["py init".to_string(), "run build".to_string()];
+        assert_eq!(history_entry(&history, "2"), Ok("run build"));
+        assert!(history_entry(&history, "0").is_err());
+        assert!(history_entry(&history, "3").is_err());
+        assert!(history_entry(&history, "x").is_err());
+    }
+
+    #[test]
+    fn watcher_ignores_build_output_and_vcs() {
+        let root = Path::new("/work/qbit");
+        let sandbox = root.join("dev-sandbox");
+        let target = root.join("target/qbit-dev");
+        assert!(!is_watched_path(
+            &root.join("target/debug/qbit-cli"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(!is_watched_path(
+            &root.join(".git/index"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(!is_watched_path(
+            &sandbox.join("qbit.yml"),
+            root,
+            &sandbox,
+            &target
+        ));
+    }
+
+    #[test]
+    fn watcher_ignores_custom_dev_target_dir() {
+        let root = Path::new("/work/qbit");
+        let sandbox = root.join("dev-sandbox");
+        let target = root.join("src/dev-build");
+        assert!(!is_watched_path(
+            &target.join("debug/qbit-cli"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(is_watched_path(
+            &root.join("src/cli.rs"),
+            root,
+            &sandbox,
+            &target
+        ));
+    }
+
+    #[test]
+    fn watcher_forwards_sources_manifest_and_config() {
+        let root = Path::new("/work/qbit");
+        let sandbox = root.join("dev-sandbox");
+        let target = root.join("target/qbit-dev");
+        assert!(is_watched_path(
+            &root.join("src/cli.rs"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(is_watched_path(
+            &root.join("Cargo.toml"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(is_watched_path(
+            &root.join("Cargo.lock"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(is_watched_path(
+            &root.join("qbit.yml"),
+            root,
+            &sandbox,
+            &target
+        ));
+        assert!(!is_watched_path(
+            &root.join("README.md"),
+            root,
+            &sandbox,
+            &target
+        ));
+    }
+}
@@ -1,29 +1,120 @@
-use crate::developers::{dart, js, py};
+use crate::config::{LoadedProjectConfig, load_project_config};
+use crate::developers::{dart, dotnet, java, js, php, py, ruby};
+use crate::error::QbitError;
 use crate::os::{install, upgrade};
-use crate::tools::runner;
-use clap::{Parser, Subcommand};
+use crate::tools::{clean, config_edit, exec, init, list, man, runner, version, watch};
+use crate::utils::aliases::{expand_aliases, subcommand_index};
+use crate::utils::batch;
+use crate::utils::ci::is_ci;
+use crate::utils::interrupt::{self, EXIT_INTERRUPTED};
+use crate::utils::output::OutputMode;
+use crate::utils::run_log::RunLog;
+use crate::utils::shell::{DryRunPlan, RunOptions};
+use anyhow::{Result, bail};
+use clap::{CommandFactory, Parser, Subcommand};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
 /// Root CLI for qbit
 #[derive(Parser)]
-#[command(name = "qbit")]
+#[command(name = "qbit", version)]
 #[command(about = "Multi-language package/project manager")]
+#[command(after_help = "Exit codes:
+  0  success
+  1  generic failure
+  2  usage error
+  3  no qbit.yml/qbit.toml found
+  4  script not defined in the config
+  N  a command run by qbit exited with code N
+  130  interrupted with Ctrl-C")]
 pub struct Cli {
+    /// Emit machine-readable JSON on stdout (progress text goes to stderr)
+    #[arg(long, global = true)]
+    pub json: bool,
+    /// Refuse all network access (also enabled by QBIT_OFFLINE=1)
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Show more detail, such as per-step timings after `qbit run`
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+    /// Keep interactive defaults even when CI is detected (CI, GITHUB_ACTIONS, ...)
+    #[arg(long, global = true)]
+    pub no_ci: bool,
     #[command(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Set up a new project: detect (or ask for) its type and write a starter qbit.yml
+    Init {
+        /// Project type to scaffold instead of detecting it
+        #[arg(long = "type", value_enum)]
+        kind: Option<init::ProjectType>,
+        /// Never prompt; fail if the type can't be detected
+        #[arg(long)]
+        non_interactive: bool,
+    },
     /// Install a system dependency (java, python, ...)
     Install {
-        /// Package to install
-        target: String,
+        /// Packages to install
+        #[arg(required_unless_present_any = ["list_managers", "validate"])]
+        targets: Vec<String>,
         /// Print the resolved installer command without executing it
         #[arg(long)]
         dry_run: bool,
         /// Prefer non-interactive mode (adds `-y`/equivalent where supported)
         #[arg(long)]
         yes: bool,
+        /// Reinstall even if already installed (uses apt --reinstall, brew/dnf reinstall)
+        #[arg(long)]
+        force: bool,
+        /// If the detected package manager fails, try the next available one
+        #[arg(long)]
+        try_all: bool,
+        /// Continue past failed targets and summarize them at the end
+        #[arg(long)]
+        keep_going: bool,
+        /// Print the resolved version, identifier, and command for each available manager, then exit
+        #[arg(long)]
+        print_plan: bool,
+        /// Refresh the package index first (apt-get update, dnf makecache, zypper refresh)
+        #[arg(long)]
+        refresh: bool,
+        /// Run system package managers without `sudo` (also QBIT_NO_SUDO=1)
+        #[arg(long)]
+        no_sudo: bool,
+        /// Accept package license prompts (choco `--accept-license`; winget always accepts)
+        #[arg(long)]
+        accept_licenses: bool,
+        /// Show every known package manager and whether it was detected, then exit
+        #[arg(long, conflicts_with = "targets")]
+        list_managers: bool,
+        /// Install exactly what qbit.lock records; fail if it is missing or out of date with qbit.yml
+        #[arg(long, conflicts_with = "try_all")]
+        frozen: bool,
+        /// Check that each target (every configured one when none are named) can be installed with the selected manager, without installing
+        #[arg(long, conflicts_with_all = ["try_all", "print_plan"])]
+        validate: bool,
+        /// Use this package manager for this run (over QBIT_PACKAGE_MANAGER and install.manager)
+        #[arg(long, value_name = "NAME", conflicts_with = "try_all")]
+        manager: Option<String>,
+        /// Install up to N targets at once (apt-get, dnf and other locking managers stay at 1)
+        #[arg(
+            short,
+            long,
+            value_name = "N",
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u16).range(1..),
+            conflicts_with_all = ["try_all", "print_plan"]
+        )]
+        jobs: u16,
+    },
+    /// Explain how `qbit install <target>` resolves (config, manager, identifier, version, command) without installing
+    Why {
+        /// Install target, optionally pinned as name:version
+        target: String,
     },
     /// Python-related commands
     Py {
@@ -32,8 +123,43 @@ pub enum Commands {
     },
     /// Run named scripts from qbit.yml/qbit.toml
     Run {
-        /// Script name defined under `scripts`
-        name: String,
+        /// Script names defined under `scripts`, run in order (defaults to the
+        /// `default` script, or lists scripts when there is none)
+        names: Vec<String>,
+        /// Re-run the script whenever project files change (Ctrl-C to stop)
+        #[arg(long)]
+        watch: bool,
+        /// Continue past failed scripts and summarize them at the end
+        #[arg(long)]
+        keep_going: bool,
+        /// Prefix every output line with its script and step (`[script:name:step N]`)
+        #[arg(long)]
+        prefix: bool,
+        /// Print each step with variables expanded instead of running it
+        #[arg(long, conflicts_with = "watch")]
+        dry_run: bool,
+        /// Also append every step's output, with timestamps, to this file
+        #[arg(long = "output", value_name = "FILE", conflicts_with = "dry_run")]
+        log_file: Option<PathBuf>,
+    },
+    /// List scripts and install targets defined in qbit.yml/qbit.toml
+    List,
+    /// Run a command from the project root with the venv on PATH
+    Exec {
+        /// Command and arguments (use `--` before commands with flags)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Remove generated artifacts (venv, node_modules, target, .dart_tool) for detected project types
+    Clean {
+        /// Don't ask for confirmation
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Read or change a single value under `install` or `scripts` in qbit.yml
+    Config {
+        #[command(subcommand)]
+        sub: ConfigCommands,
     },
     /// JavaScript-related commands
     Js {
@@ -45,8 +171,57 @@ pub enum Commands {
         #[command(subcommand)]
         sub: DartCommands,
     },
+    /// Ruby/Bundler-related commands
+    Rb {
+        #[command(subcommand)]
+        sub: RbCommands,
+    },
+    /// PHP/Composer-related commands
+    Php {
+        #[command(subcommand)]
+        sub: PhpCommands,
+    },
+    /// Java project commands (Gradle or Maven)
+    Java {
+        #[command(subcommand)]
+        sub: JavaCommands,
+    },
+    /// .NET project commands (dotnet CLI and NuGet)
+    Dotnet {
+        #[command(subcommand)]
+        sub: DotnetCommands,
+    },
     /// Download and install the latest qbit release from GitHub
-    Upgrade,
+    Upgrade {
+        /// Only report whether a newer release exists (may use a cached lookup)
+        #[arg(long)]
+        check: bool,
+        /// Ignore the cached latest-release lookup
+        #[arg(long)]
+        refresh: bool,
+        /// Include pre-release versions (also QBIT_UPGRADE_PRERELEASE=1); with --check, report both channels
+        #[arg(long, visible_alias = "include-prereleases")]
+        pre: bool,
+        /// GitHub repository to upgrade from, as owner/name (overrides QBIT_UPGRADE_REPO)
+        #[arg(long, value_name = "OWNER/NAME")]
+        repo: Option<String>,
+        /// Install this release archive (.tar.gz/.zip path or http(s) URL) instead of the latest release
+        #[arg(
+            long,
+            value_name = "PATH|URL",
+            conflicts_with_all = ["check", "refresh", "pre", "repo"]
+        )]
+        from: Option<String>,
+    },
+    /// Print the qbit version, target platform, and build commit
+    Version,
+    /// Generate roff man pages (for packagers)
+    #[command(hide = true)]
+    Man {
+        /// Directory to write one page per subcommand into (default: top-level page to stdout)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
 }
 
 /// Python subcommands
@@ -58,12 +233,30 @@ pub enum PyCommands {
     Add {
         /// Package name
         package: String,
+        /// Install for the current user (`pip install --user`) instead of into the venv
+        #[arg(long)]
+        user: bool,
     },
     /// Remove a package
     Remove {
         /// Package name
         package: String,
     },
+    /// Upgrade a package to the latest version
+    Upgrade {
+        /// Package name
+        package: String,
+    },
+    /// Install dependencies from uv.lock, poetry.lock, or requirements.txt
+    Sync,
+    /// List packages installed in the venv
+    List,
+    /// Print the venv's exact packages (`pip freeze`) without writing files
+    Freeze,
+    /// Write requirements.lock with exact pins (and hashes when pip reports them)
+    Lock,
+    /// Open your shell with the venv activated (creates the venv if needed)
+    Shell,
 }
 
 /// JavaScript subcommands
@@ -71,21 +264,55 @@ pub enum PyCommands {
 pub enum JsCommands {
     /// Initialize a JS/TS project
     Init,
-    /// Add a package via npm/yarn/pnpm (future)
+    /// Add a package via npm/yarn/pnpm/bun, or install every dependency when none is named
     Add {
-        /// Package name
-        package: String,
+        /// Package name; omit to install from the lockfile (`npm ci` and equivalents)
+        package: Option<String>,
+        /// Workspace package to target (pnpm --filter, yarn workspace, npm -w)
+        #[arg(long)]
+        filter: Option<String>,
+        /// Respect the lockfile: add the package at its locked version, or fail a plain install without one
+        #[arg(long, visible_alias = "locked")]
+        frozen: bool,
+    },
+    /// Install every dependency package.json declares, from the lockfile when there is one
+    Install {
+        /// Skip devDependencies (`npm install --omit=dev`, `pnpm install --prod`, `--production` for yarn/bun)
+        #[arg(long, visible_alias = "prod", conflicts_with = "only")]
+        production: bool,
+        /// Install devDependencies even when NODE_ENV=production (`npm install --include=dev`, `pnpm install --dev`)
+        #[arg(long, value_name = "dev", value_parser = ["dev"])]
+        only: Option<String>,
+        /// Workspace package to target (pnpm --filter, yarn workspace, npm -w)
+        #[arg(long)]
+        filter: Option<String>,
+        /// Fail instead of creating a lockfile when there is none
+        #[arg(long, visible_alias = "locked")]
+        frozen: bool,
     },
     /// Remove a package
     Remove {
         /// Package name
         package: String,
+        /// Workspace package to target (pnpm --filter, yarn workspace, npm -w)
+        #[arg(long)]
+        filter: Option<String>,
     },
+    /// Upgrade a package within its declared range
+    Upgrade {
+        /// Package name
+        package: String,
+    },
+    /// List top-level dependencies
+    List,
     /// Run an npm/pnpm/yarn/bun script
     Run {
         /// Script name under package.json scripts
         script: String,
-        /// Extra arguments forwarded to the script after `--`
+        /// Workspace package to target (pnpm --filter, yarn workspace, npm -w)
+        #[arg(long)]
+        filter: Option<String>,
+        /// Extra arguments for the script, given after `--` (e.g. `qbit js run build -- --watch`)
         #[arg(last = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
@@ -110,98 +337,544 @@ pub enum DartCommands {
     },
 }
 
+/// Ruby subcommands
+#[derive(Subcommand)]
+pub enum RbCommands {
+    /// Create a Gemfile with `bundle init`
+    Init,
+    /// Add a gem (`gem:1.2` pins the version)
+    Add {
+        /// Gem name, optionally `name:version`
+        gem: String,
+    },
+    /// Remove a gem
+    Remove {
+        /// Gem name
+        gem: String,
+    },
+    /// Run an executable from the bundle via `bundle exec`
+    Run {
+        /// Executable to run (e.g. rspec, rails)
+        script: String,
+        /// Extra arguments passed to the executable
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// PHP subcommands
+#[derive(Subcommand)]
+pub enum PhpCommands {
+    /// Create composer.json with `composer init`
+    Init,
+    /// Add a package (`vendor/pkg:1.2` requires `^1.2`)
+    Add {
+        /// Package name, optionally `name:version`
+        package: String,
+    },
+    /// Remove a package
+    Remove {
+        /// Package name
+        package: String,
+    },
+}
+
+/// Java subcommands
+#[derive(Subcommand)]
+pub enum JavaCommands {
+    /// Scaffold a minimal Gradle (default) or Maven project
+    Init {
+        /// Use Gradle: build.gradle and settings.gradle (the default)
+        #[arg(long, conflicts_with = "maven")]
+        gradle: bool,
+        /// Use Maven: pom.xml
+        #[arg(long)]
+        maven: bool,
+    },
+    /// Add a dependency to build.gradle(.kts) or pom.xml
+    Add {
+        /// Dependency coordinate as `group:artifact:version`
+        coordinate: String,
+    },
+    /// Run a Gradle task (`./gradlew <task>`) or Maven goal (`mvn <goal>`)
+    Run {
+        /// Task or goal to run (e.g. build, test, package)
+        task: String,
+        /// Extra arguments passed to Gradle or Maven
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// .NET subcommands
+#[derive(Subcommand)]
+pub enum DotnetCommands {
+    /// Create a console app with `dotnet new console`
+    Init,
+    /// Add a NuGet package (`Package:1.2` pins `--version 1.2`)
+    Add {
+        /// Package id, optionally `id:version`
+        package: String,
+    },
+    /// Remove a NuGet package
+    Remove {
+        /// Package id
+        package: String,
+    },
+    /// Build and run the project with `dotnet run`
+    Run {
+        /// Arguments passed to the app
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Config subcommands
+#[derive(Subcommand)]
+pub enum ConfigCommands {
+    /// Print the value at a dotted key, e.g. `install.postgres.version`
+    Get {
+        /// Dotted key under `install` or `scripts`
+        key: String,
+    },
+    /// Set the value at a dotted key, leaving the rest of qbit.yml untouched
+    Set {
+        /// Dotted key under `install` or `scripts`
+        key: String,
+        /// New value
+        value: String,
+    },
+}
+
+/// Exit code for generic failures.
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code for usage errors (bad arguments, unknown subcommands).
+pub const EXIT_USAGE: u8 = 2;
+/// Exit code when a command needs qbit.yml/qbit.toml and none exists.
+pub const EXIT_NO_CONFIG: u8 = 3;
+/// Exit code when the requested script isn't defined in the config.
+pub const EXIT_NOT_DEFINED: u8 = 4;
+
 /// Dispatch after parse
-pub fn run() {
-    let cli = Cli::parse();
+pub fn run() -> ExitCode {
+    run_with_args(std::env::args_os().collect())
+}
+
+/// [`run`] with explicit arguments, program name first.
+fn run_with_args(args: Vec<OsString>) -> ExitCode {
+    let (args, preloaded) = match expand_args(args) {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            eprintln!("error (alias): {e}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() {
+                ExitCode::from(EXIT_USAGE)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+
+    let output = OutputMode::from_json_flag(cli.json);
+    // CI jobs can't answer prompts: install assumes yes, everything else
+    // fails instead of asking.
+    let ci = !cli.no_ci && is_ci();
 
+    let code = dispatch(cli, output, ci, preloaded);
+    if interrupt::interrupted() {
+        return ExitCode::from(EXIT_INTERRUPTED);
+    }
+    code
+}
+
+/// `preloaded` is the config alias expansion already read, if it had to.
+fn dispatch(
+    cli: Cli,
+    output: OutputMode,
+    ci: bool,
+    preloaded: Option<LoadedProjectConfig>,
+) -> ExitCode {
     match cli.command {
+        Commands::Init {
+            kind,
+            non_interactive,
+        } => report("init", init::init_project(kind, non_interactive || ci)),
         Commands::Install {
-            target,
+            targets,
             dry_run,
             yes,
+            force,
+            try_all,
+            keep_going,
+            print_plan,
+            refresh,
+            no_sudo,
+            accept_licenses,
+            list_managers,
+            frozen,
+            validate,
+            manager,
+            jobs,
         } => {
-            if let Err(e) = install::install_target(&target, dry_run, yes) {
-                eprintln!("error (install): {e}");
-                std::process::exit(1);
+            if list_managers {
+                return report("install", install::list_managers(output));
             }
+            let options = install::InstallOptions {
+                dry_run,
+                yes: yes || ci,
+                force,
+                try_all,
+                accept_licenses,
+                refresh,
+                no_sudo,
+                frozen,
+                manager,
+            };
+            with_config("install", preloaded, |config| {
+                if validate {
+                    return install::validate_targets(&targets, config, &options, output);
+                }
+                let jobs =
+                    install::install_jobs(usize::from(jobs), targets.len(), config, &options)?;
+                if jobs > 1 {
+                    return batch::for_each_item_parallel(
+                        &targets,
+                        "target",
+                        keep_going,
+                        jobs,
+                        |target| {
+                            output.buffered(|| {
+                                install::install_target(target, config, &options, output)
+                            })
+                        },
+                    );
+                }
+                batch::for_each_item(&targets, "target", keep_going, |target| {
+                    if print_plan {
+                        install::print_plan(target, config, &options, output)
+                    } else {
+                        install::install_target(target, config, &options, output)
+                    }
+                })
+            })
         }
-        Commands::Run { name } => {
-            if let Err(e) = runner::run_named_script(&name) {
-                eprintln!("error (run): {e}");
-                std::process::exit(1);
+        Commands::Run {
+            names,
+            watch,
+            keep_going,
+            prefix,
+            dry_run,
+            log_file,
+        } => with_config("run", preloaded, |config| {
+            let log = log_file.as_deref().map(RunLog::open).transpose()?;
+            let options = RunOptions {
+                prefix_lines: prefix,
+                dry_run,
+                verbose: cli.verbose,
+                log: log.as_ref(),
+                plan: None,
+            };
+            let names = if !names.is_empty() {
+                names
+            } else if runner::has_default_script(config) {
+                vec![runner::DEFAULT_SCRIPT.to_string()]
+            } else {
+                return list::list_config(config, output);
+            };
+            match names.as_slice() {
+                [name] if watch => watch::watch_script(config, name, output, options),
+                _ if watch => bail!("--watch runs a single script; pass exactly one name."),
+                _ => {
+                    let plan = dry_run.then(DryRunPlan::default);
+                    let options = RunOptions {
+                        plan: plan.as_ref(),
+                        ..options
+                    };
+                    let result = batch::for_each_item(&names, "script", keep_going, |name| {
+                        runner::run_named_script(config, name, output, options)
+                    });
+                    if let Some(plan) = &plan {
+                        plan.print(output)?;
+                    }
+                    result
+                }
             }
+        }),
+        Commands::Why { target } => with_config("why", preloaded, |config| {
+            let options = install::InstallOptions {
+                yes: ci,
+                ..Default::default()
+            };
+            install::explain_target(&target, config, &options, output)
+        }),
+        Commands::List => with_config("list", preloaded, |config| {
+            list::list_config(config, output)
+        }),
+        Commands::Exec { command } => {
+            with_config("exec", preloaded, |config| exec::exec(config, &command))
         }
-        Commands::Py { sub } => match sub {
-            PyCommands::Init => {
-                if let Err(e) = py::init() {
-                    eprintln!("error (init): {e}");
-                    std::process::exit(1);
-                }
-            }
-            PyCommands::Add { package } => {
-                if let Err(e) = py::add_package(&package) {
-                    eprintln!("error (add): {e}");
-                    std::process::exit(1);
-                }
-            }
-            PyCommands::Remove { package } => {
-                if let Err(e) = py::remove_package(&package) {
-                    eprintln!("error (remove): {e}");
-                    std::process::exit(1);
-                }
+        Commands::Clean { yes } => report("clean", clean::clean(yes, !ci)),
+        Commands::Config { sub } => match sub {
+            ConfigCommands::Get { key } => report("config get", config_edit::get(&key)),
+            ConfigCommands::Set { key, value } => {
+                report("config set", config_edit::set(&key, &value))
             }
         },
+        Commands::Py { sub } => match sub {
+            PyCommands::Init => report("init", py::init()),
+            PyCommands::Add {
+                package,
+                user: false,
+            } => with_config("add", preloaded, |config| py::add_package(config, &package)),
+            PyCommands::Add {
+                package,
+                user: true,
+            } => report("add", py::add_user_package(&package)),
+            PyCommands::Remove { package } => with_config("remove", preloaded, |config| {
+                py::remove_package(config, &package)
+            }),
+            PyCommands::Upgrade { package } => with_config("upgrade", preloaded, |config| {
+                py::upgrade_package(config, &package)
+            }),
+            PyCommands::Sync => report("sync", py::sync()),
+            PyCommands::List => report("list", py::list_packages()),
+            PyCommands::Freeze => report("freeze", py::freeze()),
+            PyCommands::Lock => report("lock", py::lock()),
+            PyCommands::Shell => report("shell", py::shell()),
+        },
         Commands::Js { sub } => match sub {
-            JsCommands::Init => {
-                if let Err(e) = js::init() {
-                    eprintln!("error (js init): {e}");
-                    std::process::exit(1);
-                }
-            }
-            JsCommands::Add { package } => {
-                if let Err(e) = js::add_package(&package) {
-                    eprintln!("error (js add): {e}");
-                    std::process::exit(1);
-                }
-            }
-            JsCommands::Remove { package } => {
-                if let Err(e) = js::remove_package(&package) {
-                    eprintln!("error (js remove): {e}");
-                    std::process::exit(1);
-                }
+            JsCommands::Init => report("js init", js::init()),
+            JsCommands::Add {
+                package,
+                filter,
+                frozen,
+            } => with_config("js add", preloaded, |config| {
+                js::add_package(config, package.as_deref(), filter.as_deref(), frozen)
+            }),
+            JsCommands::Install {
+                production,
+                only,
+                filter,
+                frozen,
+            } => {
+                let scope = if production {
+                    js::DependencyScope::Production
+                } else if only.is_some() {
+                    js::DependencyScope::Dev
+                } else {
+                    js::DependencyScope::All
+                };
+                with_config("js install", preloaded, |config| {
+                    js::install_dependencies(config, scope, filter.as_deref(), frozen)
+                })
             }
-            JsCommands::Run { script, args } => {
-                if let Err(e) = js::run_script(&script, &args) {
-                    eprintln!("error (js run): {e}");
-                    std::process::exit(1);
-                }
+            JsCommands::Remove { package, filter } => {
+                report("js remove", js::remove_package(&package, filter.as_deref()))
             }
+            JsCommands::Upgrade { package } => with_config("js upgrade", preloaded, |config| {
+                js::upgrade_package(config, &package)
+            }),
+            JsCommands::List => report("js list", js::list_packages()),
+            JsCommands::Run {
+                script,
+                filter,
+                args,
+            } => report("js run", js::run_script(&script, &args, filter.as_deref())),
         },
         Commands::Dart { sub } => match sub {
-            DartCommands::Init => {
-                if let Err(e) = dart::init() {
-                    eprintln!("error (dart init): {e}");
-                    std::process::exit(1);
-                }
-            }
-            DartCommands::Add { packages } => {
-                if let Err(e) = dart::add_packages(&packages) {
-                    eprintln!("error (dart add): {e}");
-                    std::process::exit(1);
-                }
-            }
+            DartCommands::Init => report("dart init", dart::init()),
+            DartCommands::Add { packages } => report("dart add", dart::add_packages(&packages)),
             DartCommands::Remove { packages } => {
-                if let Err(e) = dart::remove_packages(&packages) {
-                    eprintln!("error (dart remove): {e}");
-                    std::process::exit(1);
-                }
+                report("dart remove", dart::remove_packages(&packages))
+            }
+        },
+        Commands::Rb { sub } => match sub {
+            RbCommands::Init => report("rb init", ruby::init()),
+            RbCommands::Add { gem } => report("rb add", ruby::add_package(&gem)),
+            RbCommands::Remove { gem } => report("rb remove", ruby::remove_package(&gem)),
+            RbCommands::Run { script, args } => report("rb run", ruby::run_script(&script, &args)),
+        },
+        Commands::Php { sub } => match sub {
+            PhpCommands::Init => report("php init", php::init()),
+            PhpCommands::Add { package } => report("php add", php::add_package(&package)),
+            PhpCommands::Remove { package } => report("php remove", php::remove_package(&package)),
+        },
+        Commands::Java { sub } => match sub {
+            JavaCommands::Init { maven, .. } => {
+                let tool = if maven {
+                    java::BuildTool::Maven
+                } else {
+                    java::BuildTool::Gradle
+                };
+                report("java init", java::init(tool))
             }
+            JavaCommands::Add { coordinate } => report("java add", java::add_package(&coordinate)),
+            JavaCommands::Run { task, args } => report("java run", java::run_script(&task, &args)),
         },
-        Commands::Upgrade => {
-            if let Err(e) = upgrade::upgrade() {
-                eprintln!("error (upgrade): {e}");
-                std::process::exit(1);
+        Commands::Dotnet { sub } => match sub {
+            DotnetCommands::Init => report("dotnet init", dotnet::init()),
+            DotnetCommands::Add { package } => report("dotnet add", dotnet::add_package(&package)),
+            DotnetCommands::Remove { package } => {
+                report("dotnet remove", dotnet::remove_package(&package))
             }
+            DotnetCommands::Run { args } => report("dotnet run", dotnet::run_script(&args)),
+        },
+        Commands::Upgrade {
+            check,
+            refresh,
+            pre,
+            repo,
+            from,
+        } => {
+            let options = upgrade::UpgradeOptions {
+                offline: cli.offline,
+                check,
+                refresh,
+                prerelease: pre || upgrade::prerelease_from_env(),
+                repo,
+                from,
+            };
+            report("upgrade", upgrade::upgrade(&options))
+        }
+        Commands::Version => report("version", version::print_version(output)),
+        Commands::Man { out } => {
+            report("man", man::write_man_pages(Cli::command(), out.as_deref()))
         }
     }
 }
+
+/// Process arguments with a config `aliases:` entry expanded in place of the
+/// subcommand. The config is only read when the token isn't a built-in, and
+/// is returned so the command doesn't read it again.
+fn expand_args(args: Vec<OsString>) -> Result<(Vec<OsString>, Option<LoadedProjectConfig>)> {
+    let builtins: Vec<String> = Cli::command()
+        .get_subcommands()
+        .flat_map(|sub| std::iter::once(sub.get_name()).chain(sub.get_all_aliases()))
+        .chain(std::iter::once("help"))
+        .map(str::to_string)
+        .collect();
+    let needs_config = subcommand_index(&args)
+        .and_then(|index| args[index].to_str())
+        .is_some_and(|token| !builtins.iter().any(|builtin| builtin == token));
+    if !needs_config {
+        return Ok((args, None));
+    }
+    let Some(config) = load_project_config()? else {
+        return Ok((args, None));
+    };
+    let args = expand_aliases(args, &config.data.aliases, &builtins)?;
+    Ok((args, Some(config)))
+}
+
+/// Load the project config once and hand it to a command, so every step of
+/// that command sees the same parsed file. A config alias expansion already
+/// loaded (`preloaded`) is used as is.
+fn with_config<F>(label: &str, preloaded: Option<LoadedProjectConfig>, command: F) -> ExitCode
+where
+    F: FnOnce(Option<&LoadedProjectConfig>) -> Result<()>,
+{
+    let result = match preloaded {
+        Some(config) => command(Some(&config)),
+        None => load_project_config().and_then(|config| command(config.as_ref())),
+    };
+    report(label, result)
+}
+
+/// Print a command failure and translate it into the process exit code.
+fn report(label: &str, result: Result<()>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error ({label}): {e}");
+            ExitCode::from(exit_code_for(&e))
+        }
+    }
+}
+
+/// Missing config and undefined scripts get their own codes, failed child
+/// processes propagate their exit code, and anything else is a generic failure.
+fn exit_code_for(err: &anyhow::Error) -> u8 {
+    match QbitError::find(err) {
+        Some(QbitError::NoConfig { .. }) => EXIT_NO_CONFIG,
+        Some(QbitError::ScriptNotFound { .. }) => EXIT_NOT_DEFINED,
+        Some(QbitError::Interrupted) => EXIT_INTERRUPTED,
+        Some(QbitError::CommandFailed {
+            code: Some(code), ..
+        }) => u8::try_from(*code)
+            .ok()
+            .filter(|code| *code != 0)
+            .unwrap_or(EXIT_FAILURE),
+        _ => EXIT_FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serial_test::serial;
+
+    use super::*;
+    use crate::config::CONFIG_READS;
+    use crate::utils::test_env::{CwdGuard, EnvGuard};
+
+    fn run_args(args: &[&str]) -> ExitCode {
+        run_with_args(args.iter().map(OsString::from).collect())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn a_multi_step_run_reads_the_config_once() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "aliases:\n  ci: run build\nscripts:\n  build:\n    - \"true\"\n    - \"true\"\n    - \"true\"\n",
+        )
+        .expect("write qbit.yml");
+        let _cwd = CwdGuard::set(tmp.path());
+
+        for args in [["qbit", "run", "build"].as_slice(), &["qbit", "ci"]] {
+            CONFIG_READS.with(|reads| reads.set(0));
+            assert_eq!(run_args(args), ExitCode::SUCCESS);
+            assert_eq!(CONFIG_READS.with(|reads| reads.get()), 1);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn install_fallback_reads_the_config_once_across_managers() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "install:\n  foo:\n    identifiers:\n      default: foo-pkg\n",
+        )
+        .expect("write qbit.yml");
+        let fakebin = tmp.path().join("bin");
+        fs::create_dir(&fakebin).expect("create fakebin");
+        // apt-get fails, so --try-all falls through to dnf.
+        for (tool, code) in [("apt-get", 1), ("dnf", 0)] {
+            let path = fakebin.join(tool);
+            fs::write(&path, format!("#!/bin/sh\nexit {code}\n")).expect("write fake tool");
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("chmod");
+        }
+        let _path = EnvGuard::set("PATH", &fakebin);
+        let _manager = EnvGuard::remove("QBIT_PACKAGE_MANAGER");
+        let _version_manager = EnvGuard::remove("QBIT_VERSION_MANAGER");
+        let _cwd = CwdGuard::set(tmp.path());
+
+        CONFIG_READS.with(|reads| reads.set(0));
+        let code = run_args(&["qbit", "install", "foo", "--try-all", "--yes", "--no-sudo"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(CONFIG_READS.with(|reads| reads.get()), 1);
+    }
+}
@@ -1,9 +1,14 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Context, Result};
-use serde::Deserialize;
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Deserializer};
+
+use crate::os::upgrade::parse_version;
+use crate::utils::paths::expand_path;
+use crate::utils::shell::{CommandStep, PYTHON_INTERPRETER};
 
 const CONFIG_CANDIDATES: &[(&str, ConfigFormat)] = &[
     ("qbit.yml", ConfigFormat::Yaml),
@@ -28,6 +33,67 @@ impl LoadedProjectConfig {
         self.data.scripts.get(name)
     }
 
+    /// The steps of a script, ready to run. Each step's environment is the
+    /// project-wide `env` with the script's and then the step's own `env`
+    /// layered on top; all of them override the inherited process env.
+    pub fn script_steps(&self, name: &str) -> Option<Vec<CommandStep>> {
+        let script = self.script(name)?;
+        Some(self.steps_for(script, None))
+    }
+
+    /// The `postinstall` steps of `target`'s install entry, run from the
+    /// directory holding the config file. `None` when the entry has none.
+    pub fn postinstall_steps(&self, target: &str) -> Result<Option<Vec<CommandStep>>> {
+        let Some((name, list)) = self
+            .install_target_case_insensitive(target)
+            .and_then(|(name, spec)| Some((name, spec.postinstall()?)))
+        else {
+            return Ok(None);
+        };
+        if let Some(problem) = list.problem() {
+            bail!(
+                "`postinstall` of install entry `{name}` in {} {problem}. Give it at least one command or remove it.",
+                self.path.display()
+            );
+        }
+        let root = self.path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        Ok(Some(self.steps_for(list, root)))
+    }
+
+    /// Flatten `list` over the project-wide env, starting in `cwd`.
+    fn steps_for(&self, list: &CommandList, cwd: Option<&Path>) -> Vec<CommandStep> {
+        let base = CommandStep {
+            env: self
+                .data
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            cwd: cwd.map(Path::to_path_buf),
+            ..CommandStep::default()
+        };
+        let root = self.path.parent().unwrap_or_else(|| Path::new(""));
+        let mut steps = Vec::new();
+        list.collect_steps(&base, root, &mut steps);
+        steps
+    }
+
+    /// Reject scripts that would only fail later with a confusing shell error:
+    /// blank commands and empty command lists.
+    pub fn validate(&self) -> Result<()> {
+        let mut names: Vec<&String> = self.data.scripts.keys().collect();
+        names.sort();
+        for name in names {
+            if let Some(problem) = self.data.scripts[name].problem() {
+                bail!(
+                    "Script `{name}` in {} {problem}. Give it at least one command or remove it.",
+                    self.path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn install_target_case_insensitive(&self, name: &str) -> Option<(&str, &InstallSpec)> {
         self.data
             .install
@@ -43,20 +109,265 @@ pub struct ProjectConfig {
     pub scripts: HashMap<String, CommandList>,
     #[serde(default)]
     pub install: HashMap<String, InstallSpec>,
+    #[serde(default)]
+    pub python: PythonConfig,
+    #[serde(default)]
+    pub js: JsConfig,
+    /// Package manager pinned by `install.manager`, pulled out of the
+    /// `install` map after parsing.
+    #[serde(skip)]
+    pub install_manager: Option<String>,
+    /// Extra config files whose `scripts`/`install` entries are merged in.
+    /// Paths are relative to the including file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Environment variables set for every script.
+    #[serde(default, deserialize_with = "env_map")]
+    pub env: HashMap<String, String>,
+    /// Minimum qbit version able to understand this file (`qbit_version: 0.2.0`).
+    #[serde(default)]
+    pub qbit_version: Option<String>,
+    /// Subcommand shortcuts (`up: "run build"`) expanded before argument parsing.
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+}
+
+/// Settings under the `js:` key.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct JsConfig {
+    /// Registry URL passed to the package manager for add/upgrade.
+    #[serde(default)]
+    pub registry: Option<String>,
+}
+
+/// Settings under the `python:` key.
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct PythonConfig {
+    #[serde(default)]
+    pub requirements_mode: RequirementsMode,
+}
+
+/// How `qbit py add/remove/upgrade` keep requirements.txt in sync.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RequirementsMode {
+    /// Overwrite requirements.txt with the full `pip freeze` output.
+    #[default]
+    Freeze,
+    /// Only touch the line for the package being changed, keeping comments and order.
+    Managed,
 }
 
+/// A script: one command, a list of steps, or a table that wraps either with
+/// shared settings. Every form is plain data, so YAML and TOML spell it the
+/// same way (TOML writes a list of step tables as `[[scripts.<name>]]`).
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub enum CommandList {
     Single(String),
-    Multiple(Vec<String>),
+    Multiple(Vec<ScriptStep>),
+    /// `{ shell: <interpreter>, run: <script>, env, cwd, timeout }`: the
+    /// multi-line script is saved to a temp file and run by the interpreter.
+    Interpreted {
+        shell: String,
+        #[serde(alias = "cmd")]
+        run: String,
+        #[serde(flatten)]
+        settings: StepSettings,
+    },
+    /// `{ python: <script>, env, cwd, timeout }`, run like `shell: python`.
+    Python {
+        python: String,
+        #[serde(flatten)]
+        settings: StepSettings,
+    },
+    /// `{ run|cmd|cmds: <command or list>, env, cwd, timeout }`
+    Detailed {
+        #[serde(alias = "cmd", alias = "cmds")]
+        run: Box<CommandList>,
+        #[serde(flatten)]
+        settings: StepSettings,
+    },
+}
+
+/// One entry in a script's step list.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ScriptStep {
+    Command(String),
+    /// `{ cmd: <command>, env, cwd, timeout }`
+    Detailed {
+        #[serde(alias = "run")]
+        cmd: String,
+        #[serde(flatten)]
+        settings: StepSettings,
+    },
+}
+
+/// Settings a script or step can carry. Inner values win over outer ones.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StepSettings {
+    #[serde(default, deserialize_with = "env_map")]
+    pub env: HashMap<String, String>,
+    /// Working directory, relative to the directory holding the config file.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// Seconds a step may run before it is killed.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+impl ScriptStep {
+    fn command(&self) -> &str {
+        match self {
+            ScriptStep::Command(cmd) | ScriptStep::Detailed { cmd, .. } => cmd,
+        }
+    }
+}
+
+impl StepSettings {
+    fn layer_over(&self, base: &CommandStep, root: &Path) -> CommandStep {
+        let mut step = base.clone();
+        step.env.extend(
+            self.env
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone())),
+        );
+        if let Some(cwd) = &self.cwd {
+            // `~/proj` and `$HOME/x` expand like other config paths; an
+            // absolute result replaces the enclosing directory.
+            let cwd = cwd.to_str().map_or_else(|| cwd.clone(), expand_path);
+            step.cwd = Some(base.cwd.as_deref().unwrap_or(root).join(cwd));
+        }
+        if let Some(seconds) = self.timeout {
+            step.timeout = Some(Duration::from_secs(seconds));
+        }
+        step
+    }
+}
+
+/// Env values may be written as numbers or booleans (`PORT: 3000`); they are
+/// passed to the shell as their string form.
+fn env_map<'de, D>(deserializer: D) -> std::result::Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        Text(String),
+        Bool(bool),
+        Int(i64),
+        Float(f64),
+    }
+
+    let raw = HashMap::<String, Scalar>::deserialize(deserializer)?;
+    Ok(raw
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Scalar::Text(text) => text,
+                Scalar::Bool(flag) => flag.to_string(),
+                Scalar::Int(number) => number.to_string(),
+                Scalar::Float(number) => number.to_string(),
+            };
+            (key, value)
+        })
+        .collect())
 }
 
 impl CommandList {
     pub fn commands(&self) -> Vec<String> {
         match self {
             CommandList::Single(cmd) => vec![cmd.clone()],
-            CommandList::Multiple(cmds) => cmds.clone(),
+            CommandList::Multiple(steps) => steps
+                .iter()
+                .map(|step| step.command().to_string())
+                .collect(),
+            CommandList::Interpreted { run, .. } => vec![run.clone()],
+            CommandList::Python { python, .. } => vec![python.clone()],
+            CommandList::Detailed { run, .. } => run.commands(),
+        }
+    }
+
+    /// Flatten into executable steps, layering each level's settings over
+    /// `base`. Relative `cwd`s resolve against the enclosing one, starting at
+    /// `root`.
+    fn collect_steps(&self, base: &CommandStep, root: &Path, out: &mut Vec<CommandStep>) {
+        match self {
+            CommandList::Single(cmd) => out.push(CommandStep {
+                command: cmd.clone(),
+                ..base.clone()
+            }),
+            CommandList::Multiple(steps) => {
+                for step in steps {
+                    match step {
+                        ScriptStep::Command(cmd) => out.push(CommandStep {
+                            command: cmd.clone(),
+                            ..base.clone()
+                        }),
+                        ScriptStep::Detailed { cmd, settings } => out.push(CommandStep {
+                            command: cmd.clone(),
+                            ..settings.layer_over(base, root)
+                        }),
+                    }
+                }
+            }
+            CommandList::Interpreted {
+                shell,
+                run,
+                settings,
+            } => out.push(CommandStep {
+                command: run.clone(),
+                interpreter: Some(shell.trim().to_string()),
+                ..settings.layer_over(base, root)
+            }),
+            CommandList::Python { python, settings } => out.push(CommandStep {
+                command: python.clone(),
+                interpreter: Some(PYTHON_INTERPRETER.to_string()),
+                ..settings.layer_over(base, root)
+            }),
+            CommandList::Detailed { run, settings } => {
+                run.collect_steps(&settings.layer_over(base, root), root, out)
+            }
+        }
+    }
+
+    fn problem(&self) -> Option<&'static str> {
+        match self {
+            CommandList::Single(cmd) if cmd.trim().is_empty() => Some("has an empty command"),
+            CommandList::Multiple(steps) if steps.is_empty() => Some("has an empty command list"),
+            CommandList::Multiple(steps)
+                if steps.iter().any(|step| step.command().trim().is_empty()) =>
+            {
+                Some("contains an empty command")
+            }
+            CommandList::Multiple(steps)
+                if steps.iter().any(|step| match step {
+                    ScriptStep::Detailed { settings, .. } => settings.timeout == Some(0),
+                    ScriptStep::Command(_) => false,
+                }) =>
+            {
+                Some("has a step with `timeout: 0`")
+            }
+            CommandList::Interpreted { shell, .. } if shell.trim().is_empty() => {
+                Some("has an empty `shell`")
+            }
+            CommandList::Interpreted { run: script, .. }
+            | CommandList::Python { python: script, .. }
+                if script.trim().is_empty() =>
+            {
+                Some("has an empty script")
+            }
+            CommandList::Interpreted { settings, .. }
+            | CommandList::Python { settings, .. }
+            | CommandList::Detailed { settings, .. }
+                if settings.timeout == Some(0) =>
+            {
+                Some("has `timeout: 0`")
+            }
+            CommandList::Detailed { run, .. } => run.problem(),
+            _ => None,
         }
     }
 }
@@ -70,9 +381,29 @@ pub enum InstallSpec {
         version: Option<String>,
         #[serde(default)]
         identifiers: HashMap<String, String>,
+        /// Extra instructions printed with the install plan (`note:` or `hint:`).
+        #[serde(default, alias = "hint")]
+        note: Option<String>,
+        /// Commands run after the target is installed, like a script.
+        #[serde(default)]
+        postinstall: Option<CommandList>,
+        /// Whether `identifiers.winget` is a package ID or a display name.
+        #[serde(default)]
+        winget_match: WingetMatch,
     },
 }
 
+/// How winget looks up an install entry's identifier.
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WingetMatch {
+    /// `--id`: the identifier is an exact package ID such as `Git.Git`.
+    #[default]
+    Id,
+    /// `--name`: the identifier is the display name shown by `winget search`.
+    Name,
+}
+
 impl InstallSpec {
     pub fn version(&self) -> Option<&str> {
         self.configured_version()
@@ -85,6 +416,27 @@ impl InstallSpec {
         }
     }
 
+    pub fn note(&self) -> Option<&str> {
+        match self {
+            InstallSpec::Identifier(_) => None,
+            InstallSpec::Detailed { note, .. } => note.as_deref(),
+        }
+    }
+
+    pub fn postinstall(&self) -> Option<&CommandList> {
+        match self {
+            InstallSpec::Identifier(_) => None,
+            InstallSpec::Detailed { postinstall, .. } => postinstall.as_ref(),
+        }
+    }
+
+    pub fn winget_match(&self) -> WingetMatch {
+        match self {
+            InstallSpec::Identifier(_) => WingetMatch::Id,
+            InstallSpec::Detailed { winget_match, .. } => *winget_match,
+        }
+    }
+
     pub fn global_identifier(&self) -> Option<&str> {
         match self {
             InstallSpec::Identifier(identifier) => Some(identifier.as_str()),
@@ -109,30 +461,181 @@ pub fn load_project_config() -> Result<Option<LoadedProjectConfig>> {
 }
 
 pub fn load_project_config_from_dir(base_dir: &Path) -> Result<Option<LoadedProjectConfig>> {
-    for (file, format) in CONFIG_CANDIDATES {
-        let path = base_dir.join(file);
-        if !path.exists() {
-            continue;
+    let Some((path, format)) = find_project_config_file(base_dir) else {
+        return Ok(None);
+    };
+    let mut data = parse_config_file(&path, &format)?;
+    let mut chain = vec![canonical_or_self(&path)];
+    merge_includes(&mut data, &path, &mut chain)?;
+    Ok(Some(LoadedProjectConfig { path, data }))
+}
+
+/// The config file in `base_dir` qbit would load, without reading it.
+pub fn find_project_config_file(base_dir: &Path) -> Option<(PathBuf, ConfigFormat)> {
+    CONFIG_CANDIDATES
+        .iter()
+        .map(|(file, format)| (base_dir.join(file), format.clone()))
+        .find(|(path, _)| path.exists())
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of config files read on this thread, so tests can assert that
+    /// commands share one loaded config instead of re-reading it.
+    pub(crate) static CONFIG_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+fn parse_config_file(path: &Path, format: &ConfigFormat) -> Result<ProjectConfig> {
+    #[cfg(test)]
+    CONFIG_READS.with(|reads| reads.set(reads.get() + 1));
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading project config at {}", path.display()))?;
+    let config = match format {
+        ConfigFormat::Yaml => parse_yaml_str(&content).map_err(|err| {
+            let location = err.location().map(|at| (at.line(), at.column()));
+            // serde_yaml ends its message with the location; it moves up front.
+            let problem = match location {
+                Some((line, column)) => {
+                    err.to_string()
+                        .replacen(&format!(" at line {line} column {column}"), "", 1)
+                }
+                None => err.to_string(),
+            };
+            config_parse_error(path, "YAML", location, &problem)
+        })?,
+        ConfigFormat::Toml => parse_toml_str(&content).map_err(|err| {
+            let location = err.span().map(|span| line_and_column(&content, span.start));
+            let problem = err.message().lines().collect::<Vec<_>>().join("; ");
+            config_parse_error(path, "TOML", location, &problem)
+        })?,
+    };
+    if let Some(required) = &config.qbit_version {
+        ensure_compatible(required, env!("CARGO_PKG_VERSION"), path)?;
+    }
+    Ok(config)
+}
+
+/// `parsing YAML config at qbit.yml, line 3 column 2: did not find expected
+/// key`. The parser's location is part of the message itself because qbit
+/// prints only the outermost error.
+fn config_parse_error(
+    path: &Path,
+    format: &str,
+    location: Option<(usize, usize)>,
+    problem: &str,
+) -> anyhow::Error {
+    match location {
+        Some((line, column)) => anyhow!(
+            "parsing {format} config at {}, line {line} column {column}: {problem}",
+            path.display()
+        ),
+        None => anyhow!("parsing {format} config at {}: {problem}", path.display()),
+    }
+}
+
+/// 1-based line and column of byte `offset` in `content`.
+fn line_and_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Refuse configs that declare a newer minimum qbit version than this binary.
+fn ensure_compatible(required: &str, current: &str, path: &Path) -> Result<()> {
+    let Ok(minimum) = parse_version(required) else {
+        bail!(
+            "Invalid qbit_version `{required}` in {}: expected a semantic version like 0.2.0",
+            path.display()
+        );
+    };
+    let current = parse_version(current).context("parsing current qbit version")?;
+    if current < minimum {
+        bail!(
+            "{} requires qbit >= {minimum}, you have {current}; run `qbit upgrade`",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Load every file listed under `include` (recursively) and merge its
+/// `scripts`/`install` maps into `config`. Entries already present win, so the
+/// including file overrides its libraries and earlier includes override later ones.
+fn merge_includes(config: &mut ProjectConfig, path: &Path, chain: &mut Vec<PathBuf>) -> Result<()> {
+    let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for raw in std::mem::take(&mut config.include) {
+        let include_path = base_dir.join(expand_path(&raw));
+        if !include_path.exists() {
+            bail!(
+                "Included config `{raw}` not found (resolved to {}, included from {})",
+                include_path.display(),
+                path.display()
+            );
         }
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("reading project config at {}", path.display()))?;
-        let data = match format {
-            ConfigFormat::Yaml => parse_yaml_str(&content)
-                .with_context(|| format!("parsing YAML config at {}", path.display()))?,
-            ConfigFormat::Toml => parse_toml_str(&content)
-                .with_context(|| format!("parsing TOML config at {}", path.display()))?,
+
+        let canonical = canonical_or_self(&include_path);
+        if chain.contains(&canonical) {
+            let cycle = chain
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            bail!("Config include cycle detected: {cycle}");
+        }
+
+        let format = if include_path.extension().is_some_and(|ext| ext == "toml") {
+            ConfigFormat::Toml
+        } else {
+            ConfigFormat::Yaml
         };
-        return Ok(Some(LoadedProjectConfig { path, data }));
+        let mut included = parse_config_file(&include_path, &format)?;
+        chain.push(canonical);
+        merge_includes(&mut included, &include_path, chain)?;
+        chain.pop();
+
+        for (name, script) in included.scripts {
+            config.scripts.entry(name).or_insert(script);
+        }
+        for (name, spec) in included.install {
+            config.install.entry(name).or_insert(spec);
+        }
+        for (key, value) in included.env {
+            config.env.entry(key).or_insert(value);
+        }
+        for (name, expansion) in included.aliases {
+            config.aliases.entry(name).or_insert(expansion);
+        }
+        if config.install_manager.is_none() {
+            config.install_manager = included.install_manager;
+        }
     }
-    Ok(None)
+    Ok(())
 }
 
-pub(crate) fn parse_yaml_str(content: &str) -> Result<ProjectConfig> {
-    Ok(serde_yaml::from_str(content)?)
+fn canonical_or_self(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
 }
 
-pub(crate) fn parse_toml_str(content: &str) -> Result<ProjectConfig> {
-    Ok(toml::from_str(content)?)
+pub(crate) fn parse_yaml_str(content: &str) -> Result<ProjectConfig, serde_yaml::Error> {
+    serde_yaml::from_str(content).map(extract_install_manager)
+}
+
+pub(crate) fn parse_toml_str(content: &str) -> Result<ProjectConfig, toml::de::Error> {
+    toml::from_str(content).map(extract_install_manager)
+}
+
+/// `install.manager: <name>` shares the `install` map with targets, so a plain
+/// string under the `manager` key is treated as the pinned package manager.
+fn extract_install_manager(mut config: ProjectConfig) -> ProjectConfig {
+    if let Some(InstallSpec::Identifier(name)) = config.install.get("manager") {
+        config.install_manager = Some(name.trim().to_string());
+        config.install.remove("manager");
+    }
+    config
 }
 
 #[cfg(test)]
@@ -140,6 +643,30 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    #[test]
+    fn parse_errors_name_the_line_and_column() {
+        let tmp = tempdir().expect("temp dir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "scripts:\n  build: echo hi\n bad: 1\n",
+        )
+        .expect("write qbit.yml");
+        let err = load_project_config_from_dir(tmp.path()).expect_err("malformed YAML");
+        let message = err.to_string();
+        assert!(message.contains("qbit.yml, line 3 column 2: "), "{message}");
+        assert!(message.contains("did not find expected key"), "{message}");
+        assert!(!message.contains("at line 3 column 2"), "{message}");
+
+        fs::remove_file(tmp.path().join("qbit.yml")).expect("remove qbit.yml");
+        fs::write(tmp.path().join("qbit.toml"), "[scripts]\nbuild = \n").expect("write qbit.toml");
+        let err = load_project_config_from_dir(tmp.path()).expect_err("malformed TOML");
+        let message = err.to_string();
+        assert!(
+            message.contains("qbit.toml, line 2 column 9: invalid string"),
+            "{message}"
+        );
+    }
+
     #[test]
     fn install_lookup_is_case_insensitive() {
         let cfg = LoadedProjectConfig {
@@ -152,6 +679,7 @@ mod tests {
                 )]
                 .into_iter()
                 .collect(),
+                ..Default::default()
             },
         };
 
@@ -168,6 +696,9 @@ mod tests {
             identifiers: [("Winget".to_string(), "Python.Python.3.12".to_string())]
                 .into_iter()
                 .collect(),
+            note: None,
+            postinstall: None,
+            winget_match: WingetMatch::Id,
         };
 
         assert_eq!(spec.identifier("winget"), Some("Python.Python.3.12"));
@@ -202,7 +733,7 @@ install:
             .clone()
         {
             CommandList::Single(cmd) => assert_eq!(cmd, "echo hi"),
-            CommandList::Multiple(_) => panic!("expected single command"),
+            _ => panic!("expected single command"),
         }
 
         assert_eq!(
@@ -255,7 +786,7 @@ brew = "python@3.12"
             .clone()
         {
             CommandList::Single(cmd) => assert_eq!(cmd, "echo hi"),
-            CommandList::Multiple(_) => panic!("expected single command"),
+            _ => panic!("expected single command"),
         }
 
         assert_eq!(
@@ -279,6 +810,71 @@ brew = "python@3.12"
         assert_eq!(python_spec.identifier("brew"), Some("python@3.12"));
     }
 
+    #[test]
+    fn includes_merge_scripts_with_local_definitions_winning() {
+        let tmp = tempdir().expect("temp dir");
+        fs::create_dir_all(tmp.path().join("shared")).expect("shared dir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "include: [\"shared/qbit.tasks.yml\"]\nscripts:\n  build: \"make local\"\n",
+        )
+        .expect("write main");
+        fs::write(
+            tmp.path().join("shared/qbit.tasks.yml"),
+            "include: [\"lint.toml\"]\nscripts:\n  build: \"make shared\"\n  test: \"make test\"\n",
+        )
+        .expect("write tasks");
+        fs::write(
+            tmp.path().join("shared/lint.toml"),
+            "[scripts]\nlint = \"cargo clippy\"\n",
+        )
+        .expect("write lint");
+
+        let loaded = load_project_config_from_dir(tmp.path())
+            .expect("load")
+            .expect("config present");
+        let command = |name: &str| loaded.script(name).expect(name).commands();
+        assert_eq!(command("build"), vec!["make local".to_string()]);
+        assert_eq!(command("test"), vec!["make test".to_string()]);
+        assert_eq!(command("lint"), vec!["cargo clippy".to_string()]);
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let tmp = tempdir().expect("temp dir");
+        fs::write(tmp.path().join("qbit.yml"), "include: [\"a.yml\"]\n").expect("write main");
+        fs::write(tmp.path().join("a.yml"), "include: [\"qbit.yml\"]\n").expect("write a");
+
+        let err = load_project_config_from_dir(tmp.path()).expect_err("cycle must fail");
+        assert!(err.to_string().contains("include cycle"), "{err}");
+    }
+
+    #[test]
+    fn validate_rejects_empty_commands_naming_script_and_path() {
+        let tmp = tempdir().expect("temp dir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "scripts:\n  ok: \"echo ok\"\n  build: \"\"\n",
+        )
+        .expect("write yaml");
+        let loaded = load_project_config_from_dir(tmp.path())
+            .expect("load")
+            .expect("config present");
+        let err = loaded.validate().expect_err("empty command must fail");
+        let message = err.to_string();
+        assert!(message.contains("Script `build`"), "{message}");
+        assert!(message.contains("qbit.yml"), "{message}");
+        assert!(message.contains("empty command"), "{message}");
+
+        let parsed = parse_yaml_str("scripts:\n  build: []\n").expect("yaml parse");
+        let loaded = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: parsed,
+        };
+        let err = loaded.validate().expect_err("empty list must fail");
+        assert!(err.to_string().contains("empty command list"));
+    }
+
     #[test]
     fn parse_yaml_str_handles_single_and_multiple_scripts() {
         let yaml = r#"scripts:
@@ -298,6 +894,31 @@ brew = "python@3.12"
         );
     }
 
+    #[test]
+    fn requirements_mode_defaults_to_freeze_and_parses_managed() {
+        let parsed = parse_yaml_str("scripts: {}\n").expect("yaml parse");
+        assert_eq!(parsed.python.requirements_mode, RequirementsMode::Freeze);
+
+        let parsed = parse_yaml_str("python:\n  requirements_mode: managed\n").expect("yaml parse");
+        assert_eq!(parsed.python.requirements_mode, RequirementsMode::Managed);
+    }
+
+    #[test]
+    fn install_manager_is_not_an_install_target() {
+        let parsed =
+            parse_yaml_str("install:\n  manager: dnf\n  node: \"nodejs\"\n").expect("yaml parse");
+        assert_eq!(parsed.install_manager.as_deref(), Some("dnf"));
+        assert!(!parsed.install.contains_key("manager"));
+        assert!(parsed.install.contains_key("node"));
+    }
+
+    #[test]
+    fn parses_js_registry_from_toml() {
+        let parsed =
+            parse_toml_str("[js]\nregistry = \"https://npm.acme.test\"\n").expect("toml parse");
+        assert_eq!(parsed.js.registry.as_deref(), Some("https://npm.acme.test"));
+    }
+
     #[test]
     fn parse_toml_str_handles_install_identifier_and_detailed_forms() {
         let toml = r#"[install]
@@ -323,4 +944,199 @@ default = "python"
         assert_eq!(python.identifier("winget"), Some("Python.Python.3.12"));
         assert_eq!(python.identifier("default"), Some("python"));
     }
+
+    #[test]
+    fn newer_qbit_version_requirement_prompts_upgrade() {
+        let tmp = tempdir().expect("temp dir");
+        fs::write(
+            tmp.path().join("qbit.yml"),
+            "qbit_version: \"999.0.0\"\nscripts:\n  build: \"echo build\"\n",
+        )
+        .expect("write yaml");
+
+        let err = load_project_config_from_dir(tmp.path()).expect_err("binary too old");
+        let message = format!("{err:#}");
+        assert!(message.contains("requires qbit >= 999.0.0"), "{message}");
+        assert!(
+            message.contains(&format!("you have {}", env!("CARGO_PKG_VERSION"))),
+            "{message}"
+        );
+        assert!(message.contains("run `qbit upgrade`"), "{message}");
+    }
+
+    #[test]
+    fn satisfied_or_invalid_qbit_version() {
+        let path = Path::new("qbit.yml");
+        ensure_compatible("0.1.0", "0.1.0", path).expect("equal version is fine");
+        ensure_compatible("v0.1", "0.2.0", path).expect_err("not a full semver");
+        let err = ensure_compatible("latest", "0.2.0", path).expect_err("invalid version");
+        assert!(err.to_string().contains("Invalid qbit_version `latest`"));
+    }
+
+    #[test]
+    fn script_env_layers_script_values_over_project_env() {
+        let yaml = r#"env:
+  NODE_ENV: development
+  PORT: 3000
+scripts:
+  serve:
+    run: "npm start"
+    env:
+      NODE_ENV: production
+  test: "npm test"
+"#;
+        let loaded = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: parse_yaml_str(yaml).expect("yaml parse"),
+        };
+
+        let serve = loaded.script_steps("serve").expect("serve");
+        assert_eq!(serve[0].env["NODE_ENV"], "production");
+        assert_eq!(serve[0].env["PORT"], "3000");
+        assert_eq!(
+            loaded.script("serve").expect("serve").commands(),
+            vec!["npm start".to_string()]
+        );
+        assert_eq!(
+            loaded.script_steps("test").expect("test")[0].env["NODE_ENV"],
+            "development"
+        );
+    }
+
+    #[test]
+    fn detailed_scripts_parse_the_same_from_yaml_and_toml() {
+        let yaml = r#"scripts:
+  deploy:
+    cmds:
+      - "make build"
+      - cmd: "make upload"
+        cwd: dist
+        timeout: 30
+        env:
+          TARGET: prod
+    env:
+      REGION: eu
+    cwd: app
+    timeout: 600
+  check:
+    - "cargo fmt --check"
+    - cmd: "cargo test"
+      env:
+        RUST_LOG: debug
+"#;
+        let toml = r#"
+[scripts.deploy]
+cmds = ["make build", { cmd = "make upload", cwd = "dist", timeout = 30, env = { TARGET = "prod" } }]
+env = { REGION = "eu" }
+cwd = "app"
+timeout = 600
+
+[[scripts.check]]
+cmd = "cargo fmt --check"
+
+[[scripts.check]]
+cmd = "cargo test"
+env = { RUST_LOG = "debug" }
+"#;
+        let load = |data| LoadedProjectConfig {
+            path: Path::new("project").join("qbit.yml"),
+            data,
+        };
+        let from_yaml = load(parse_yaml_str(yaml).expect("yaml parse"));
+        let from_toml = load(parse_toml_str(toml).expect("toml parse"));
+
+        for name in ["deploy", "check"] {
+            assert_eq!(
+                from_yaml.script_steps(name),
+                from_toml.script_steps(name),
+                "{name}"
+            );
+        }
+
+        let deploy = from_toml.script_steps("deploy").expect("deploy");
+        assert_eq!(deploy[0].command, "make build");
+        assert_eq!(deploy[0].cwd, Some(Path::new("project").join("app")));
+        assert_eq!(deploy[0].timeout, Some(Duration::from_secs(600)));
+        assert_eq!(deploy[0].env.get("TARGET"), None);
+        assert_eq!(
+            deploy[1].cwd,
+            Some(Path::new("project").join("app").join("dist"))
+        );
+        assert_eq!(deploy[1].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(deploy[1].env["TARGET"], "prod");
+        assert_eq!(deploy[1].env["REGION"], "eu");
+
+        let check = from_toml.script_steps("check").expect("check");
+        assert_eq!(check.len(), 2);
+        assert_eq!(check[0].cwd, None);
+        assert_eq!(check[1].env["RUST_LOG"], "debug");
+    }
+
+    #[test]
+    fn step_cwd_expands_home_and_variables() {
+        let Ok(home) = std::env::var("HOME") else {
+            return;
+        };
+        let loaded = LoadedProjectConfig {
+            path: Path::new("project").join("qbit.yml"),
+            data: parse_yaml_str(
+                "scripts:\n  deploy:\n    cwd: app\n    cmds:\n      - cmd: ls\n        cwd: ~/proj\n      - cmd: ls\n        cwd: $HOME/x\n      - cmd: ls\n        cwd: $QBIT_UNSET_TEST_VAR/y\n",
+            )
+            .expect("yaml parse"),
+        };
+        let steps = loaded.script_steps("deploy").expect("deploy");
+        assert_eq!(steps[0].cwd, Some(Path::new(&home).join("proj")));
+        assert_eq!(steps[1].cwd, Some(Path::new(&home).join("x")));
+        assert_eq!(
+            steps[2].cwd,
+            Some(
+                Path::new("project")
+                    .join("app")
+                    .join("$QBIT_UNSET_TEST_VAR/y")
+            )
+        );
+    }
+
+    #[test]
+    fn validate_rejects_zero_timeout() {
+        let loaded = LoadedProjectConfig {
+            path: PathBuf::from("qbit.toml"),
+            data: parse_toml_str("[scripts.slow]\ncmd = \"sleep 1\"\ntimeout = 0\n")
+                .expect("toml parse"),
+        };
+        let err = loaded.validate().expect_err("zero timeout");
+        assert!(err.to_string().contains("timeout: 0"), "{err}");
+    }
+
+    #[test]
+    fn interpreter_blocks_parse_into_script_file_steps() {
+        let loaded = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: parse_yaml_str(
+                "scripts:\n  list:\n    shell: bash\n    run: |\n      set -e\n      echo one\n    env:\n      MODE: ci\n  stats:\n    python: \"print(1)\"\n  empty:\n    shell: bash\n    run: \"  \"\n",
+            )
+            .expect("yaml parse"),
+        };
+
+        let steps = loaded.script_steps("list").expect("list");
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].interpreter.as_deref(), Some("bash"));
+        assert_eq!(steps[0].command, "set -e\necho one\n");
+        assert_eq!(steps[0].env.get("MODE").map(String::as_str), Some("ci"));
+        assert_eq!(steps[0].describe(), "bash script (2 lines)");
+
+        let steps = loaded.script_steps("stats").expect("stats");
+        assert_eq!(steps[0].interpreter.as_deref(), Some(PYTHON_INTERPRETER));
+
+        let toml =
+            parse_toml_str("[scripts.list]\nshell = \"bash\"\nrun = \"\"\"\necho one\n\"\"\"\n")
+                .expect("toml parse");
+        assert!(matches!(
+            toml.scripts.get("list"),
+            Some(CommandList::Interpreted { shell, .. }) if shell == "bash"
+        ));
+
+        let err = loaded.validate().expect_err("empty script");
+        assert!(err.to_string().contains("has an empty script"), "{err}");
+    }
 }
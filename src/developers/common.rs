@@ -1,16 +1,325 @@
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::find_project_config_file;
+use crate::error::QbitError;
+use crate::utils::paths::find_executable;
 
 /// Shared metadata about the project root to help language-specific managers.
-#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct ProjectContext {
     pub root: PathBuf,
 }
 
 impl ProjectContext {
-    #[allow(dead_code)]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
     pub fn from_current_dir() -> Self {
         let root = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
         Self { root }
     }
+
+    /// Ecosystems whose marker files sit in the project root, most specific
+    /// first (a Node project with a pyproject.toml is reported as Node).
+    pub fn detect_project_types(&self) -> Vec<Ecosystem> {
+        Ecosystem::ALL
+            .into_iter()
+            .filter(|ecosystem| ecosystem.is_present(&self.root))
+            .collect()
+    }
+}
+
+/// Language ecosystems qbit recognizes from their marker files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ecosystem {
+    Js,
+    Python,
+    Ruby,
+    Php,
+    Java,
+    Dotnet,
+    Dart,
+    Go,
+    Rust,
+}
+
+impl Ecosystem {
+    const ALL: [Ecosystem; 9] = [
+        Ecosystem::Js,
+        Ecosystem::Python,
+        Ecosystem::Ruby,
+        Ecosystem::Php,
+        Ecosystem::Java,
+        Ecosystem::Dotnet,
+        Ecosystem::Dart,
+        Ecosystem::Go,
+        Ecosystem::Rust,
+    ];
+
+    /// Display name, e.g. "Node" in "detected a Node project".
+    pub fn name(self) -> &'static str {
+        match self {
+            Ecosystem::Js => "Node",
+            Ecosystem::Python => "Python",
+            Ecosystem::Ruby => "Ruby",
+            Ecosystem::Php => "PHP",
+            Ecosystem::Java => "Java",
+            Ecosystem::Dotnet => ".NET",
+            Ecosystem::Dart => "Dart",
+            Ecosystem::Go => "Go",
+            Ecosystem::Rust => "Rust",
+        }
+    }
+
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Js => &["package.json"],
+            Ecosystem::Python => &["pyproject.toml", "requirements.txt"],
+            Ecosystem::Ruby => &["Gemfile"],
+            Ecosystem::Php => &["composer.json"],
+            Ecosystem::Java => &["build.gradle", "build.gradle.kts", "pom.xml"],
+            Ecosystem::Dotnet => &[],
+            Ecosystem::Dart => &["pubspec.yaml"],
+            Ecosystem::Go => &["go.mod"],
+            Ecosystem::Rust => &["Cargo.toml"],
+        }
+    }
+
+    /// Extensions of marker files whose names vary per project.
+    fn marker_extensions(self) -> &'static [&'static str] {
+        match self {
+            Ecosystem::Dotnet => &["csproj", "fsproj", "vbproj", "sln"],
+            _ => &[],
+        }
+    }
+
+    pub fn is_present(self, root: &Path) -> bool {
+        self.markers()
+            .iter()
+            .any(|marker| root.join(marker).exists())
+            || (!self.marker_extensions().is_empty() && self.has_marker_extension(root))
+    }
+
+    fn has_marker_extension(self, root: &Path) -> bool {
+        fs::read_dir(root).is_ok_and(|entries| {
+            entries.filter_map(|entry| entry.ok()).any(|entry| {
+                entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| self.marker_extensions().contains(&ext))
+            })
+        })
+    }
+
+    /// Next step for setting up qbit in a project of this kind.
+    pub fn suggestion(self) -> &'static str {
+        match self {
+            Ecosystem::Js => "try `qbit js init`",
+            Ecosystem::Python => "try `qbit py init`",
+            Ecosystem::Ruby => "try `qbit rb init`",
+            Ecosystem::Php => "try `qbit php init`",
+            Ecosystem::Java => "try `qbit java init`",
+            Ecosystem::Dotnet => "try `qbit dotnet init`",
+            Ecosystem::Dart => "try `qbit dart init`",
+            Ecosystem::Go => "try `qbit init --type go`",
+            Ecosystem::Rust => "add a qbit.yml with scripts such as `build: cargo build`",
+        }
+    }
+}
+
+/// Write `template` as qbit.yml unless the current directory already has a
+/// project config.
+pub fn ensure_project_config_file(template: &str) -> Result<()> {
+    if find_project_config_file(Path::new(".")).is_some() {
+        return Ok(());
+    }
+    fs::write("qbit.yml", template).context("writing qbit.yml template")?;
+    println!("Created qbit.yml");
+    Ok(())
+}
+
+/// Split `name[:version]` into its name and optional version. `noun` names
+/// the thing in errors, e.g. "gem" or "package".
+pub fn parse_name_version<'a>(spec: &'a str, noun: &str) -> Result<(&'a str, Option<&'a str>)> {
+    let trimmed = spec.trim();
+    let (name, version) = match trimmed.split_once(':') {
+        Some((name, version)) => (name.trim(), Some(version.trim())),
+        None => (trimmed, None),
+    };
+    if name.is_empty() {
+        bail!("The {noun} name must be non-empty.");
+    }
+    if version.is_some_and(str::is_empty) {
+        bail!("Missing version after `{name}:`; use `{name}:<version>` or just `{name}`.");
+    }
+    Ok((name, version))
+}
+
+/// Like [`parse_name_version`], for commands such as `bundle remove` that
+/// take no version.
+pub fn parse_name_only<'a>(spec: &'a str, noun: &str, command: &str) -> Result<&'a str> {
+    match parse_name_version(spec, noun)? {
+        (name, None) => Ok(name),
+        (name, Some(_)) => {
+            bail!("`{command}` takes a {noun} name without a version; use `{name}`.")
+        }
+    }
+}
+
+/// Fail with [`QbitError::ToolNotFound`] unless `tool` is on PATH.
+pub fn require_tool(tool: &str, hint: &str) -> Result<()> {
+    if find_executable(tool).is_none() {
+        bail!(QbitError::ToolNotFound {
+            tool: tool.to_string(),
+            hint: hint.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// A developer tool invocation, built apart from running it so each module's
+/// argv mapping can be tested without the tool installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl ToolCommand {
+    pub fn new<I, S>(program: impl Into<String>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Run with inherited stdio; `hint` follows the exit code on failure.
+    pub fn run(&self, hint: &str) -> Result<()> {
+        let label = self.render();
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .with_context(|| format!("running {label}"))?;
+
+        if !status.success() {
+            bail!(
+                "`{label}` failed (exit code {}). {hint}",
+                status.code().unwrap_or(1)
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::utils::test_env::CwdGuard;
+
+    #[test]
+    fn detects_js_from_package_json() {
+        let tmp = tempdir().expect("tempdir");
+        let context = ProjectContext::new(tmp.path());
+        assert!(context.detect_project_types().is_empty());
+
+        fs::write(tmp.path().join("package.json"), "{}").expect("write package.json");
+        assert_eq!(context.detect_project_types(), vec![Ecosystem::Js]);
+    }
+
+    #[test]
+    fn reports_every_ecosystem_present_in_priority_order() {
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("Cargo.toml"), "").expect("write Cargo.toml");
+        fs::write(tmp.path().join("requirements.txt"), "").expect("write requirements.txt");
+        assert_eq!(
+            ProjectContext::new(tmp.path()).detect_project_types(),
+            vec![Ecosystem::Python, Ecosystem::Rust]
+        );
+    }
+
+    #[test]
+    fn detects_ecosystems_from_build_files_and_project_extensions() {
+        let tmp = tempdir().expect("tempdir");
+        let context = ProjectContext::new(tmp.path());
+        fs::write(tmp.path().join("app.csproj"), "<Project/>").expect("write app.csproj");
+        assert_eq!(context.detect_project_types(), vec![Ecosystem::Dotnet]);
+
+        fs::write(tmp.path().join("pom.xml"), "<project/>").expect("write pom.xml");
+        fs::write(tmp.path().join("Gemfile"), "").expect("write Gemfile");
+        assert_eq!(
+            context.detect_project_types(),
+            vec![Ecosystem::Ruby, Ecosystem::Java, Ecosystem::Dotnet]
+        );
+    }
+
+    #[test]
+    fn parses_name_and_optional_version() {
+        assert_eq!(
+            parse_name_version(" rails : 7.1 ", "gem").expect("spec"),
+            ("rails", Some("7.1"))
+        );
+        assert_eq!(
+            parse_name_version("monolog/monolog", "package").expect("spec"),
+            ("monolog/monolog", None)
+        );
+        assert!(parse_name_version("  ", "gem").is_err());
+        assert!(parse_name_version(":1.0", "gem").is_err());
+        assert!(parse_name_version("rails:", "gem").is_err());
+
+        assert_eq!(
+            parse_name_only("Serilog", "package", "dotnet remove package").expect("name"),
+            "Serilog"
+        );
+        let err = parse_name_only("Serilog:3.1.1", "package", "dotnet remove package")
+            .expect_err("version rejected");
+        assert!(err.to_string().contains("use `Serilog`"), "{err}");
+    }
+
+    #[test]
+    #[serial]
+    fn writes_config_template_only_without_existing_config() {
+        let tmp = tempdir().expect("tempdir");
+        let _cwd = CwdGuard::set(tmp.path());
+
+        fs::write("qbit.toml", "").expect("write qbit.toml");
+        ensure_project_config_file("scripts: {}\n").expect("skip");
+        assert!(!Path::new("qbit.yml").exists());
+
+        fs::remove_file("qbit.toml").expect("remove qbit.toml");
+        ensure_project_config_file("scripts: {}\n").expect("write");
+        assert_eq!(
+            fs::read_to_string("qbit.yml").expect("read qbit.yml"),
+            "scripts: {}\n"
+        );
+    }
+
+    #[test]
+    fn renders_tool_command() {
+        let command = ToolCommand::new("bundle", ["add", "rails"]);
+        assert_eq!(command.render(), "bundle add rails");
+    }
 }
@@ -4,6 +4,9 @@ use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result, bail};
 
+use crate::error::QbitError;
+use crate::utils::runtime::{DART_OVERRIDE, find_runtime};
+
 pub fn init() -> Result<()> {
     let created = create_minimal_project_if_missing()?;
     if created {
@@ -12,27 +15,27 @@ pub fn init() -> Result<()> {
         println!("pubspec.yaml already exists");
     }
 
-    ensure_dart_available()?;
+    let dart = ensure_dart_available()?;
     let command = build_pub_get_command();
-    run_dart_command(&command, "dart pub get")?;
+    run_dart_command(&dart, &command, "dart pub get")?;
     println!("Dart dependencies resolved.");
     Ok(())
 }
 
 pub fn add_packages(packages: &[String]) -> Result<()> {
-    ensure_dart_available()?;
+    let dart = ensure_dart_available()?;
     ensure_pubspec_exists()?;
     let command = build_dart_pub_command("add", packages)?;
-    run_dart_command(&command, "dart pub add")?;
+    run_dart_command(&dart, &command, "dart pub add")?;
     println!("Added Dart packages: {}", packages.join(", "));
     Ok(())
 }
 
 pub fn remove_packages(packages: &[String]) -> Result<()> {
-    ensure_dart_available()?;
+    let dart = ensure_dart_available()?;
     ensure_pubspec_exists()?;
     let command = build_dart_pub_command("remove", packages)?;
-    run_dart_command(&command, "dart pub remove")?;
+    run_dart_command(&dart, &command, "dart pub remove")?;
     println!("Removed Dart packages: {}", packages.join(", "));
     Ok(())
 }
@@ -148,24 +151,19 @@ fn build_dart_pub_command(subcommand: &str, packages: &[String]) -> Result<DartC
     Ok(DartCommandSpec { args })
 }
 
-fn ensure_dart_available() -> Result<()> {
-    let status = Command::new("dart")
-        .arg("--version")
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status();
-
-    match status {
-        Ok(st) if st.success() => Ok(()),
-        _ => bail!(
-            "`dart` executable was not found in PATH. Install Dart SDK (https://dart.dev/get-dart) and ensure `dart --version` works."
-        ),
+/// The `dart` to invoke: `QBIT_DART` when it works, otherwise `dart` on PATH.
+fn ensure_dart_available() -> Result<String> {
+    match find_runtime(DART_OVERRIDE, "dart") {
+        Some(dart) => Ok(dart),
+        None => bail!(QbitError::ToolNotFound {
+            tool: "dart".to_string(),
+            hint: "Install Dart SDK (https://dart.dev/get-dart) and ensure `dart --version` works, or point QBIT_DART at an SDK's dart binary.".to_string(),
+        }),
     }
 }
 
-fn run_dart_command(command: &DartCommandSpec, label: &str) -> Result<()> {
-    let status = Command::new("dart")
+fn run_dart_command(dart: &str, command: &DartCommandSpec, label: &str) -> Result<()> {
+    let status = Command::new(dart)
         .args(&command.args)
         .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use crate::developers::common::{
+    Ecosystem, ToolCommand, ensure_project_config_file, parse_name_only, parse_name_version,
+    require_tool,
+};
+
+const CONFIG_TEMPLATE: &str = r#"scripts:
+  build: "dotnet build"
+  start: "qbit dotnet run"
+  test: "dotnet test"
+"#;
+
+const FAILURE_HINT: &str = "Check the dotnet output above and retry.";
+
+/// Create a console app with `dotnet new console` and scaffold qbit.yml if
+/// it's missing.
+pub fn init() -> Result<()> {
+    ensure_project_config_file(CONFIG_TEMPLATE)?;
+    if Ecosystem::Dotnet.is_present(Path::new(".")) {
+        println!(".NET project already exists");
+        return Ok(());
+    }
+
+    ensure_dotnet_available()?;
+    build_init_command().run(FAILURE_HINT)?;
+    println!(".NET project initialized with `dotnet new console`.");
+    Ok(())
+}
+
+/// Add a NuGet package with `dotnet add package`; `pkg:1.2` pins the version.
+pub fn add_package(package: &str) -> Result<()> {
+    ensure_dotnet_available()?;
+    ensure_project_exists()?;
+    build_add_command(package)?.run(FAILURE_HINT)?;
+    println!("Package `{package}` added via dotnet.");
+    Ok(())
+}
+
+pub fn remove_package(package: &str) -> Result<()> {
+    ensure_dotnet_available()?;
+    ensure_project_exists()?;
+    build_remove_command(package)?.run(FAILURE_HINT)?;
+    println!("Package `{package}` removed via dotnet.");
+    Ok(())
+}
+
+/// Build and run the project with `dotnet run`, passing `app_args` to the app.
+pub fn run_script(app_args: &[String]) -> Result<()> {
+    ensure_dotnet_available()?;
+    ensure_project_exists()?;
+    build_run_command(app_args).run(FAILURE_HINT)
+}
+
+fn ensure_project_exists() -> Result<()> {
+    if !Ecosystem::Dotnet.is_present(Path::new(".")) {
+        bail!(
+            "No .NET project (*.csproj, *.fsproj, *.vbproj, or *.sln) found in the current directory. Run `qbit dotnet init` first."
+        );
+    }
+    Ok(())
+}
+
+fn build_init_command() -> ToolCommand {
+    ToolCommand::new("dotnet", ["new", "console"])
+}
+
+fn build_add_command(package: &str) -> Result<ToolCommand> {
+    let (name, version) = parse_name_version(package, "package")?;
+    let mut args = vec!["add", "package", name];
+    if let Some(version) = version {
+        args.extend(["--version", version]);
+    }
+    Ok(ToolCommand::new("dotnet", args))
+}
+
+fn build_remove_command(package: &str) -> Result<ToolCommand> {
+    let name = parse_name_only(package, "package", "dotnet remove package")?;
+    Ok(ToolCommand::new("dotnet", ["remove", "package", name]))
+}
+
+/// `dotnet run`, with app arguments after `--` so dotnet doesn't parse them.
+fn build_run_command(app_args: &[String]) -> ToolCommand {
+    let mut args = vec!["run".to_string()];
+    if !app_args.is_empty() {
+        args.push("--".to_string());
+        args.extend(app_args.iter().cloned());
+    }
+    ToolCommand::new("dotnet", args)
+}
+
+fn ensure_dotnet_available() -> Result<()> {
+    require_tool(
+        "dotnet",
+        "Install the .NET SDK (https://dot.net/download, or `qbit install dotnet`) and ensure `dotnet --version` works.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_dotnet_new_console_command() {
+        assert_eq!(build_init_command().render(), "dotnet new console");
+    }
+
+    #[test]
+    fn builds_dotnet_add_package_command() {
+        let command = build_add_command("Newtonsoft.Json").expect("build command");
+        assert_eq!(
+            command.args,
+            vec![
+                "add".to_string(),
+                "package".to_string(),
+                "Newtonsoft.Json".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_dotnet_add_package_command_with_pinned_version() {
+        let command = build_add_command("Serilog:3.1.1").expect("build command");
+        assert_eq!(
+            command.args,
+            vec![
+                "add".to_string(),
+                "package".to_string(),
+                "Serilog".to_string(),
+                "--version".to_string(),
+                "3.1.1".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_dotnet_remove_package_command() {
+        let command = build_remove_command("Serilog").expect("build command");
+        assert_eq!(
+            command.args,
+            vec![
+                "remove".to_string(),
+                "package".to_string(),
+                "Serilog".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_dotnet_run_command_with_app_args_after_separator() {
+        assert_eq!(build_run_command(&[]).args, vec!["run".to_string()]);
+        assert_eq!(
+            build_run_command(&["--port".to_string(), "8080".to_string()]).args,
+            vec![
+                "run".to_string(),
+                "--".to_string(),
+                "--port".to_string(),
+                "8080".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,517 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::developers::common::{ToolCommand, ensure_project_config_file, require_tool};
+
+/// JVM build tools qbit drives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildTool {
+    Gradle,
+    Maven,
+}
+
+impl BuildTool {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gradle => "Gradle",
+            Self::Maven => "Maven",
+        }
+    }
+
+    /// Project-local wrapper script, preferred over a tool on PATH.
+    fn wrapper(self) -> &'static str {
+        match (self, cfg!(windows)) {
+            (Self::Gradle, false) => "gradlew",
+            (Self::Gradle, true) => "gradlew.bat",
+            (Self::Maven, false) => "mvnw",
+            (Self::Maven, true) => "mvnw.cmd",
+        }
+    }
+
+    fn executable(self) -> &'static str {
+        match self {
+            Self::Gradle => "gradle",
+            Self::Maven => "mvn",
+        }
+    }
+
+    fn config_template(self) -> &'static str {
+        match self {
+            Self::Gradle => {
+                r#"scripts:
+  build: "qbit java run build"
+  start: "qbit java run run"
+  test: "qbit java run test"
+"#
+            }
+            Self::Maven => {
+                r#"scripts:
+  build: "qbit java run package"
+  test: "qbit java run test"
+"#
+            }
+        }
+    }
+}
+
+const GRADLE_BUILD_FILES: [&str; 2] = ["build.gradle", "build.gradle.kts"];
+const MAVEN_BUILD_FILE: &str = "pom.xml";
+
+/// Scaffold a minimal Gradle or Maven project with an `app.App` main class,
+/// plus qbit.yml if it's missing.
+pub fn init(tool: BuildTool) -> Result<()> {
+    ensure_project_config_file(tool.config_template())?;
+    if let Some((_, build_file)) = detect_build_file(Path::new(".")) {
+        println!("{} already exists", build_file.display());
+        return Ok(());
+    }
+
+    let name = artifact_name();
+    match tool {
+        BuildTool::Gradle => {
+            fs::write("build.gradle", GRADLE_TEMPLATE).context("writing build.gradle")?;
+            println!("Created build.gradle");
+            fs::write("settings.gradle", format!("rootProject.name = '{name}'\n"))
+                .context("writing settings.gradle")?;
+            println!("Created settings.gradle");
+        }
+        BuildTool::Maven => {
+            fs::write(MAVEN_BUILD_FILE, maven_template(&name)).context("writing pom.xml")?;
+            println!("Created pom.xml");
+        }
+    }
+    ensure_main_class()?;
+    println!("Java project scaffolded with {}.", tool.name());
+    Ok(())
+}
+
+/// Declare `coordinate` (`group:artifact:version`) in build.gradle(.kts) or
+/// pom.xml. The build tool downloads it on the next build.
+pub fn add_package(coordinate: &str) -> Result<()> {
+    let dependency = Dependency::parse(coordinate)?;
+    let (tool, build_file) = require_build_file()?;
+    let content = fs::read_to_string(&build_file)
+        .with_context(|| format!("reading {}", build_file.display()))?;
+    let updated = match tool {
+        BuildTool::Gradle => {
+            let kotlin = build_file.extension().is_some_and(|ext| ext == "kts");
+            add_gradle_dependency(&content, &dependency, kotlin)?
+        }
+        BuildTool::Maven => add_maven_dependency(&content, &dependency)?,
+    };
+    fs::write(&build_file, updated).with_context(|| format!("writing {}", build_file.display()))?;
+    println!(
+        "Dependency `{}` added to {}.",
+        dependency.coordinate(),
+        build_file.display()
+    );
+    Ok(())
+}
+
+/// Run a Gradle task or Maven goal, through the project's wrapper when it
+/// has one.
+pub fn run_script(task: &str, task_args: &[String]) -> Result<()> {
+    let (tool, _) = require_build_file()?;
+    let command = build_run_command(tool, task, task_args, Path::new("."))?;
+    if command.program == tool.executable() {
+        require_tool(
+            tool.executable(),
+            &format!(
+                "Install {} (for example `qbit install {}`) or add its wrapper script ({}) to the project, then retry.",
+                tool.name(),
+                tool.name().to_ascii_lowercase(),
+                tool.wrapper()
+            ),
+        )?;
+    }
+    command.run("Check the build output above and retry.")
+}
+
+const GRADLE_TEMPLATE: &str = r#"plugins {
+    id 'application'
+}
+
+repositories {
+    mavenCentral()
+}
+
+dependencies {
+}
+
+application {
+    mainClass = 'app.App'
+}
+"#;
+
+fn maven_template(name: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<project xmlns="http://maven.apache.org/POM/4.0.0"
+         xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+         xsi:schemaLocation="http://maven.apache.org/POM/4.0.0 https://maven.apache.org/xsd/maven-4.0.0.xsd">
+  <modelVersion>4.0.0</modelVersion>
+
+  <groupId>com.example</groupId>
+  <artifactId>{name}</artifactId>
+  <version>0.1.0-SNAPSHOT</version>
+
+  <properties>
+    <maven.compiler.release>17</maven.compiler.release>
+    <project.build.sourceEncoding>UTF-8</project.build.sourceEncoding>
+  </properties>
+
+  <dependencies>
+  </dependencies>
+</project>
+"#
+    )
+}
+
+fn ensure_main_class() -> Result<()> {
+    let dir = Path::new("src").join("main").join("java").join("app");
+    let entry = dir.join("App.java");
+    if entry.exists() {
+        println!("{} already exists", entry.display());
+        return Ok(());
+    }
+    fs::create_dir_all(&dir).context("creating src/main/java/app")?;
+    let content = r#"package app;
+
+public class App {
+    public static void main(String[] args) {
+        System.out.println("Hello from qbit java init!");
+    }
+}
+"#;
+    fs::write(&entry, content).with_context(|| format!("writing {}", entry.display()))?;
+    println!("Created {}", entry.display());
+    Ok(())
+}
+
+/// The current directory's name as a Maven artifact id / Gradle project name.
+fn artifact_name() -> String {
+    let name: String = std::env::current_dir()
+        .ok()
+        .and_then(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('-');
+    if name.is_empty() {
+        "qbit-app".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// The build tool whose build file sits in `dir`; Gradle wins when a project
+/// has both.
+fn detect_build_file(dir: &Path) -> Option<(BuildTool, PathBuf)> {
+    GRADLE_BUILD_FILES
+        .iter()
+        .map(|file| (BuildTool::Gradle, *file))
+        .chain([(BuildTool::Maven, MAVEN_BUILD_FILE)])
+        .map(|(tool, file)| (tool, dir.join(file)))
+        .find(|(_, path)| path.is_file())
+        .map(|(tool, path)| (tool, path.strip_prefix(dir).unwrap_or(&path).to_path_buf()))
+}
+
+fn require_build_file() -> Result<(BuildTool, PathBuf)> {
+    detect_build_file(Path::new(".")).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No build.gradle, build.gradle.kts, or pom.xml found in the current directory. Run `qbit java init` first."
+        )
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Dependency {
+    group: String,
+    artifact: String,
+    version: String,
+}
+
+impl Dependency {
+    fn parse(coordinate: &str) -> Result<Self> {
+        let coordinate = coordinate.trim();
+        let parts: Vec<&str> = coordinate.split(':').map(str::trim).collect();
+        let valid_part = |part: &&str| {
+            !part.is_empty()
+                && !part
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '<' | '>' | '&'))
+        };
+        match parts.as_slice() {
+            [group, artifact, version] if parts.iter().all(valid_part) => Ok(Self {
+                group: group.to_string(),
+                artifact: artifact.to_string(),
+                version: version.to_string(),
+            }),
+            _ => bail!(
+                "Dependency `{coordinate}` must be a `group:artifact:version` coordinate, e.g. `com.google.guava:guava:33.0.0-jre`."
+            ),
+        }
+    }
+
+    fn coordinate(&self) -> String {
+        format!("{}:{}:{}", self.group, self.artifact, self.version)
+    }
+}
+
+/// Add an `implementation` line to the top-level `dependencies { }` block,
+/// creating the block at the end of the file when there is none.
+fn add_gradle_dependency(content: &str, dependency: &Dependency, kotlin: bool) -> Result<String> {
+    let module = format!("{}:{}:", dependency.group, dependency.artifact);
+    if content.contains(&format!("'{module}")) || content.contains(&format!("\"{module}")) {
+        bail!(
+            "`{}:{}` is already declared in the build file; change its version there instead.",
+            dependency.group,
+            dependency.artifact
+        );
+    }
+    let line = if kotlin {
+        format!("    implementation(\"{}\")", dependency.coordinate())
+    } else {
+        format!("    implementation '{}'", dependency.coordinate())
+    };
+
+    let lines: Vec<&str> = content.lines().collect();
+    let block_end = lines
+        .iter()
+        .position(|line| line.trim_end() == "dependencies {")
+        .and_then(|start| {
+            lines[start + 1..]
+                .iter()
+                .position(|line| line.trim_end() == "}")
+                .map(|offset| start + 1 + offset)
+        });
+
+    let mut updated: Vec<&str> = Vec::with_capacity(lines.len() + 4);
+    match block_end {
+        Some(end) => {
+            updated.extend(&lines[..end]);
+            updated.push(&line);
+            updated.extend(&lines[end..]);
+        }
+        None => {
+            updated.extend(&lines);
+            if updated.last().is_some_and(|last| !last.trim().is_empty()) {
+                updated.push("");
+            }
+            updated.extend(["dependencies {", &line, "}"]);
+        }
+    }
+    Ok(updated.join("\n") + "\n")
+}
+
+/// Add a `<dependency>` to the project's own `<dependencies>` (not the ones
+/// under `<dependencyManagement>`, `<build>` plugins, or `<profiles>`),
+/// creating the element before `</project>` when there is none.
+fn add_maven_dependency(pom: &str, dependency: &Dependency) -> Result<String> {
+    let compact: String = pom.split_whitespace().collect();
+    if compact.contains(&format!(
+        "<groupId>{}</groupId><artifactId>{}</artifactId>",
+        dependency.group, dependency.artifact
+    )) {
+        bail!(
+            "`{}:{}` is already declared in pom.xml; change its version there instead.",
+            dependency.group,
+            dependency.artifact
+        );
+    }
+
+    let element = |indent: &str| {
+        format!(
+            "{indent}<dependency>\n{indent}  <groupId>{}</groupId>\n{indent}  <artifactId>{}</artifactId>\n{indent}  <version>{}</version>\n{indent}</dependency>\n",
+            dependency.group, dependency.artifact, dependency.version
+        )
+    };
+
+    if let Some(close) = project_dependencies_end(pom) {
+        let line_start = pom[..close].rfind('\n').map_or(0, |newline| newline + 1);
+        let indent = &pom[line_start..close];
+        if indent.trim().is_empty() {
+            let block = element(&format!("{indent}  "));
+            return Ok(format!(
+                "{}{block}{}",
+                &pom[..line_start],
+                &pom[line_start..]
+            ));
+        }
+        let block = element("    ");
+        return Ok(format!("{}\n{block}  {}", &pom[..close], &pom[close..]));
+    }
+
+    let Some(project_end) = pom.rfind("</project>") else {
+        bail!("pom.xml has no closing </project> element; fix the file and retry.");
+    };
+    let line_start = pom[..project_end]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let block = format!("  <dependencies>\n{}  </dependencies>\n", element("    "));
+    Ok(format!(
+        "{}{block}{}",
+        &pom[..line_start],
+        &pom[line_start..]
+    ))
+}
+
+/// Offset of the `</dependencies>` that closes the project's own dependency
+/// list.
+fn project_dependencies_end(pom: &str) -> Option<usize> {
+    let nested: Vec<(usize, usize)> = ["dependencyManagement", "build", "profiles"]
+        .iter()
+        .filter_map(|tag| {
+            let start = pom.find(&format!("<{tag}>"))?;
+            let end = start + pom[start..].find(&format!("</{tag}>"))?;
+            Some((start, end))
+        })
+        .collect();
+    pom.match_indices("</dependencies>")
+        .map(|(offset, _)| offset)
+        .find(|offset| {
+            !nested
+                .iter()
+                .any(|(start, end)| start < offset && offset < end)
+        })
+}
+
+/// `./gradlew <task>` or `./mvnw <goal>` when the project ships a wrapper,
+/// else `gradle <task>` or `mvn <goal>`.
+fn build_run_command(
+    tool: BuildTool,
+    task: &str,
+    task_args: &[String],
+    dir: &Path,
+) -> Result<ToolCommand> {
+    let task = task.trim();
+    if task.is_empty() {
+        bail!("Task name must be non-empty.");
+    }
+    let wrapper = dir.join(tool.wrapper());
+    let program = if wrapper.is_file() {
+        wrapper.display().to_string()
+    } else {
+        tool.executable().to_string()
+    };
+    let args = std::iter::once(task.to_string()).chain(task_args.iter().cloned());
+    Ok(ToolCommand::new(program, args))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn guava() -> Dependency {
+        Dependency::parse("com.google.guava:guava:33.0.0-jre").expect("coordinate")
+    }
+
+    #[test]
+    fn parses_dependency_coordinates() {
+        assert_eq!(
+            guava(),
+            Dependency {
+                group: "com.google.guava".to_string(),
+                artifact: "guava".to_string(),
+                version: "33.0.0-jre".to_string(),
+            }
+        );
+        for bad in [
+            "guava",
+            "com.google.guava:guava",
+            "a::1",
+            "a:b:1:2",
+            "a:b c:1",
+        ] {
+            assert!(Dependency::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn adds_gradle_dependency_inside_dependencies_block() {
+        let updated = add_gradle_dependency(GRADLE_TEMPLATE, &guava(), false).expect("add");
+        assert!(
+            updated.contains(
+                "dependencies {\n    implementation 'com.google.guava:guava:33.0.0-jre'\n}\n"
+            ),
+            "{updated}"
+        );
+        assert!(add_gradle_dependency(&updated, &guava(), false).is_err());
+
+        let kotlin =
+            add_gradle_dependency("plugins {\n    java\n}\n", &guava(), true).expect("add");
+        assert_eq!(
+            kotlin,
+            "plugins {\n    java\n}\n\ndependencies {\n    implementation(\"com.google.guava:guava:33.0.0-jre\")\n}\n"
+        );
+    }
+
+    #[test]
+    fn adds_maven_dependency_to_project_dependencies_only() {
+        let pom = "<project>\n  <dependencyManagement>\n    <dependencies>\n    </dependencies>\n  </dependencyManagement>\n  <dependencies>\n  </dependencies>\n</project>\n";
+        let updated = add_maven_dependency(pom, &guava()).expect("add");
+        assert!(
+            updated.ends_with("  <dependencies>\n    <dependency>\n      <groupId>com.google.guava</groupId>\n      <artifactId>guava</artifactId>\n      <version>33.0.0-jre</version>\n    </dependency>\n  </dependencies>\n</project>\n"),
+            "{updated}"
+        );
+        assert!(
+            updated.contains("<dependencyManagement>\n    <dependencies>\n    </dependencies>")
+        );
+        assert!(add_maven_dependency(&updated, &guava()).is_err());
+
+        let bare = add_maven_dependency("<project>\n</project>\n", &guava()).expect("add");
+        assert!(
+            bare.starts_with("<project>\n  <dependencies>\n    <dependency>\n"),
+            "{bare}"
+        );
+        assert!(bare.ends_with("  </dependencies>\n</project>\n"), "{bare}");
+    }
+
+    #[test]
+    fn run_prefers_project_wrapper_over_tool_on_path() {
+        let tmp = tempdir().expect("tempdir");
+        let args = ["--info".to_string()];
+
+        let command =
+            build_run_command(BuildTool::Gradle, "test", &args, tmp.path()).expect("command");
+        assert_eq!(command.program, "gradle");
+        assert_eq!(command.args, vec!["test", "--info"]);
+
+        fs::write(tmp.path().join(BuildTool::Maven.wrapper()), "").expect("write mvnw");
+        let command =
+            build_run_command(BuildTool::Maven, "package", &[], tmp.path()).expect("command");
+        assert_ne!(command.program, "mvn");
+        assert!(command.program.ends_with(BuildTool::Maven.wrapper()));
+        assert!(build_run_command(BuildTool::Maven, " ", &[], tmp.path()).is_err());
+    }
+
+    #[test]
+    fn detects_gradle_before_maven() {
+        let tmp = tempdir().expect("tempdir");
+        assert_eq!(detect_build_file(tmp.path()), None);
+        fs::write(tmp.path().join("pom.xml"), "<project/>").expect("write pom.xml");
+        assert_eq!(
+            detect_build_file(tmp.path()),
+            Some((BuildTool::Maven, PathBuf::from("pom.xml")))
+        );
+        fs::write(tmp.path().join("build.gradle.kts"), "").expect("write build.gradle.kts");
+        assert_eq!(
+            detect_build_file(tmp.path()),
+            Some((BuildTool::Gradle, PathBuf::from("build.gradle.kts")))
+        );
+    }
+}
@@ -5,9 +5,15 @@ use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result, bail};
 
+use crate::config::LoadedProjectConfig;
+use crate::developers::common::ensure_project_config_file;
+use crate::utils::retry::run_with_retries;
+use crate::utils::runtime::{NODE_OVERRIDE, path_with_runtime_first, runtime_override};
+use crate::utils::shell::{CommandStep, join_args};
+
 /// Initialize a minimal JS/TS project by scaffolding package.json and src/index.js
 pub fn init() -> Result<()> {
-    ensure_project_config_file()?;
+    ensure_project_config_file(CONFIG_TEMPLATE)?;
     ensure_package_json()?;
     ensure_src_tree()?;
     println!(
@@ -16,29 +22,149 @@ pub fn init() -> Result<()> {
     Ok(())
 }
 
-pub fn add_package(package: &str) -> Result<()> {
+/// Which dependencies a lockfile sync installs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyScope {
+    All,
+    /// Skip devDependencies (`qbit js install --prod`).
+    Production,
+    /// Make sure devDependencies are installed (`qbit js install --only=dev`),
+    /// even when `NODE_ENV=production` would otherwise leave them out.
+    Dev,
+}
+
+/// Add `package`, or install everything already declared when no package is
+/// named. With `frozen`, an added package keeps the version pinned in the
+/// lockfile, and a plain install fails instead of creating a lockfile.
+pub fn add_package(
+    config: Option<&LoadedProjectConfig>,
+    package: Option<&str>,
+    filter: Option<&str>,
+    frozen: bool,
+) -> Result<()> {
+    let Some(package) = package else {
+        return install_dependencies(config, DependencyScope::All, filter, frozen);
+    };
+
     ensure_package_json()?;
     let pm = resolve_package_manager()?;
-    let command = build_add_command(pm, package)?;
-    run_package_manager(&command)?;
+    let registry = resolve_registry(config);
+    warn_if_filter_without_workspace(filter);
+
+    let package = if frozen {
+        locked_package_spec(pm, package)?
+    } else {
+        package.to_string()
+    };
+    let command = with_workspace(
+        build_add_command(pm, &package, registry.as_deref())?,
+        filter,
+    )?;
+    run_package_manager(&command, true)?;
     println!("Package `{package}` added via {}.", pm.name());
     Ok(())
 }
 
-pub fn remove_package(package: &str) -> Result<()> {
+/// Install what package.json declares, limited to `scope`: the manager's
+/// clean install when a lockfile exists, else a plain install, which `frozen`
+/// forbids.
+pub fn install_dependencies(
+    config: Option<&LoadedProjectConfig>,
+    scope: DependencyScope,
+    filter: Option<&str>,
+    frozen: bool,
+) -> Result<()> {
     ensure_package_json()?;
     let pm = resolve_package_manager()?;
-    let command = build_remove_command(pm, package)?;
-    run_package_manager(&command)?;
+    let registry = resolve_registry(config);
+    warn_if_filter_without_workspace(filter);
+
+    let locked = Path::new(pm.lockfile()).exists();
+    let command = with_workspace(
+        build_install_command(pm, locked, frozen, scope, registry.as_deref())?,
+        filter,
+    )?;
+    run_package_manager(&command, true)?;
+    let what = match scope {
+        DependencyScope::All => "Dependencies",
+        DependencyScope::Production => "Production dependencies",
+        DependencyScope::Dev => "Dependencies (including dev)",
+    };
+    if locked {
+        println!("{what} installed from {} via {}.", pm.lockfile(), pm.name());
+    } else {
+        println!("{what} installed via {}.", pm.name());
+    }
+    Ok(())
+}
+
+pub fn remove_package(package: &str, filter: Option<&str>) -> Result<()> {
+    ensure_package_json()?;
+    let pm = resolve_package_manager()?;
+    warn_if_filter_without_workspace(filter);
+    let command = with_workspace(build_remove_command(pm, package)?, filter)?;
+    run_package_manager(&command, false)?;
     println!("Package `{package}` removed via {}.", pm.name());
     Ok(())
 }
 
-pub fn run_script(script: &str, script_args: &[String]) -> Result<()> {
+pub fn run_script(script: &str, script_args: &[String], filter: Option<&str>) -> Result<()> {
+    ensure_package_json()?;
+    let pm = resolve_package_manager()?;
+    warn_if_filter_without_workspace(filter);
+    let command = with_workspace(build_run_command(pm, script, script_args)?, filter)?;
+    run_package_manager(&command, false)?;
+    Ok(())
+}
+
+/// `qbit run <script>` in a project without a qbit config: a step running
+/// package.json's `script` through the detected package manager, with that
+/// manager's name. `None` when package.json is missing or lacks the script.
+pub fn package_script_step(script: &str) -> Result<Option<(&'static str, CommandStep)>> {
+    if !package_json_defines_script(Path::new("package.json"), script) {
+        return Ok(None);
+    }
+    let pm = resolve_package_manager()?;
+    let command = build_run_command(pm, script, &[])?;
+    let mut argv = vec![pm.executable().to_string()];
+    argv.extend(command.args);
+    let mut step = CommandStep {
+        command: join_args(&argv),
+        ..CommandStep::default()
+    };
+    if let Some(path) = runtime_override(NODE_OVERRIDE)
+        .and_then(|node| path_with_runtime_first(&node, &env::var_os("PATH").unwrap_or_default()))
+        .and_then(|path| path.into_string().ok())
+    {
+        step.env.insert("PATH".to_string(), path);
+    }
+    Ok(Some((pm.name(), step)))
+}
+
+fn package_json_defines_script(path: &Path, script: &str) -> bool {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|package| package["scripts"].get(script).is_some())
+}
+
+/// Upgrade a dependency to the newest version allowed by package.json.
+pub fn upgrade_package(config: Option<&LoadedProjectConfig>, package: &str) -> Result<()> {
     ensure_package_json()?;
     let pm = resolve_package_manager()?;
-    let command = build_run_command(pm, script, script_args)?;
-    run_package_manager(&command)?;
+    let registry = resolve_registry(config);
+    let command = build_upgrade_command(pm, package, registry.as_deref())?;
+    run_package_manager(&command, true)?;
+    println!("Package `{package}` upgraded via {}.", pm.name());
+    Ok(())
+}
+
+/// Show top-level dependencies via the detected package manager.
+pub fn list_packages() -> Result<()> {
+    ensure_package_json()?;
+    let pm = resolve_package_manager()?;
+    let command = build_list_command(pm);
+    run_package_manager(&command, false)?;
     Ok(())
 }
 
@@ -86,15 +212,7 @@ fn ensure_src_tree() -> Result<()> {
     Ok(())
 }
 
-fn ensure_project_config_file() -> Result<()> {
-    if Path::new("qbit.yml").exists()
-        || Path::new("qbit.yaml").exists()
-        || Path::new("qbit.toml").exists()
-    {
-        return Ok(());
-    }
-
-    let template = r#"scripts:
+const CONFIG_TEMPLATE: &str = r#"scripts:
   dev: "npm run dev"
   lint:
     - "qbit py init"
@@ -112,10 +230,6 @@ install:
       apt: "redis-server"
       winget: "Redis.Redis-CLI"
 "#;
-    fs::write("qbit.yml", template.as_bytes()).context("writing qbit.yml template")?;
-    println!("Created qbit.yml");
-    Ok(())
-}
 
 fn project_name() -> String {
     std::env::current_dir()
@@ -140,13 +254,31 @@ impl JsCommandSpec {
     }
 }
 
-fn run_package_manager(command: &JsCommandSpec) -> Result<()> {
+/// Run `command`, showing it first. Commands that fetch from the registry
+/// pass `retry_network_errors` so a flaky connection gets another attempt;
+/// scripts and listings never rerun.
+fn run_package_manager(command: &JsCommandSpec, retry_network_errors: bool) -> Result<()> {
     println!("Using JavaScript package manager: {}", command.pm.name());
     println!("{}", command.render());
-    let status = run_js_command(command.pm.executable(), &command.args)
-        .with_context(|| format!("spawning {}", command.pm.executable()))?;
+    let executable = command.pm.executable();
+    let (status, attempts, network_error) = if retry_network_errors {
+        let run = run_with_retries(executable, || js_command(executable, &command.args))?;
+        (run.status, run.attempts, run.network_error)
+    } else {
+        let status = js_command(executable, &command.args)
+            .status()
+            .with_context(|| format!("running {executable}"))?;
+        (status, 1, false)
+    };
 
     if !status.success() {
+        if network_error {
+            bail!(
+                "{} command failed (code: {}) after {attempts} attempts because the registry looks unreachable. Check your network or proxy settings (or `js.registry`) and retry.",
+                command.pm.name(),
+                status.code().unwrap_or_default(),
+            );
+        }
         bail!(
             "{} command failed (code: {})",
             command.pm.name(),
@@ -156,28 +288,29 @@ fn run_package_manager(command: &JsCommandSpec) -> Result<()> {
     Ok(())
 }
 
-fn run_js_command(executable: &str, args: &[String]) -> std::io::Result<std::process::ExitStatus> {
+fn js_command(executable: &str, args: &[String]) -> Command {
     #[cfg(windows)]
-    {
-        Command::new("cmd")
-            .arg("/C")
-            .arg(executable)
-            .args(args)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
-    }
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(executable);
+        command
+    };
 
     #[cfg(not(windows))]
+    let mut command = Command::new(executable);
+
+    // Scripts call `node` by name, so a pinned QBIT_NODE goes first on PATH.
+    if let Some(path) = runtime_override(NODE_OVERRIDE)
+        .and_then(|node| path_with_runtime_first(&node, &env::var_os("PATH").unwrap_or_default()))
     {
-        Command::new(executable)
-            .args(args)
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::inherit())
-            .stderr(Stdio::inherit())
-            .status()
+        command.env("PATH", path);
     }
+
+    command
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit());
+    command
 }
 
 fn resolve_package_manager() -> Result<JsPackageManager> {
@@ -284,15 +417,168 @@ fn command_available(cmd: &str) -> bool {
     }
 }
 
-fn build_add_command(pm: JsPackageManager, package: &str) -> Result<JsCommandSpec> {
+/// Registry for add/upgrade: `QBIT_JS_REGISTRY` wins over `js.registry` in config.
+fn resolve_registry(config: Option<&LoadedProjectConfig>) -> Option<String> {
+    let trimmed = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+    env::var("QBIT_JS_REGISTRY")
+        .ok()
+        .and_then(trimmed)
+        .or_else(|| {
+            config
+                .and_then(|cfg| cfg.data.js.registry.clone())
+                .and_then(trimmed)
+        })
+}
+
+fn build_add_command(
+    pm: JsPackageManager,
+    package: &str,
+    registry: Option<&str>,
+) -> Result<JsCommandSpec> {
     let package = package.trim();
     if package.is_empty() {
         bail!("Package name must be non-empty.");
     }
-    Ok(JsCommandSpec {
-        pm,
-        args: pm.add_args([package]),
-    })
+    let mut args = pm.add_args([package]);
+    if let Some(url) = registry {
+        args.extend(pm.registry_args(url));
+    }
+    Ok(JsCommandSpec { pm, args })
+}
+
+/// Install what package.json declares: the manager's clean-install command
+/// when `locked` (a lockfile exists), else a plain install, which `frozen`
+/// forbids because it would write a new lockfile. `scope` adds the manager's
+/// production or dev flag.
+fn build_install_command(
+    pm: JsPackageManager,
+    locked: bool,
+    frozen: bool,
+    scope: DependencyScope,
+    registry: Option<&str>,
+) -> Result<JsCommandSpec> {
+    let mut args = if locked {
+        pm.ci_args()
+    } else if frozen {
+        bail!(
+            "--frozen needs {}, but it does not exist. Run `qbit js add` without --frozen first to create it.",
+            pm.lockfile()
+        );
+    } else {
+        vec!["install".to_string()]
+    };
+    args.extend(pm.scope_args(scope)?);
+    if let Some(url) = registry {
+        args.extend(pm.registry_args(url));
+    }
+    Ok(JsCommandSpec { pm, args })
+}
+
+/// `package@<locked version>` for `qbit js add --frozen`.
+fn locked_package_spec(pm: JsPackageManager, package: &str) -> Result<String> {
+    let package = package.trim();
+    let (name, version) = split_package_spec(package);
+    if let Some(version) = version {
+        bail!(
+            "--frozen takes `{name}`'s version from {}; drop the `@{version}` suffix.",
+            pm.lockfile()
+        );
+    }
+    let lockfile = pm.lockfile();
+    if pm == JsPackageManager::Bun {
+        bail!(
+            "--frozen can't read {lockfile} because it is binary; pin the version yourself, e.g. `qbit js add {name}@<version>`."
+        );
+    }
+    if !Path::new(lockfile).exists() {
+        bail!(
+            "--frozen needs {lockfile}, but it does not exist. Run `qbit js add {name}` without --frozen."
+        );
+    }
+    let content = fs::read_to_string(lockfile).with_context(|| format!("reading {lockfile}"))?;
+    let version = locked_version(pm, &content, name)
+        .with_context(|| format!("parsing {lockfile}"))?
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "`{name}` is not pinned in {lockfile}. Run `qbit js add {name}` without --frozen to add it."
+            )
+        })?;
+    Ok(format!("{name}@{version}"))
+}
+
+/// Split `name@version` into its parts; the `@` of a scope (`@types/node`)
+/// isn't a separator.
+fn split_package_spec(spec: &str) -> (&str, Option<&str>) {
+    match spec.rfind('@') {
+        Some(at) if at > 0 => (&spec[..at], Some(&spec[at + 1..])),
+        _ => (spec, None),
+    }
+}
+
+/// Version of the top-level dependency `name` recorded in `content`, the
+/// text of `pm`'s lockfile.
+fn locked_version(pm: JsPackageManager, content: &str, name: &str) -> Result<Option<String>> {
+    match pm {
+        JsPackageManager::Npm => {
+            let lock: serde_json::Value = serde_json::from_str(content)?;
+            let entry = lock
+                .pointer(&format!(
+                    "/packages/node_modules~1{}",
+                    name.replace('/', "~1")
+                ))
+                .or_else(|| lock.get("dependencies").and_then(|deps| deps.get(name)));
+            Ok(entry
+                .and_then(|entry| entry.get("version"))
+                .and_then(|version| version.as_str())
+                .map(str::to_string))
+        }
+        JsPackageManager::Pnpm => {
+            let lock: serde_yaml::Value = serde_yaml::from_str(content)?;
+            // pnpm 6+ nests the root project under `importers: .`; older
+            // lockfiles list its dependencies at the top level.
+            let project = lock
+                .get("importers")
+                .and_then(|importers| importers.get("."))
+                .unwrap_or(&lock);
+            let entry = ["dependencies", "devDependencies", "optionalDependencies"]
+                .iter()
+                .find_map(|section| project.get(section).and_then(|deps| deps.get(name)));
+            let version = entry.and_then(|entry| match entry {
+                serde_yaml::Value::Mapping(_) => entry.get("version").and_then(|v| v.as_str()),
+                other => other.as_str(),
+            });
+            // Peer-resolved versions look like `18.2.0(react@18.2.0)`.
+            Ok(version.map(|version| version.split('(').next().unwrap_or(version).to_string()))
+        }
+        JsPackageManager::Yarn => Ok(yarn_locked_version(content, name)),
+        JsPackageManager::Bun => bail!("bun.lockb is a binary lockfile and can't be read."),
+    }
+}
+
+/// Scan a yarn.lock (classic or berry) for the block whose header names
+/// `name`, and return its `version`.
+fn yarn_locked_version(content: &str, name: &str) -> Option<String> {
+    let mut in_block = false;
+    for line in content.lines() {
+        if !line.starts_with(' ') && line.ends_with(':') {
+            in_block = line.trim_end_matches(':').split(", ").any(|descriptor| {
+                let descriptor = descriptor.trim_matches('"');
+                split_package_spec(descriptor).0 == name
+            });
+            continue;
+        }
+        if !in_block {
+            continue;
+        }
+        let Some(rest) = line.trim_start().strip_prefix("version") else {
+            continue;
+        };
+        let version = rest.trim_start_matches(':').trim().trim_matches('"');
+        if !version.is_empty() {
+            return Some(version.to_string());
+        }
+    }
+    None
 }
 
 fn build_remove_command(pm: JsPackageManager, package: &str) -> Result<JsCommandSpec> {
@@ -306,6 +592,63 @@ fn build_remove_command(pm: JsPackageManager, package: &str) -> Result<JsCommand
     })
 }
 
+fn build_upgrade_command(
+    pm: JsPackageManager,
+    package: &str,
+    registry: Option<&str>,
+) -> Result<JsCommandSpec> {
+    let package = package.trim();
+    if package.is_empty() {
+        bail!("Package name must be non-empty.");
+    }
+    let mut args = vec![pm.upgrade_verb().to_string(), package.to_string()];
+    if let Some(url) = registry {
+        args.extend(pm.registry_args(url));
+    }
+    Ok(JsCommandSpec { pm, args })
+}
+
+/// Scope a command to one workspace package (`pnpm --filter`, `yarn workspace`, `npm -w`).
+fn with_workspace(command: JsCommandSpec, filter: Option<&str>) -> Result<JsCommandSpec> {
+    let Some(filter) = filter.map(str::trim) else {
+        return Ok(command);
+    };
+    if filter.is_empty() {
+        bail!("Workspace filter must be non-empty.");
+    }
+    let mut args = command.pm.workspace_args(filter)?;
+    args.extend(command.args);
+    Ok(JsCommandSpec {
+        pm: command.pm,
+        args,
+    })
+}
+
+fn warn_if_filter_without_workspace(filter: Option<&str>) {
+    if filter.is_some() && !is_workspace_root(Path::new(".")) {
+        eprintln!(
+            "warning: --filter given but no workspace detected (no pnpm-workspace.yaml or `workspaces` in package.json)."
+        );
+    }
+}
+
+fn is_workspace_root(dir: &Path) -> bool {
+    if dir.join("pnpm-workspace.yaml").exists() {
+        return true;
+    }
+    fs::read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .is_some_and(|package| package.get("workspaces").is_some())
+}
+
+fn build_list_command(pm: JsPackageManager) -> JsCommandSpec {
+    JsCommandSpec {
+        pm,
+        args: pm.list_args(),
+    }
+}
+
 fn build_run_command(
     pm: JsPackageManager,
     script: &str,
@@ -363,6 +706,44 @@ impl JsPackageManager {
         args
     }
 
+    /// The lockfile this manager writes; its presence selects the manager
+    /// during detection.
+    fn lockfile(self) -> &'static str {
+        lockfile_priority()
+            .into_iter()
+            .find(|(_, pm)| *pm == self)
+            .map(|(lockfile, _)| lockfile)
+            .unwrap_or("package-lock.json")
+    }
+
+    /// Install exactly what the lockfile records, failing if package.json
+    /// and the lockfile disagree.
+    fn ci_args(self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Npm => &["ci"],
+            Self::Pnpm | Self::Yarn | Self::Bun => &["install", "--frozen-lockfile"],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    /// Flags that limit an install to production or dev dependencies. Only
+    /// npm and pnpm can ask for dev dependencies explicitly.
+    fn scope_args(self, scope: DependencyScope) -> Result<Vec<String>> {
+        let args: &[&str] = match (self, scope) {
+            (_, DependencyScope::All) => &[],
+            (Self::Npm, DependencyScope::Production) => &["--omit=dev"],
+            (Self::Npm, DependencyScope::Dev) => &["--include=dev"],
+            (Self::Pnpm, DependencyScope::Production) => &["--prod"],
+            (Self::Pnpm, DependencyScope::Dev) => &["--dev"],
+            (Self::Yarn | Self::Bun, DependencyScope::Production) => &["--production"],
+            (Self::Yarn | Self::Bun, DependencyScope::Dev) => bail!(
+                "{} install has no dev-only option; run `qbit js install` without --only=dev, or switch to npm or pnpm with QBIT_JS_PM.",
+                self.name()
+            ),
+        };
+        Ok(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
     fn remove_args<I>(self, packages: I) -> Vec<String>
     where
         I: IntoIterator,
@@ -373,15 +754,48 @@ impl JsPackageManager {
         args
     }
 
+    /// `run <script>` plus the script's own args. Only npm needs `--` to stop
+    /// parsing its own flags; pnpm, yarn, and bun forward everything after the
+    /// script name and would hand a literal `--` to the script.
     fn run_args(self, script: &str, script_args: &[String]) -> Vec<String> {
         let mut args = vec!["run".to_string(), script.to_string()];
         if !script_args.is_empty() {
-            args.push("--".to_string());
+            if self == Self::Npm {
+                args.push("--".to_string());
+            }
             args.extend(script_args.iter().cloned());
         }
         args
     }
 
+    fn workspace_args(self, filter: &str) -> Result<Vec<String>> {
+        match self {
+            Self::Pnpm => Ok(vec!["--filter".to_string(), filter.to_string()]),
+            Self::Yarn => Ok(vec!["workspace".to_string(), filter.to_string()]),
+            Self::Npm => Ok(vec!["-w".to_string(), filter.to_string()]),
+            Self::Bun => bail!(
+                "bun does not support targeting a workspace package from qbit. Run the command inside the package directory instead."
+            ),
+        }
+    }
+
+    fn registry_args(self, url: &str) -> Vec<String> {
+        match self {
+            Self::Yarn => vec!["--registry".to_string(), url.to_string()],
+            Self::Npm | Self::Pnpm | Self::Bun => vec![format!("--registry={url}")],
+        }
+    }
+
+    fn list_args(self) -> Vec<String> {
+        let args: &[&str] = match self {
+            Self::Npm => &["ls", "--depth=0"],
+            Self::Pnpm => &["list", "--depth=0"],
+            Self::Yarn => &["list", "--depth=0"],
+            Self::Bun => &["pm", "ls"],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
     fn add_verb(self) -> &'static str {
         match self {
             Self::Npm => "install",
@@ -391,6 +805,15 @@ impl JsPackageManager {
         }
     }
 
+    fn upgrade_verb(self) -> &'static str {
+        match self {
+            Self::Npm => "update",
+            Self::Pnpm => "update",
+            Self::Yarn => "upgrade",
+            Self::Bun => "update",
+        }
+    }
+
     fn remove_verb(self) -> &'static str {
         match self {
             Self::Npm => "uninstall",
@@ -413,62 +836,7 @@ mod tests {
     use tempfile::tempdir;
 
     use super::*;
-
-    struct EnvGuard {
-        key: &'static str,
-        original: Option<OsString>,
-    }
-
-    impl EnvGuard {
-        fn set(key: &'static str, value: impl AsRef<std::ffi::OsStr>) -> Self {
-            let original = std::env::var_os(key);
-            // SAFETY: tests that mutate env vars use `#[serial]`, so there is no
-            // concurrent mutation in this process.
-            unsafe { std::env::set_var(key, value) };
-            Self { key, original }
-        }
-
-        fn remove(key: &'static str) -> Self {
-            let original = std::env::var_os(key);
-            // SAFETY: tests that mutate env vars use `#[serial]`, so there is no
-            // concurrent mutation in this process.
-            unsafe { std::env::remove_var(key) };
-            Self { key, original }
-        }
-    }
-
-    impl Drop for EnvGuard {
-        fn drop(&mut self) {
-            match &self.original {
-                Some(value) => {
-                    // SAFETY: restoration runs in the same serial test context.
-                    unsafe { std::env::set_var(self.key, value) };
-                }
-                None => {
-                    // SAFETY: restoration runs in the same serial test context.
-                    unsafe { std::env::remove_var(self.key) };
-                }
-            }
-        }
-    }
-
-    struct CwdGuard {
-        original: PathBuf,
-    }
-
-    impl CwdGuard {
-        fn set(path: &Path) -> Self {
-            let original = std::env::current_dir().expect("current dir");
-            std::env::set_current_dir(path).expect("set current dir");
-            Self { original }
-        }
-    }
-
-    impl Drop for CwdGuard {
-        fn drop(&mut self) {
-            let _ = std::env::set_current_dir(&self.original);
-        }
-    }
+    use crate::utils::test_env::{CwdGuard, EnvGuard};
 
     fn set_fake_path(fakebin: &Path) -> EnvGuard {
         let mut path = OsString::from(fakebin.as_os_str());
@@ -535,9 +903,119 @@ exit 0
         assert_eq!(JsPackageManager::Yarn.remove_verb(), "remove");
     }
 
+    #[test]
+    fn upgrade_command_uses_manager_specific_verb() {
+        let expected = [
+            (JsPackageManager::Npm, "update"),
+            (JsPackageManager::Pnpm, "update"),
+            (JsPackageManager::Yarn, "upgrade"),
+            (JsPackageManager::Bun, "update"),
+        ];
+        for (pm, verb) in expected {
+            let command = build_upgrade_command(pm, "react", None).expect("command");
+            assert_eq!(command.args, vec![verb.to_string(), "react".to_string()]);
+        }
+        assert!(build_upgrade_command(JsPackageManager::Npm, "  ", None).is_err());
+    }
+
+    #[test]
+    fn npm_add_includes_registry_flag_and_keeps_scope() {
+        let command = build_add_command(
+            JsPackageManager::Npm,
+            "@acme/thing",
+            Some("https://npm.acme.test"),
+        )
+        .expect("command");
+        assert_eq!(
+            command.args,
+            vec![
+                "install".to_string(),
+                "@acme/thing".to_string(),
+                "--registry=https://npm.acme.test".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn yarn_registry_flag_takes_separate_value() {
+        let command = build_add_command(
+            JsPackageManager::Yarn,
+            "@acme/thing",
+            Some("https://npm.acme.test"),
+        )
+        .expect("command");
+        assert_eq!(
+            command.render(),
+            "yarn add @acme/thing --registry https://npm.acme.test"
+        );
+    }
+
+    #[test]
+    fn pnpm_workspace_filter_precedes_subcommand() {
+        let command = with_workspace(
+            build_add_command(JsPackageManager::Pnpm, "react", None).expect("command"),
+            Some("web"),
+        )
+        .expect("workspace");
+        assert_eq!(command.render(), "pnpm --filter web add react");
+    }
+
+    #[test]
+    fn yarn_workspace_uses_workspace_command() {
+        let command = with_workspace(
+            build_run_command(JsPackageManager::Yarn, "build", &[]).expect("command"),
+            Some("web"),
+        )
+        .expect("workspace");
+        assert_eq!(command.render(), "yarn workspace web run build");
+    }
+
+    #[test]
+    fn bun_workspace_filter_is_rejected() {
+        let err = with_workspace(
+            build_add_command(JsPackageManager::Bun, "react", None).expect("command"),
+            Some("web"),
+        )
+        .expect_err("must fail");
+        assert!(err.to_string().contains("bun does not support"));
+    }
+
+    #[test]
+    fn detects_workspace_from_package_json_field() {
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("package.json"), "{}").expect("package.json");
+        assert!(!is_workspace_root(tmp.path()));
+        fs::write(
+            tmp.path().join("package.json"),
+            r#"{"workspaces": ["packages/*"]}"#,
+        )
+        .expect("package.json");
+        assert!(is_workspace_root(tmp.path()));
+    }
+
+    #[test]
+    fn list_command_matches_each_manager() {
+        assert_eq!(
+            build_list_command(JsPackageManager::Npm).render(),
+            "npm ls --depth=0"
+        );
+        assert_eq!(
+            build_list_command(JsPackageManager::Pnpm).render(),
+            "pnpm list --depth=0"
+        );
+        assert_eq!(
+            build_list_command(JsPackageManager::Yarn).render(),
+            "yarn list --depth=0"
+        );
+        assert_eq!(
+            build_list_command(JsPackageManager::Bun).render(),
+            "bun pm ls"
+        );
+    }
+
     #[test]
     fn run_args_forward_extra_args_after_separator() {
-        let args = JsPackageManager::Pnpm.run_args(
+        let args = JsPackageManager::Npm.run_args(
             "build",
             &[
                 "--watch".to_string(),
@@ -558,6 +1036,31 @@ exit 0
         );
     }
 
+    #[test]
+    fn run_args_skip_separator_for_pnpm_yarn_and_bun() {
+        let extra = ["--watch".to_string()];
+        for pm in [
+            JsPackageManager::Pnpm,
+            JsPackageManager::Yarn,
+            JsPackageManager::Bun,
+        ] {
+            assert_eq!(
+                pm.run_args("build", &extra),
+                vec![
+                    "run".to_string(),
+                    "build".to_string(),
+                    "--watch".to_string()
+                ],
+                "{}",
+                pm.name()
+            );
+        }
+        assert_eq!(
+            JsPackageManager::Npm.run_args("build", &[]),
+            vec!["run".to_string(), "build".to_string()]
+        );
+    }
+
     #[test]
     fn lockfile_priority_prefers_bun_over_others() {
         let resolved = lockfile_priority()
@@ -603,7 +1106,7 @@ exit 0
             |_| true,
         )
         .expect("must resolve");
-        let command = build_add_command(pm, "axios").expect("command");
+        let command = build_add_command(pm, "axios", None).expect("command");
         assert_eq!(command.pm, JsPackageManager::Pnpm);
         assert_eq!(command.args, vec!["add".to_string(), "axios".to_string()]);
     }
@@ -653,9 +1156,9 @@ exit 0
         let _path = set_fake_path(&fakebin);
         let _log = EnvGuard::set("QBIT_FAKE_LOG", log_path.as_os_str());
 
-        add_package("left-pad").expect("add package");
-        remove_package("left-pad").expect("remove package");
-        run_script("build", &["--watch".to_string()]).expect("run script");
+        add_package(None, Some("left-pad"), None, false).expect("add package");
+        remove_package("left-pad", None).expect("remove package");
+        run_script("build", &["--watch".to_string()], None).expect("run script");
 
         let log = read_log(&log_path);
         assert!(log.contains("install left-pad"), "log was: {log}");
@@ -663,13 +1166,173 @@ exit 0
         assert!(log.contains("run build -- --watch"), "log was: {log}");
     }
 
+    #[test]
+    #[serial]
+    fn add_without_package_in_locked_repo_runs_npm_ci() {
+        let tmp = tempdir().expect("tempdir");
+        let _cwd = CwdGuard::set(tmp.path());
+
+        fs::write("package.json", "{}\n").expect("package.json");
+        fs::write("package-lock.json", "{}\n").expect("package-lock.json");
+        let log_path = tmp.path().join("pm.log");
+        let fakebin = tmp.path().join("fakebin");
+        create_fake_pm_executable(&fakebin, "npm");
+
+        let _pm = EnvGuard::remove("QBIT_JS_PM");
+        let _registry = EnvGuard::remove("QBIT_JS_REGISTRY");
+        let _path = set_fake_path(&fakebin);
+        let _log = EnvGuard::set("QBIT_FAKE_LOG", log_path.as_os_str());
+
+        add_package(None, None, None, false).expect("install from lockfile");
+
+        let log = read_log(&log_path);
+        assert!(log.lines().any(|line| line == "ci"), "log was: {log}");
+    }
+
+    #[test]
+    #[serial]
+    fn frozen_add_pins_locked_version_and_requires_lockfile() {
+        let tmp = tempdir().expect("tempdir");
+        let _cwd = CwdGuard::set(tmp.path());
+
+        let err = locked_package_spec(JsPackageManager::Npm, "react").expect_err("no lockfile");
+        assert!(err.to_string().contains("package-lock.json"), "{err}");
+        assert!(
+            build_install_command(
+                JsPackageManager::Npm,
+                false,
+                true,
+                DependencyScope::All,
+                None
+            )
+            .is_err()
+        );
+
+        fs::write(
+            "package-lock.json",
+            r#"{"packages": {"node_modules/react": {"version": "18.2.0"}}}"#,
+        )
+        .expect("package-lock.json");
+        assert_eq!(
+            locked_package_spec(JsPackageManager::Npm, "react").expect("locked"),
+            "react@18.2.0"
+        );
+        assert!(locked_package_spec(JsPackageManager::Npm, "react@19").is_err());
+        assert!(locked_package_spec(JsPackageManager::Npm, "vue").is_err());
+    }
+
+    #[test]
+    fn reads_locked_versions_from_each_lockfile_format() {
+        let npm_v1 = r#"{"dependencies": {"@types/node": {"version": "20.1.0"}}}"#;
+        assert_eq!(
+            locked_version(JsPackageManager::Npm, npm_v1, "@types/node").expect("npm"),
+            Some("20.1.0".to_string())
+        );
+
+        let pnpm = "lockfileVersion: '9.0'\nimporters:\n  .:\n    dependencies:\n      react-dom:\n        specifier: ^18.2.0\n        version: 18.2.0(react@18.2.0)\n";
+        assert_eq!(
+            locked_version(JsPackageManager::Pnpm, pnpm, "react-dom").expect("pnpm"),
+            Some("18.2.0".to_string())
+        );
+        let pnpm_v5 = "lockfileVersion: 5.4\ndependencies:\n  react: 17.0.2\n";
+        assert_eq!(
+            locked_version(JsPackageManager::Pnpm, pnpm_v5, "react").expect("pnpm v5"),
+            Some("17.0.2".to_string())
+        );
+
+        let yarn = "# yarn lockfile v1\n\n\"@babel/core@^7.0.0\", \"@babel/core@^7.1.0\":\n  version \"7.2.0\"\n\nreact@^18.2.0:\n  version \"18.2.0\"\n";
+        assert_eq!(
+            yarn_locked_version(yarn, "react"),
+            Some("18.2.0".to_string())
+        );
+        assert_eq!(
+            yarn_locked_version(yarn, "@babel/core"),
+            Some("7.2.0".to_string())
+        );
+        let berry =
+            "\"react@npm:^18.2.0\":\n  version: 18.2.0\n  resolution: \"react@npm:18.2.0\"\n";
+        assert_eq!(
+            yarn_locked_version(berry, "react"),
+            Some("18.2.0".to_string())
+        );
+        assert_eq!(yarn_locked_version(berry, "vue"), None);
+    }
+
+    #[test]
+    fn ci_args_match_each_manager() {
+        assert_eq!(JsPackageManager::Npm.ci_args(), vec!["ci"]);
+        assert_eq!(
+            JsPackageManager::Pnpm.ci_args(),
+            vec!["install", "--frozen-lockfile"]
+        );
+        assert_eq!(JsPackageManager::Yarn.lockfile(), "yarn.lock");
+    }
+
+    #[test]
+    fn install_scope_maps_to_each_managers_flags() {
+        let args = |pm, locked, scope| {
+            build_install_command(pm, locked, false, scope, None)
+                .map(|command| command.args)
+                .expect("install command")
+        };
+        use DependencyScope::{All, Dev, Production};
+        use JsPackageManager::{Bun, Npm, Pnpm, Yarn};
+
+        assert_eq!(args(Npm, true, All), vec!["ci"]);
+        assert_eq!(args(Npm, true, Production), vec!["ci", "--omit=dev"]);
+        assert_eq!(args(Npm, false, Dev), vec!["install", "--include=dev"]);
+        assert_eq!(
+            args(Pnpm, true, Production),
+            vec!["install", "--frozen-lockfile", "--prod"]
+        );
+        assert_eq!(args(Pnpm, false, Dev), vec!["install", "--dev"]);
+        assert_eq!(
+            args(Yarn, true, Production),
+            vec!["install", "--frozen-lockfile", "--production"]
+        );
+        assert_eq!(
+            args(Bun, false, Production),
+            vec!["install", "--production"]
+        );
+
+        for pm in [Yarn, Bun] {
+            let err = build_install_command(pm, true, false, Dev, None).expect_err("no dev-only");
+            assert!(err.to_string().contains("--only=dev"), "{err}");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn production_install_runs_npm_ci_without_dev_dependencies() {
+        let tmp = tempdir().expect("tempdir");
+        let _cwd = CwdGuard::set(tmp.path());
+        fs::write("package.json", "{}").expect("package.json");
+        fs::write("package-lock.json", "{}").expect("package-lock.json");
+        let fakebin = tmp.path().join("fakebin");
+        let log_path = tmp.path().join("pm.log");
+        create_fake_pm_executable(&fakebin, "npm");
+
+        let _pm = EnvGuard::remove("QBIT_JS_PM");
+        let _registry = EnvGuard::remove("QBIT_JS_REGISTRY");
+        let _path = set_fake_path(&fakebin);
+        let _log = EnvGuard::set("QBIT_FAKE_LOG", log_path.as_os_str());
+
+        install_dependencies(None, DependencyScope::Production, None, false).expect("prod install");
+
+        let log = read_log(&log_path);
+        assert!(
+            log.lines().any(|line| line == "ci --omit=dev"),
+            "log was: {log}"
+        );
+    }
+
     #[test]
     #[serial]
     fn snapshot_generated_qbit_template() {
         let tmp = tempdir().expect("tempdir");
         let _cwd = CwdGuard::set(tmp.path());
 
-        ensure_project_config_file().expect("write template");
+        ensure_project_config_file(CONFIG_TEMPLATE).expect("write template");
         let content = fs::read_to_string("qbit.yml")
             .expect("template content")
             .replace("\r\n", "\n");
@@ -1,4 +1,8 @@
 pub mod common;
 pub mod dart;
+pub mod dotnet;
+pub mod java;
 pub mod js;
+pub mod php;
 pub mod py;
+pub mod ruby;
@@ -0,0 +1,127 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use crate::developers::common::{ToolCommand, parse_name_only, parse_name_version, require_tool};
+
+const FAILURE_HINT: &str = "Check that composer.json is valid and retry.";
+
+/// Create composer.json with `composer init --no-interaction`.
+pub fn init() -> Result<()> {
+    if Path::new("composer.json").exists() {
+        println!("composer.json already exists");
+        return Ok(());
+    }
+
+    ensure_composer_available()?;
+    build_init_command().run(FAILURE_HINT)?;
+    println!("PHP project initialized with Composer.");
+    Ok(())
+}
+
+/// Add a package with `composer require`; `pkg:1.2` pins `^1.2`.
+pub fn add_package(package: &str) -> Result<()> {
+    ensure_composer_available()?;
+    ensure_composer_json_exists()?;
+    build_require_command(package)?.run(FAILURE_HINT)?;
+    println!("Package `{package}` added via Composer.");
+    Ok(())
+}
+
+pub fn remove_package(package: &str) -> Result<()> {
+    ensure_composer_available()?;
+    ensure_composer_json_exists()?;
+    build_remove_command(package)?.run(FAILURE_HINT)?;
+    println!("Package `{package}` removed via Composer.");
+    Ok(())
+}
+
+fn ensure_composer_json_exists() -> Result<()> {
+    if !Path::new("composer.json").exists() {
+        bail!("No composer.json found in the current directory. Run `qbit php init` first.");
+    }
+    Ok(())
+}
+
+fn build_init_command() -> ToolCommand {
+    ToolCommand::new("composer", ["init", "--no-interaction"])
+}
+
+fn build_require_command(package: &str) -> Result<ToolCommand> {
+    let (name, version) = parse_name_version(package, "package")?;
+    let requirement = match version {
+        Some(version) => format!("{name}:{}", version_constraint(version)),
+        None => name.to_string(),
+    };
+    Ok(ToolCommand::new(
+        "composer",
+        ["require".to_string(), requirement],
+    ))
+}
+
+fn build_remove_command(package: &str) -> Result<ToolCommand> {
+    let name = parse_name_only(package, "package", "composer remove")?;
+    Ok(ToolCommand::new("composer", ["remove", name]))
+}
+
+/// A bare version becomes a caret constraint; explicit constraints pass through.
+fn version_constraint(version: &str) -> String {
+    if version.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("^{version}")
+    } else {
+        version.to_string()
+    }
+}
+
+fn ensure_composer_available() -> Result<()> {
+    require_tool(
+        "composer",
+        "Install it with `qbit install composer` (or from https://getcomposer.org) and retry.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_composer_init_command() {
+        assert_eq!(
+            build_init_command().render(),
+            "composer init --no-interaction"
+        );
+    }
+
+    #[test]
+    fn builds_composer_require_command() {
+        let command = build_require_command("monolog/monolog").expect("build command");
+        assert_eq!(
+            command.args,
+            vec!["require".to_string(), "monolog/monolog".to_string()]
+        );
+    }
+
+    #[test]
+    fn builds_composer_require_command_with_caret_pin() {
+        let command = build_require_command("monolog/monolog:1.2").expect("build command");
+        assert_eq!(
+            command.args,
+            vec!["require".to_string(), "monolog/monolog:^1.2".to_string()]
+        );
+    }
+
+    #[test]
+    fn keeps_explicit_version_constraints() {
+        let command = build_require_command("monolog/monolog:~2.0").expect("build command");
+        assert_eq!(command.args[1], "monolog/monolog:~2.0");
+    }
+
+    #[test]
+    fn builds_composer_remove_command() {
+        let command = build_remove_command("monolog/monolog").expect("build command");
+        assert_eq!(
+            command.args,
+            vec!["remove".to_string(), "monolog/monolog".to_string()]
+        );
+    }
+}
@@ -1,10 +1,20 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use anyhow::{Context, Result, bail};
 
-use crate::utils::python::find_python;
+use crate::config::{LoadedProjectConfig, RequirementsMode};
+use crate::developers::common::require_tool;
+use crate::error::QbitError;
+use crate::utils::paths::find_executable;
+use crate::utils::python::{find_python, venv_bin_dir, venv_env};
+use crate::utils::retry::run_with_retries;
+
+/// Pinned snapshot written by `qbit py lock`, next to requirements.txt.
+const LOCK_FILE: &str = "requirements.lock";
 
 /// Initialize Python project (requirements.txt + venv)
 pub fn init() -> Result<()> {
@@ -24,25 +34,212 @@ pub fn init() -> Result<()> {
 }
 
 /// Install a dependency inside the managed venv and refresh requirements.txt.
-pub fn add_package(package: &str) -> Result<()> {
+pub fn add_package(config: Option<&LoadedProjectConfig>, package: &str) -> Result<()> {
     ensure_requirements()?;
     let interpreter = resolve_and_prepare_python()?;
     pip_install(&interpreter, package)?;
-    refresh_requirements(&interpreter)?;
+    sync_requirements_after_install(config, &interpreter, package)?;
     println!("Package `{package}` installed and requirements.txt updated.");
     Ok(())
 }
 
+/// Install a package for the current user (`pip install --user`) instead of
+/// into the project venv; requirements.txt is left alone.
+pub fn add_user_package(package: &str) -> Result<()> {
+    if let Some(venv) = std::env::var_os("VIRTUAL_ENV") {
+        bail!(
+            "--user can't be used inside a virtualenv ({}); deactivate it first or drop --user.",
+            PathBuf::from(venv).display()
+        );
+    }
+    let Some(py) = find_python() else {
+        eprintln!("Python not found.");
+        eprintln!("Hint: install it via `qbit install python`");
+        bail!("python interpreter not available");
+    };
+    let (bin, prefix) = split_first(&py);
+    println!("Installing `{package}` for the current user via pip...");
+    let status = Command::new(bin)
+        .args(prefix)
+        .args(pip_install_args(package, true))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .context("running pip install --user")?;
+
+    if !status.success() {
+        bail!("pip install --user failed for `{package}`");
+    }
+    println!("Package `{package}` installed for the current user.");
+    Ok(())
+}
+
 /// Remove a dependency inside the managed venv and refresh requirements.txt.
-pub fn remove_package(package: &str) -> Result<()> {
+pub fn remove_package(config: Option<&LoadedProjectConfig>, package: &str) -> Result<()> {
     ensure_requirements()?;
     let interpreter = resolve_and_prepare_python()?;
     pip_remove(&interpreter, package)?;
-    refresh_requirements(&interpreter)?;
+    match requirements_mode(config) {
+        RequirementsMode::Freeze => refresh_requirements(&interpreter)?,
+        RequirementsMode::Managed => {
+            let content = read_requirements()?;
+            write_requirements(&remove_requirement(&content, package))?;
+        }
+    }
     println!("Package `{package}` removed (if installed) and requirements.txt updated.");
     Ok(())
 }
 
+/// Upgrade a dependency inside the managed venv and refresh requirements.txt.
+pub fn upgrade_package(config: Option<&LoadedProjectConfig>, package: &str) -> Result<()> {
+    ensure_requirements()?;
+    let interpreter = resolve_and_prepare_python()?;
+    pip_upgrade(&interpreter, package)?;
+    sync_requirements_after_install(config, &interpreter, package)?;
+    println!("Package `{package}` upgraded and requirements.txt updated.");
+    Ok(())
+}
+
+/// Materialize the venv from an existing requirements.txt.
+pub fn sync() -> Result<()> {
+    let backend = detect_sync_backend(Path::new("."))?;
+    let program = match backend {
+        SyncBackend::Pip => resolve_and_prepare_python()?,
+        SyncBackend::Uv | SyncBackend::Poetry => {
+            require_tool(
+                backend.tool(),
+                &format!(
+                    "Install {} or remove {} to sync from requirements.txt with pip.",
+                    backend.tool(),
+                    backend.source()
+                ),
+            )?;
+            PathBuf::from(backend.tool())
+        }
+    };
+    let label = backend.label();
+    println!("Installing dependencies from {}...", backend.source());
+    let status = Command::new(&program)
+        .args(build_sync_args(backend))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .with_context(|| format!("running {label}"))?;
+
+    if !status.success() {
+        bail!("{label} failed (exit code {})", status.code().unwrap_or(1));
+    }
+    println!("Environment synced with {}.", backend.source());
+    Ok(())
+}
+
+/// List packages installed in the managed venv via `pip list`.
+pub fn list_packages() -> Result<()> {
+    let python = existing_venv_python()?;
+    let args = pip_list_args();
+    let status = Command::new(&python)
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .context("running pip list")?;
+
+    if !status.success() {
+        bail!("pip list failed (exit code {})", status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+/// Print the venv's installed packages (`pip freeze`) without writing any file.
+pub fn freeze() -> Result<()> {
+    let python = existing_venv_python()?;
+    let frozen = pip_freeze(&python)?;
+    io::stdout()
+        .write_all(&frozen)
+        .context("writing pip freeze output")?;
+    Ok(())
+}
+
+/// Pin the venv's exact packages into requirements.lock, with sha256 hashes
+/// when pip can report them. requirements.txt stays the hand-edited source.
+pub fn lock() -> Result<()> {
+    let python = existing_venv_python()?;
+    println!("Freezing the venv via `pip freeze`...");
+    let frozen = String::from_utf8_lossy(&pip_freeze(&python)?).into_owned();
+    let hashes = resolve_hashes(&python, &frozen);
+    fs::write(LOCK_FILE, render_lock(&frozen, hashes.as_ref()))
+        .with_context(|| format!("writing {LOCK_FILE}"))?;
+    if hashes.is_some() {
+        println!(
+            "Wrote {LOCK_FILE} with hashes. Install it with `pip install --require-hashes -r {LOCK_FILE}`."
+        );
+    } else {
+        println!(
+            "Wrote {LOCK_FILE} without hashes (pip could not report them); it still pins exact versions."
+        );
+    }
+    Ok(())
+}
+
+/// Open an interactive shell with ./venv activated, creating the venv first
+/// when it isn't ready. The shell's exit code becomes qbit's.
+pub fn shell() -> Result<()> {
+    let venv = Path::new("venv");
+    if venv_state(venv) != VenvState::Ready {
+        resolve_and_prepare_python()?;
+    }
+    let venv = venv.canonicalize().context("resolving ./venv")?;
+    let program = interactive_shell();
+    let mut command = venv_shell_command(&program, &venv)?;
+    println!(
+        "Starting {program} with {} activated; type `exit` to leave.",
+        venv.display()
+    );
+    let status = command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .with_context(|| format!("starting shell `{program}`"))?;
+    if !status.success() {
+        return Err(QbitError::CommandFailed {
+            command: program,
+            code: status.code(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// The user's shell: `$SHELL` (else `/bin/sh`) on Unix; on Windows `pwsh`
+/// when installed, else `%COMSPEC%` (normally `cmd.exe`).
+fn interactive_shell() -> String {
+    let from_env = |key: &str| {
+        std::env::var(key)
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    };
+    if cfg!(windows) {
+        if find_executable("pwsh").is_some() {
+            return "pwsh".to_string();
+        }
+        from_env("COMSPEC").unwrap_or_else(|| "cmd.exe".to_string())
+    } else {
+        from_env("SHELL").unwrap_or_else(|| "/bin/sh".to_string())
+    }
+}
+
+/// `program` with the venv activated the way its `activate` script would:
+/// venv executables first on PATH, `VIRTUAL_ENV` set, `PYTHONHOME` cleared.
+fn venv_shell_command(program: &str, venv: &Path) -> Result<Command> {
+    let mut command = Command::new(program);
+    command.envs(venv_env(venv)?).env_remove("PYTHONHOME");
+    Ok(command)
+}
+
 fn ensure_requirements() -> Result<()> {
     if !Path::new("requirements.txt").exists() {
         fs::write("requirements.txt", b"# pin your dependencies here\n")
@@ -55,15 +252,59 @@ fn ensure_requirements() -> Result<()> {
 }
 
 fn ensure_venv(py: &str) -> Result<()> {
-    if Path::new("venv").exists() {
-        println!("venv already exists");
-        return Ok(());
+    ensure_venv_at(py, Path::new("venv"))
+}
+
+/// What's at the venv path before `qbit` uses it.
+#[derive(Debug, PartialEq, Eq)]
+enum VenvState {
+    Missing,
+    Ready,
+    /// A venv (it has `pyvenv.cfg`) whose interpreter is missing, e.g. after an
+    /// interrupted `python -m venv`.
+    Incomplete,
+    /// Something that isn't a venv; never deleted.
+    Foreign,
+}
+
+fn venv_state(venv: &Path) -> VenvState {
+    if !venv.exists() {
+        VenvState::Missing
+    } else if venv_python_in(venv).exists() {
+        VenvState::Ready
+    } else if venv.join("pyvenv.cfg").is_file() {
+        VenvState::Incomplete
+    } else {
+        VenvState::Foreign
+    }
+}
+
+fn ensure_venv_at(py: &str, venv: &Path) -> Result<()> {
+    match venv_state(venv) {
+        VenvState::Ready => {
+            println!("venv already exists");
+            return Ok(());
+        }
+        VenvState::Incomplete => {
+            println!(
+                "venv at {} is incomplete (no interpreter); recreating it...",
+                venv.display()
+            );
+            fs::remove_dir_all(venv)
+                .with_context(|| format!("removing incomplete venv at {}", venv.display()))?;
+        }
+        VenvState::Foreign => bail!(
+            "{} exists but is not a virtualenv (no pyvenv.cfg or interpreter). Move or remove it, then retry.",
+            venv.display()
+        ),
+        VenvState::Missing => {}
     }
 
+    let venv_arg = venv.to_string_lossy();
     let (bin, mut args) = split_first(py);
     args.push("-m");
     args.push("venv");
-    args.push("venv");
+    args.push(&venv_arg);
 
     println!("Creating venv...");
     let status = Command::new(bin)
@@ -82,7 +323,7 @@ fn ensure_venv(py: &str) -> Result<()> {
         ));
     }
 
-    println!("venv created at ./venv");
+    println!("venv created at {}", venv.display());
     Ok(())
 }
 
@@ -100,30 +341,129 @@ fn resolve_and_prepare_python() -> Result<PathBuf> {
     Ok(venv_python)
 }
 
+fn existing_venv_python() -> Result<PathBuf> {
+    let venv_python = venv_python_path();
+    if !venv_python.exists() {
+        bail!(
+            "No virtualenv found at ./venv (expected {}). Run `qbit py init` first.",
+            venv_python.display()
+        );
+    }
+    Ok(venv_python)
+}
+
+/// Where `qbit py sync` installs from: a uv or Poetry lockfile, which that
+/// tool installs into its own environment, or requirements.txt into ./venv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncBackend {
+    Uv,
+    Poetry,
+    Pip,
+}
+
+impl SyncBackend {
+    fn source(self) -> &'static str {
+        match self {
+            Self::Uv => "uv.lock",
+            Self::Poetry => "poetry.lock",
+            Self::Pip => "requirements.txt",
+        }
+    }
+
+    fn tool(self) -> &'static str {
+        match self {
+            Self::Uv => "uv",
+            Self::Poetry => "poetry",
+            Self::Pip => "pip",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Uv => "uv sync",
+            Self::Poetry => "poetry install",
+            Self::Pip => "pip install -r requirements.txt",
+        }
+    }
+}
+
+/// The first of uv.lock, poetry.lock, and requirements.txt found in `root`.
+fn detect_sync_backend(root: &Path) -> Result<SyncBackend> {
+    [SyncBackend::Uv, SyncBackend::Poetry, SyncBackend::Pip]
+        .into_iter()
+        .find(|backend| root.join(backend.source()).exists())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No requirements.txt, uv.lock, or poetry.lock found in {}. Run `qbit py init` or add one before syncing.",
+                root.display()
+            )
+        })
+}
+
+fn build_sync_args(backend: SyncBackend) -> Vec<String> {
+    let args: &[&str] = match backend {
+        SyncBackend::Uv => &["sync"],
+        SyncBackend::Poetry => &["install"],
+        SyncBackend::Pip => &["-m", "pip", "install", "-r", "requirements.txt"],
+    };
+    args.iter().map(|s| s.to_string()).collect()
+}
+
+fn pip_list_args() -> Vec<String> {
+    ["-m", "pip", "list"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
 fn venv_python_path() -> PathBuf {
+    venv_python_in(Path::new("venv"))
+}
+
+fn venv_python_in(venv: &Path) -> PathBuf {
+    let bin = venv_bin_dir(venv);
     if cfg!(windows) {
-        Path::new("venv").join("Scripts").join("python.exe")
+        bin.join("python.exe")
     } else {
-        Path::new("venv").join("bin").join("python")
+        bin.join("python")
     }
 }
 
 fn pip_install(python: &Path, package: &str) -> Result<()> {
     println!("Installing `{package}` via pip...");
-    let status = Command::new(python)
-        .args(["-m", "pip", "install", package])
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status()
-        .context("running pip install")?;
+    let run = run_with_retries("pip install", || {
+        let mut command = Command::new(python);
+        command
+            .args(pip_install_args(package, false))
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit());
+        command
+    })?;
 
-    if !status.success() {
+    if !run.status.success() {
+        if run.network_error {
+            bail!(
+                "pip install failed for `{package}` after {} attempts because the package index looks unreachable. Check your network or proxy settings and retry.",
+                run.attempts
+            );
+        }
         bail!("pip install failed for `{package}`");
     }
     Ok(())
 }
 
+fn pip_install_args(package: &str, user: bool) -> Vec<String> {
+    let mut args: Vec<String> = ["-m", "pip", "install"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if user {
+        args.push("--user".to_string());
+    }
+    args.push(package.to_string());
+    args
+}
+
 fn pip_remove(python: &Path, package: &str) -> Result<()> {
     println!("Removing `{package}` via pip...");
     let status = Command::new(python)
@@ -140,8 +480,37 @@ fn pip_remove(python: &Path, package: &str) -> Result<()> {
     Ok(())
 }
 
+fn pip_upgrade(python: &Path, package: &str) -> Result<()> {
+    println!("Upgrading `{package}` via pip...");
+    let status = Command::new(python)
+        .args(pip_upgrade_args(package))
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()
+        .context("running pip install --upgrade")?;
+
+    if !status.success() {
+        bail!("pip upgrade failed for `{package}`");
+    }
+    Ok(())
+}
+
+fn pip_upgrade_args(package: &str) -> Vec<String> {
+    ["-m", "pip", "install", "--upgrade", package]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
 fn refresh_requirements(python: &Path) -> Result<()> {
     println!("Syncing requirements.txt via `pip freeze`...");
+    let frozen = pip_freeze(python)?;
+    fs::write("requirements.txt", frozen).context("writing requirements.txt from freeze")?;
+    Ok(())
+}
+
+fn pip_freeze(python: &Path) -> Result<Vec<u8>> {
     let output = Command::new(python)
         .args(["-m", "pip", "freeze"])
         .stdin(Stdio::null())
@@ -153,9 +522,234 @@ fn refresh_requirements(python: &Path) -> Result<()> {
     if !output.status.success() {
         bail!("pip freeze failed");
     }
+    Ok(output.stdout)
+}
 
-    fs::write("requirements.txt", output.stdout).context("writing requirements.txt from freeze")?;
-    Ok(())
+/// sha256 per (normalized) package name for every pin in `frozen`, taken from
+/// the report of a `pip install --dry-run` that resolves the same pins. `None`
+/// when any line can't be hashed (editable/VCS installs, old pip, offline).
+fn resolve_hashes(python: &Path, frozen: &str) -> Option<HashMap<String, String>> {
+    let pins: Vec<&str> = frozen
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if pins.is_empty() || !pins.iter().all(|pin| is_exact_pin(pin)) {
+        return None;
+    }
+
+    let output = Command::new(python)
+        .args(pip_hash_report_args(&pins))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hashes = parse_report_hashes(&String::from_utf8_lossy(&output.stdout))?;
+    pins.iter()
+        .all(|pin| hashes.contains_key(&normalize_requirement_name(requirement_name(pin))))
+        .then_some(hashes)
+}
+
+/// `name==version` with nothing else on the line.
+fn is_exact_pin(line: &str) -> bool {
+    line.split_once("==").is_some_and(|(name, version)| {
+        !name.trim().is_empty() && !version.trim().is_empty() && !line.contains([' ', ';', '@'])
+    })
+}
+
+fn pip_hash_report_args(pins: &[&str]) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-m",
+        "pip",
+        "install",
+        "--dry-run",
+        "--ignore-installed",
+        "--no-deps",
+        "--quiet",
+        "--report",
+        "-",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    args.extend(pins.iter().map(|pin| pin.to_string()));
+    args
+}
+
+/// Pull `name -> sha256` out of a `pip install --report` JSON document.
+fn parse_report_hashes(report: &str) -> Option<HashMap<String, String>> {
+    let report: serde_json::Value = serde_json::from_str(report).ok()?;
+    let mut hashes = HashMap::new();
+    for item in report.get("install")?.as_array()? {
+        let name = item.pointer("/metadata/name")?.as_str()?;
+        let Some(sha256) = item
+            .pointer("/download_info/archive_info/hashes/sha256")
+            .and_then(|hash| hash.as_str())
+        else {
+            continue;
+        };
+        hashes.insert(normalize_requirement_name(name), sha256.to_string());
+    }
+    Some(hashes)
+}
+
+fn render_lock(frozen: &str, hashes: Option<&HashMap<String, String>>) -> String {
+    let mut out =
+        String::from("# Generated by `qbit py lock` from ./venv; edit requirements.txt instead.\n");
+    match hashes {
+        Some(_) => out.push_str(&format!(
+            "# Install with `pip install --require-hashes -r {LOCK_FILE}`.\n"
+        )),
+        None => out.push_str(
+            "# No hashes: pip could not report them. Add `--hash=sha256:...` to every line\n# (e.g. via `pip hash`) before installing with `--require-hashes`.\n",
+        ),
+    }
+    for line in frozen
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let hash = hashes
+            .and_then(|hashes| hashes.get(&normalize_requirement_name(requirement_name(line))));
+        match hash {
+            Some(sha256) => out.push_str(&format!("{line} --hash=sha256:{sha256}\n")),
+            None => {
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+fn requirements_mode(config: Option<&LoadedProjectConfig>) -> RequirementsMode {
+    config
+        .map(|cfg| cfg.data.python.requirements_mode)
+        .unwrap_or_default()
+}
+
+fn sync_requirements_after_install(
+    config: Option<&LoadedProjectConfig>,
+    python: &Path,
+    package: &str,
+) -> Result<()> {
+    match requirements_mode(config) {
+        RequirementsMode::Freeze => refresh_requirements(python),
+        RequirementsMode::Managed => {
+            println!("Updating `{package}` in requirements.txt (managed mode)...");
+            let version = installed_version(python, requirement_name(package));
+            let content = read_requirements()?;
+            write_requirements(&upsert_requirement(&content, package, version.as_deref()))
+        }
+    }
+}
+
+fn read_requirements() -> Result<String> {
+    fs::read_to_string("requirements.txt").context("reading requirements.txt")
+}
+
+fn write_requirements(content: &str) -> Result<()> {
+    fs::write("requirements.txt", content).context("writing requirements.txt")
+}
+
+/// Ask pip which version of `package` ended up installed.
+fn installed_version(python: &Path, package: &str) -> Option<String> {
+    let output = Command::new(python)
+        .args(["-m", "pip", "show", package])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_pip_show_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_pip_show_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Version:"))
+        .map(|version| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+}
+
+/// The requirement spec up to its version constraint, e.g. `requests[socks]`
+/// for `requests[socks]>=2`.
+fn requirement_base(spec: &str) -> &str {
+    let spec = spec.trim();
+    let end = spec
+        .find(|c: char| matches!(c, '=' | '<' | '>' | '!' | '~' | ';' | '@') || c.is_whitespace())
+        .unwrap_or(spec.len());
+    spec[..end].trim()
+}
+
+/// The bare distribution name, without extras or constraints.
+fn requirement_name(spec: &str) -> &str {
+    let base = requirement_base(spec);
+    base.split('[').next().unwrap_or(base).trim()
+}
+
+/// PEP 503 normalization so `Foo_Bar` and `foo-bar` match.
+fn normalize_requirement_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace(['_', '.'], "-")
+}
+
+fn line_matches_package(line: &str, package: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('-') {
+        return false;
+    }
+    normalize_requirement_name(requirement_name(trimmed))
+        == normalize_requirement_name(requirement_name(package))
+}
+
+/// Replace the first line for `package` (dropping duplicates) or append a new
+/// one, leaving comments and unrelated lines untouched.
+fn upsert_requirement(content: &str, package: &str, version: Option<&str>) -> String {
+    let base = requirement_base(package);
+    let new_line = match version {
+        Some(version) => format!("{base}=={version}"),
+        None => package.trim().to_string(),
+    };
+
+    let mut lines = Vec::new();
+    let mut replaced = false;
+    for line in content.lines() {
+        if line_matches_package(line, package) {
+            if !replaced {
+                lines.push(new_line.clone());
+                replaced = true;
+            }
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if !replaced {
+        lines.push(new_line);
+    }
+
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+/// Drop every line for `package`, leaving everything else as-is.
+fn remove_requirement(content: &str, package: &str) -> String {
+    let mut out: String = content
+        .lines()
+        .filter(|line| !line_matches_package(line, package))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
 }
 
 /// Split "py -3" into ("py", ["-3"])
@@ -170,6 +764,221 @@ fn split_first(cmd: &str) -> (&str, Vec<&str>) {
 mod tests {
     use super::*;
 
+    #[test]
+    fn venv_shell_command_activates_the_venv() {
+        let venv = Path::new("/project/venv");
+        let command = venv_shell_command("/bin/sh", venv).expect("command");
+        let envs: HashMap<_, _> = command.get_envs().collect();
+
+        let path = envs
+            .get(std::ffi::OsStr::new("PATH"))
+            .copied()
+            .flatten()
+            .expect("PATH set");
+        let first = std::env::split_paths(path)
+            .next()
+            .expect("first PATH entry");
+        assert_eq!(first, venv_bin_dir(venv));
+        assert_eq!(
+            envs.get(std::ffi::OsStr::new("VIRTUAL_ENV"))
+                .copied()
+                .flatten(),
+            Some(venv.as_os_str())
+        );
+        assert_eq!(
+            envs.get(std::ffi::OsStr::new("PYTHONHOME")).copied(),
+            Some(None)
+        );
+    }
+
+    #[test]
+    fn managed_mode_appends_pinned_line_and_keeps_comments() {
+        let content = "# pin your dependencies here\nflask==3.0.0\n";
+        let updated = upsert_requirement(content, "requests", Some("2.32.3"));
+        assert_eq!(
+            updated,
+            "# pin your dependencies here\nflask==3.0.0\nrequests==2.32.3\n"
+        );
+    }
+
+    #[test]
+    fn managed_mode_replaces_existing_line_in_place_and_dedupes() {
+        let content = "Requests==2.0\n# keep me\nflask\nrequests>=1\n";
+        let updated = upsert_requirement(content, "requests", Some("2.32.3"));
+        assert_eq!(updated, "requests==2.32.3\n# keep me\nflask\n");
+    }
+
+    #[test]
+    fn managed_mode_remove_drops_only_matching_lines() {
+        let content = "# deps\nfoo_bar==1.0\nbaz\n";
+        assert_eq!(remove_requirement(content, "Foo-Bar"), "# deps\nbaz\n");
+    }
+
+    #[test]
+    fn lock_pins_carry_hashes_from_pip_report() {
+        let report = r#"{"version": "1", "install": [
+            {"metadata": {"name": "Requests", "version": "2.32.3"},
+             "download_info": {"archive_info": {"hashes": {"sha256": "abc123"}}}},
+            {"metadata": {"name": "idna", "version": "3.7"},
+             "download_info": {"archive_info": {"hashes": {"sha256": "def456"}}}}
+        ]}"#;
+        let hashes = parse_report_hashes(report).expect("valid report");
+        let lock = render_lock("requests==2.32.3\nidna==3.7\n", Some(&hashes));
+        assert!(
+            lock.contains("requests==2.32.3 --hash=sha256:abc123\n"),
+            "{lock}"
+        );
+        assert!(lock.contains("idna==3.7 --hash=sha256:def456\n"), "{lock}");
+        assert!(lock.contains("--require-hashes"), "{lock}");
+    }
+
+    #[test]
+    fn lock_without_hashes_keeps_pins_and_explains() {
+        let lock = render_lock("requests==2.32.3\n", None);
+        assert!(lock.ends_with("requests==2.32.3\n"), "{lock}");
+        assert!(lock.contains("No hashes"), "{lock}");
+    }
+
+    #[test]
+    fn only_plain_version_pins_are_hashable() {
+        assert!(is_exact_pin("requests==2.32.3"));
+        assert!(!is_exact_pin(
+            "-e git+https://example.com/repo.git#egg=tool"
+        ));
+        assert!(!is_exact_pin("tool @ file:///src/tool"));
+        assert!(!is_exact_pin("requests>=2"));
+    }
+
+    #[test]
+    fn venv_state_tells_incomplete_from_foreign_dirs() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let venv = tmp.path().join("venv");
+        assert_eq!(venv_state(&venv), VenvState::Missing);
+
+        fs::create_dir_all(&venv).expect("create venv dir");
+        assert_eq!(venv_state(&venv), VenvState::Foreign);
+
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").expect("write pyvenv.cfg");
+        assert_eq!(venv_state(&venv), VenvState::Incomplete);
+
+        let python = venv_python_in(&venv);
+        fs::create_dir_all(python.parent().expect("bin dir")).expect("create bin dir");
+        fs::write(&python, "").expect("write interpreter");
+        assert_eq!(venv_state(&venv), VenvState::Ready);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn incomplete_venv_is_removed_and_recreated() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let venv = tmp.path().join("venv");
+        fs::create_dir_all(&venv).expect("create venv dir");
+        fs::write(venv.join("pyvenv.cfg"), "home = /usr/bin\n").expect("write pyvenv.cfg");
+        fs::write(venv.join("stale"), "").expect("write stale file");
+
+        // Stand-in for `python -m venv <dir>` that lays out a minimal venv.
+        let fake_python = tmp.path().join("fake-python");
+        fs::write(
+            &fake_python,
+            "#!/bin/sh\nmkdir -p \"$3/bin\" && touch \"$3/pyvenv.cfg\" \"$3/bin/python\"\n",
+        )
+        .expect("write fake python");
+        fs::set_permissions(&fake_python, fs::Permissions::from_mode(0o755))
+            .expect("chmod fake python");
+
+        ensure_venv_at(&fake_python.to_string_lossy(), &venv).expect("recreate venv");
+
+        assert_eq!(venv_state(&venv), VenvState::Ready);
+        assert!(!venv.join("stale").exists());
+    }
+
+    #[test]
+    fn foreign_venv_dir_is_left_alone() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let venv = tmp.path().join("venv");
+        fs::create_dir_all(&venv).expect("create venv dir");
+        fs::write(venv.join("notes.txt"), "mine").expect("write file");
+
+        let err = ensure_venv_at("python3", &venv).expect_err("must refuse");
+        assert!(err.to_string().contains("not a virtualenv"), "{err}");
+        assert!(venv.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn pip_show_version_is_parsed() {
+        let output = "Name: requests\nVersion: 2.32.3\nSummary: HTTP\n";
+        assert_eq!(parse_pip_show_version(output).as_deref(), Some("2.32.3"));
+    }
+
+    #[test]
+    fn pip_user_install_passes_user_flag() {
+        assert_eq!(
+            pip_install_args("black", true).join(" "),
+            "-m pip install --user black"
+        );
+        assert_eq!(
+            pip_install_args("black", false).join(" "),
+            "-m pip install black"
+        );
+    }
+
+    #[test]
+    fn pip_upgrade_passes_upgrade_flag() {
+        assert_eq!(
+            pip_upgrade_args("requests").join(" "),
+            "-m pip install --upgrade requests"
+        );
+    }
+
+    #[test]
+    fn sync_installs_from_requirements_file() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(tmp.path().join("requirements.txt"), "requests==2.32.3\n")
+            .expect("write requirements");
+        let backend = detect_sync_backend(tmp.path()).expect("sync backend");
+        assert_eq!(backend, SyncBackend::Pip);
+        assert_eq!(
+            build_sync_args(backend).join(" "),
+            "-m pip install -r requirements.txt"
+        );
+    }
+
+    #[test]
+    fn sync_prefers_uv_then_poetry_lockfiles() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        fs::write(tmp.path().join("requirements.txt"), "").expect("write requirements");
+        fs::write(tmp.path().join("poetry.lock"), "").expect("write poetry.lock");
+        let backend = detect_sync_backend(tmp.path()).expect("sync backend");
+        assert_eq!(
+            (backend.tool(), build_sync_args(backend)),
+            ("poetry", vec!["install".to_string()])
+        );
+
+        fs::write(tmp.path().join("uv.lock"), "").expect("write uv.lock");
+        let backend = detect_sync_backend(tmp.path()).expect("sync backend");
+        assert_eq!(
+            (backend.tool(), build_sync_args(backend)),
+            ("uv", vec!["sync".to_string()])
+        );
+    }
+
+    #[test]
+    fn sync_requires_requirements_file() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let err = detect_sync_backend(tmp.path()).expect_err("must fail");
+        assert!(err.to_string().contains("No requirements.txt"));
+    }
+
+    #[test]
+    fn pip_list_runs_pip_module() {
+        assert_eq!(
+            pip_list_args(),
+            vec!["-m".to_string(), "pip".to_string(), "list".to_string()]
+        );
+    }
+
     #[test]
     fn venv_python_path_is_platform_specific() {
         let path = venv_python_path();
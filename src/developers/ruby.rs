@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use crate::developers::common::{
+    ToolCommand, ensure_project_config_file, parse_name_only, parse_name_version, require_tool,
+};
+
+const CONFIG_TEMPLATE: &str = r#"scripts:
+  dev: "bundle exec rails server"
+  test: "bundle exec rake test"
+  lint: "bundle exec rubocop"
+"#;
+
+const FAILURE_HINT: &str = "Check that the Gemfile is valid and retry.";
+
+/// Create a Gemfile with `bundle init` and scaffold qbit.yml if it's missing.
+pub fn init() -> Result<()> {
+    ensure_project_config_file(CONFIG_TEMPLATE)?;
+    if Path::new("Gemfile").exists() {
+        println!("Gemfile already exists");
+        return Ok(());
+    }
+
+    ensure_bundle_available()?;
+    build_init_command().run(FAILURE_HINT)?;
+    println!("Ruby project initialized with Bundler.");
+    Ok(())
+}
+
+/// Add a gem with `bundle add`; `gem:1.2` pins the version.
+pub fn add_package(package: &str) -> Result<()> {
+    ensure_bundle_available()?;
+    ensure_gemfile_exists()?;
+    build_add_command(package)?.run(FAILURE_HINT)?;
+    println!("Gem `{package}` added via Bundler.");
+    Ok(())
+}
+
+pub fn remove_package(package: &str) -> Result<()> {
+    ensure_bundle_available()?;
+    ensure_gemfile_exists()?;
+    build_remove_command(package)?.run(FAILURE_HINT)?;
+    println!("Gem `{package}` removed via Bundler.");
+    Ok(())
+}
+
+/// Run an executable from the bundle with `bundle exec`.
+pub fn run_script(script: &str, script_args: &[String]) -> Result<()> {
+    ensure_bundle_available()?;
+    ensure_gemfile_exists()?;
+    build_exec_command(script, script_args)?.run(FAILURE_HINT)
+}
+
+fn ensure_gemfile_exists() -> Result<()> {
+    if !Path::new("Gemfile").exists() {
+        bail!("No Gemfile found in the current directory. Run `qbit rb init` first.");
+    }
+    Ok(())
+}
+
+fn build_init_command() -> ToolCommand {
+    ToolCommand::new("bundle", ["init"])
+}
+
+fn build_add_command(package: &str) -> Result<ToolCommand> {
+    let (name, version) = parse_name_version(package, "gem")?;
+    let mut args = vec!["add", name];
+    if let Some(version) = version {
+        args.extend(["--version", version]);
+    }
+    Ok(ToolCommand::new("bundle", args))
+}
+
+fn build_remove_command(package: &str) -> Result<ToolCommand> {
+    let name = parse_name_only(package, "gem", "bundle remove")?;
+    Ok(ToolCommand::new("bundle", ["remove", name]))
+}
+
+fn build_exec_command(script: &str, script_args: &[String]) -> Result<ToolCommand> {
+    let script = script.trim();
+    if script.is_empty() {
+        bail!("Script name must be non-empty.");
+    }
+    let args = ["exec", script]
+        .into_iter()
+        .map(str::to_string)
+        .chain(script_args.iter().cloned());
+    Ok(ToolCommand::new("bundle", args))
+}
+
+fn ensure_bundle_available() -> Result<()> {
+    require_tool(
+        "bundle",
+        "Install Ruby and Bundler (`gem install bundler`) and ensure `bundle --version` works.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_bundle_init_command() {
+        assert_eq!(build_init_command().render(), "bundle init");
+    }
+
+    #[test]
+    fn builds_bundle_add_command() {
+        let command = build_add_command("rails").expect("build command");
+        assert_eq!(command.args, vec!["add".to_string(), "rails".to_string()]);
+    }
+
+    #[test]
+    fn builds_bundle_add_command_with_pinned_version() {
+        let command = build_add_command("rails:7.1.3").expect("build command");
+        assert_eq!(
+            command.args,
+            vec![
+                "add".to_string(),
+                "rails".to_string(),
+                "--version".to_string(),
+                "7.1.3".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_bundle_remove_command() {
+        let command = build_remove_command("rails").expect("build command");
+        assert_eq!(
+            command.args,
+            vec!["remove".to_string(), "rails".to_string()]
+        );
+    }
+
+    #[test]
+    fn builds_bundle_exec_command_with_args() {
+        let command =
+            build_exec_command("rspec", &["spec/models".to_string()]).expect("build command");
+        assert_eq!(
+            command.args,
+            vec![
+                "exec".to_string(),
+                "rspec".to_string(),
+                "spec/models".to_string()
+            ]
+        );
+        assert!(build_exec_command(" ", &[]).is_err());
+    }
+}
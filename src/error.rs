@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::developers::common::{Ecosystem, ProjectContext};
+
+/// Failures callers (and the exit-code mapping in `cli`) need to tell apart.
+/// They travel inside `anyhow::Error` like any other error; use
+/// [`QbitError::find`] to get the variant back out of a context chain.
+#[derive(Debug, thiserror::Error)]
+pub enum QbitError {
+    /// No qbit.yml/qbit.toml exists where a command needs one. `detected`
+    /// holds the ecosystems found in the directory, so the message can
+    /// suggest a setup.
+    #[error("No qbit.yml/qbit.toml file found in the current directory.{}", detected_hint(.detected))]
+    NoConfig { detected: Vec<Ecosystem> },
+
+    /// The config exists but doesn't define the requested script.
+    #[error("Script `{name}` not found in {}", .path.display())]
+    ScriptNotFound { name: String, path: PathBuf },
+
+    /// An external program qbit needs isn't installed. `hint` says how to fix it.
+    #[error("`{tool}` executable was not found in PATH. {hint}")]
+    ToolNotFound { tool: String, hint: String },
+
+    /// No package manager qbit supports is installed.
+    #[error(
+        "No supported package manager detected in PATH. Checked: {}. Install one of them or set QBIT_PACKAGE_MANAGER.",
+        .checked.join(", ")
+    )]
+    NoManagerDetected { checked: Vec<String> },
+
+    /// A package manager was requested by name (`setting` says where) but
+    /// isn't installed.
+    #[error(
+        "{setting} is `{manager}`, but executable `{executable}` is not available in PATH. Install it or change {setting}."
+    )]
+    ManagerUnavailable {
+        manager: String,
+        executable: String,
+        setting: String,
+    },
+
+    /// A network request was about to be made in offline mode.
+    #[error("network disabled (offline mode)")]
+    Offline,
+
+    /// An HTTP request failed to connect or came back with an error status.
+    #[error("request to {url} failed")]
+    Network {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// A shell command ran but exited unsuccessfully. Carries the child's
+    /// exit code so the CLI can propagate it as its own.
+    #[error("command `{command}` {}", exit_description(*.code))]
+    CommandFailed { command: String, code: Option<i32> },
+
+    /// A step ran past its `timeout` and was killed.
+    #[error("command `{command}` timed out after {}s and was stopped", .timeout.as_secs())]
+    StepTimedOut { command: String, timeout: Duration },
+
+    /// Ctrl-C stopped the running step.
+    #[error("interrupted")]
+    Interrupted,
+
+    /// A package manager's install command exited unsuccessfully.
+    #[error("Installer command failed (exit code {}): {command}", .code.unwrap_or(1))]
+    InstallerFailed { command: String, code: Option<i32> },
+}
+
+impl QbitError {
+    /// [`QbitError::NoConfig`] with the project types detected in the
+    /// current directory.
+    pub fn no_config() -> Self {
+        Self::NoConfig {
+            detected: ProjectContext::from_current_dir().detect_project_types(),
+        }
+    }
+
+    /// The outermost `QbitError` in `err`'s context chain.
+    pub fn find(err: &anyhow::Error) -> Option<&QbitError> {
+        err.chain()
+            .find_map(|cause| cause.downcast_ref::<QbitError>())
+    }
+}
+
+fn detected_hint(detected: &[Ecosystem]) -> String {
+    match detected.first() {
+        Some(primary) => format!(
+            " Detected a {} project; {}.",
+            primary.name(),
+            primary.suggestion()
+        ),
+        None => String::new(),
+    }
+}
+
+fn exit_description(code: Option<i32>) -> String {
+    match code {
+        Some(code) => format!("exited with code {code}"),
+        None => "was terminated by a signal".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn no_config_suggests_setup_for_detected_project() {
+        let err = QbitError::NoConfig {
+            detected: vec![Ecosystem::Js, Ecosystem::Python],
+        };
+        assert_eq!(
+            err.to_string(),
+            "No qbit.yml/qbit.toml file found in the current directory. Detected a Node project; try `qbit js init`."
+        );
+        let bare = QbitError::NoConfig { detected: vec![] };
+        assert!(bare.to_string().ends_with("current directory."));
+    }
+
+    #[test]
+    fn command_failures_describe_exit_code_or_signal() {
+        let failed = |code| QbitError::CommandFailed {
+            command: "make".to_string(),
+            code,
+        };
+        assert_eq!(
+            failed(Some(2)).to_string(),
+            "command `make` exited with code 2"
+        );
+        assert_eq!(
+            failed(None).to_string(),
+            "command `make` was terminated by a signal"
+        );
+    }
+
+    #[test]
+    fn find_sees_through_context() {
+        let err = Err::<(), _>(QbitError::Offline)
+            .context("fetching release")
+            .unwrap_err();
+        assert!(matches!(QbitError::find(&err), Some(QbitError::Offline)));
+        assert!(QbitError::find(&anyhow::anyhow!("plain")).is_none());
+    }
+}
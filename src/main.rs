@@ -1,15 +1,18 @@
 mod cli;
 mod config;
 mod developers;
+mod error;
 mod os;
 mod tools;
 mod utils;
 
-fn main() {
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
     if let Ok(root) = std::env::var("QBIT_PROJECT_ROOT") {
-        if let Err(e) = std::env::set_current_dir(&root) {
+        if let Err(e) = std::env::set_current_dir(utils::paths::expand_path(&root)) {
             eprintln!("warning: failed to switch to sandbox at {root}: {e}");
         }
     }
-    cli::run();
+    cli::run()
 }
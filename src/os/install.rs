@@ -1,39 +1,1082 @@
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+use std::sync::{Mutex, PoisonError};
 
 use anyhow::{Context, Result, bail};
+use serde::Serialize;
 
-use crate::config::{InstallSpec, load_project_config};
+use crate::config::{InstallSpec, LoadedProjectConfig, WingetMatch};
+use crate::error::QbitError;
+use crate::os::lockfile::{InstallLock, LOCK_FILE, LockedTarget, lock_path};
 #[cfg(test)]
 use crate::os::package_manager::package_manager_from_name;
-use crate::os::package_manager::{InstallCommand, PackageManager, detect_package_manager};
+use crate::os::package_manager::{
+    InstallCommand, ManagerChoice, ManagerSettings, PackageManager, all_package_managers,
+    available_package_managers, ensure_sudo_wont_block, is_detection_candidate,
+    package_manager_choice, with_optional_sudo,
+};
+use crate::os::version_manager::{
+    VERSION_MANAGER_OVERRIDE, VersionManager, detect_version_manager,
+};
+use crate::utils::output::OutputMode;
+use crate::utils::shell::{self, RunOptions, ScriptReport};
 
 #[derive(Debug, Clone)]
 struct InstallPlan {
+    target: String,
     manager_name: String,
     identifier: String,
+    identifier_source: IdentifierSource,
     requested_version: Option<String>,
     inline_overrode_config: bool,
+    config_source: Option<(String, PathBuf)>,
     command: InstallCommand,
+    note: Option<String>,
 }
 
-/// Entry point from CLI.
-pub fn install_target(raw_spec: &str, dry_run: bool, yes: bool) -> Result<()> {
-    let selected_manager = detect_package_manager()?;
-    let plan = build_install_plan(raw_spec, selected_manager.as_ref(), yes)?;
+impl InstallPlan {
+    /// What qbit.lock records once this plan is installed.
+    fn locked_target(&self) -> LockedTarget {
+        LockedTarget {
+            manager: self.manager_name.clone(),
+            identifier: self.identifier.clone(),
+            version: self.requested_version.clone(),
+            command: self.command.render(),
+        }
+    }
+}
+
+/// Which part of an `install:` entry supplied the identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IdentifierSource {
+    /// The entry is a plain string used by every manager.
+    Entry,
+    /// The `identifiers:` key for the manager, e.g. `apt`.
+    ManagerKey(&'static str),
+    /// The `identifiers: default` key.
+    DefaultKey,
+    /// No entry or matching key, so the target name itself.
+    TargetName,
+}
+
+impl IdentifierSource {
+    fn describe(self, manager: &str) -> String {
+        match self {
+            Self::Entry => {
+                "the install entry is a plain identifier used by every manager".to_string()
+            }
+            Self::ManagerKey(key) => {
+                format!("the entry's `{key}` key for {manager}")
+            }
+            Self::DefaultKey => {
+                format!("the entry's `default` key, since no key matches {manager}")
+            }
+            Self::TargetName => {
+                "no install entry or key applies, so the target name is used as is".to_string()
+            }
+        }
+    }
+}
+
+/// Characters besides letters and digits allowed in an install target name,
+/// enough for names like `python3.12`, `postgresql@15`, and `extras/vscode`.
+const TARGET_NAME_PUNCTUATION: &str = "-_.+@/";
+
+/// Flags from `qbit install` that shape planning and execution.
+#[derive(Debug, Clone, Default)]
+pub struct InstallOptions {
+    /// Print the resolved command without executing it.
+    pub dry_run: bool,
+    /// Add the manager's non-interactive flag.
+    pub yes: bool,
+    /// Install even when the package is already present, using the manager's
+    /// reinstall mode where it has one.
+    pub force: bool,
+    /// Fall back to the next available package manager when one fails.
+    pub try_all: bool,
+    /// Pre-accept package licenses with the manager's own flag.
+    pub accept_licenses: bool,
+    /// Refresh the package index before installing, for managers that can.
+    pub refresh: bool,
+    /// Never prefix system package managers with `sudo`.
+    pub no_sudo: bool,
+    /// Install exactly what qbit.lock records and fail if it is missing or stale.
+    pub frozen: bool,
+    /// Package manager named with `--manager`; wins over `QBIT_PACKAGE_MANAGER`
+    /// and `install.manager`.
+    pub manager: Option<String>,
+}
+
+/// Managers whose index was already refreshed by this process, so installing
+/// several targets runs `apt-get update` only once.
+static REFRESHED_INDEXES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Held while qbit.lock is rewritten, since `--jobs` installs run on threads.
+static LOCK_FILE_GUARD: Mutex<()> = Mutex::new(());
+
+/// JSON view of a resolved install plan.
+#[derive(Debug, Serialize)]
+struct InstallReport<'a> {
+    target: &'a str,
+    identifier: &'a str,
+    version: Option<&'a str>,
+    manager: &'a str,
+    command: String,
+    note: Option<&'a str>,
+    dry_run: bool,
+    already_installed: bool,
+    /// The `postinstall` run, when there was one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postinstall: Option<ScriptReport>,
+}
+
+/// One row of `qbit install --list-managers`.
+#[derive(Debug, Serialize)]
+struct ManagerReport {
+    name: &'static str,
+    executable: &'static str,
+    available: bool,
+    detection_candidate: bool,
+    config_keys: &'static [&'static str],
+}
+
+/// Show every known package manager and how detection sees it, without
+/// installing anything.
+pub fn list_managers(output: OutputMode) -> Result<()> {
+    let rows: Vec<ManagerReport> = all_package_managers()
+        .iter()
+        .map(|pm| ManagerReport {
+            name: pm.name(),
+            executable: pm.executable(),
+            available: pm.is_available(),
+            detection_candidate: is_detection_candidate(pm.name()),
+            config_keys: pm.config_keys(),
+        })
+        .collect();
+
+    if output.is_json() {
+        return output.json(&rows);
+    }
+
+    if let Ok(raw_override) = std::env::var("QBIT_PACKAGE_MANAGER") {
+        println!("QBIT_PACKAGE_MANAGER override: `{}`", raw_override.trim());
+    }
+    println!(
+        "{:<10} {:<10} {:<10} {:<12} CONFIG KEYS",
+        "MANAGER", "EXECUTABLE", "AVAILABLE", "AUTO-DETECT"
+    );
+    let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+    for row in &rows {
+        println!(
+            "{:<10} {:<10} {:<10} {:<12} {}",
+            row.name,
+            row.executable,
+            yes_no(row.available),
+            yes_no(row.detection_candidate),
+            row.config_keys.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// JSON view of `qbit install --print-plan`.
+#[derive(Debug, Serialize)]
+struct PlanPreview<'a> {
+    target: &'a str,
+    version: Option<String>,
+    managers: Vec<ManagerPlan>,
+}
+
+/// What one available manager would run for the target.
+#[derive(Debug, Serialize)]
+struct ManagerPlan {
+    manager: &'static str,
+    selected: bool,
+    identifier: Option<String>,
+    command: Option<String>,
+    error: Option<String>,
+}
+
+/// Resolve `raw_spec` for the selected manager and every other available one
+/// and print the result, without probing or installing anything.
+pub fn print_plan(
+    raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> Result<()> {
+    let (target, _) = parse_target_spec(raw_spec)?;
+    let lock = InstallLock::load(&lock_path(config))?;
+    let selected = select_manager(config, options, lock.as_ref())?.manager;
+    let selected_name = selected.name();
+    let mut managers = vec![selected];
+    managers.extend(
+        available_package_managers()
+            .into_iter()
+            .filter(|pm| pm.name() != selected_name),
+    );
+
+    let mut version = None;
+    let rows: Vec<ManagerPlan> = managers
+        .iter()
+        .enumerate()
+        .map(|(index, manager)| {
+            match build_install_plan(raw_spec, config, manager.as_ref(), options) {
+                Ok(plan) => {
+                    version = version.take().or(plan.requested_version);
+                    ManagerPlan {
+                        manager: manager.name(),
+                        selected: index == 0,
+                        identifier: Some(plan.identifier),
+                        command: Some(plan.command.render()),
+                        error: None,
+                    }
+                }
+                Err(e) => ManagerPlan {
+                    manager: manager.name(),
+                    selected: index == 0,
+                    identifier: None,
+                    command: None,
+                    error: Some(format!("{e:#}")),
+                },
+            }
+        })
+        .collect();
+
+    if output.is_json() {
+        return output.json(&PlanPreview {
+            target: &target,
+            version,
+            managers: rows,
+        });
+    }
+
+    println!("Target: {target}");
+    println!(
+        "Version: {}",
+        version
+            .as_deref()
+            .unwrap_or("latest available from package manager")
+    );
+    for row in &rows {
+        let label = if row.selected {
+            format!("{} (selected)", row.manager)
+        } else {
+            row.manager.to_string()
+        };
+        match (&row.identifier, &row.command, &row.error) {
+            (Some(identifier), Some(command), _) => {
+                println!("  {label}: {identifier} -> {command}")
+            }
+            (_, _, error) => println!("  {label}: error: {}", error.as_deref().unwrap_or("")),
+        }
+    }
+    Ok(())
+}
+
+/// `qbit install --validate` result for one target.
+#[derive(Debug, Serialize)]
+struct TargetValidation {
+    target: String,
+    command: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ValidationReport<'a> {
+    manager: &'a str,
+    targets: &'a [TargetValidation],
+}
+
+/// Check that every target (every configured one when none are given) can be
+/// turned into an install command for the selected manager, without running
+/// anything. Fails when any of them can't.
+pub fn validate_targets(
+    targets: &[String],
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> Result<()> {
+    let targets = if targets.is_empty() {
+        let mut configured: Vec<String> = config
+            .map(|cfg| cfg.data.install.keys().cloned().collect())
+            .unwrap_or_default();
+        configured.sort();
+        configured
+    } else {
+        targets.to_vec()
+    };
+    if targets.is_empty() {
+        bail!(
+            "Nothing to validate: pass install targets or add an `install:` section to qbit.yml."
+        );
+    }
+
+    let lock = InstallLock::load(&lock_path(config))?;
+    let manager = select_manager(config, options, lock.as_ref())?.manager;
+    let results = validate_with(&targets, config, manager.as_ref(), options);
+
+    if output.is_json() {
+        output.json(&ValidationReport {
+            manager: manager.name(),
+            targets: &results,
+        })?;
+    } else {
+        println!("Validating install targets for {}:", manager.name());
+        for result in &results {
+            match (&result.command, &result.error) {
+                (Some(command), _) => println!("  ok     {}: {command}", result.target),
+                (_, error) => println!(
+                    "  error  {}: {}",
+                    result.target,
+                    error.as_deref().unwrap_or("")
+                ),
+            }
+        }
+    }
+
+    let failed: Vec<&str> = results
+        .iter()
+        .filter(|result| result.error.is_some())
+        .map(|result| result.target.as_str())
+        .collect();
+    if !failed.is_empty() {
+        bail!(
+            "{} of {} install targets can't be installed with {}: {}.",
+            failed.len(),
+            results.len(),
+            manager.name(),
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn validate_with(
+    targets: &[String],
+    config: Option<&LoadedProjectConfig>,
+    manager: &dyn PackageManager,
+    options: &InstallOptions,
+) -> Vec<TargetValidation> {
+    targets
+        .iter()
+        .map(
+            |target| match build_install_plan(target, config, manager, options) {
+                Ok(plan) => TargetValidation {
+                    target: target.clone(),
+                    command: Some(plan.command.render()),
+                    error: None,
+                },
+                Err(e) => TargetValidation {
+                    target: target.clone(),
+                    command: None,
+                    error: Some(format!("{e:#}")),
+                },
+            },
+        )
+        .collect()
+}
+
+/// One step of the `qbit why` trace.
+#[derive(Debug, Serialize)]
+struct Decision {
+    step: &'static str,
+    choice: String,
+    reason: String,
+}
+
+/// JSON view of `qbit why`.
+#[derive(Debug, Serialize)]
+struct WhyReport<'a> {
+    target: &'a str,
+    decisions: &'a [Decision],
+}
+
+/// Run the same resolution as `qbit install <raw_spec>` and print each
+/// decision it makes, without probing or installing anything.
+pub fn explain_target(
+    raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> Result<()> {
+    let (target, inline_version) = parse_target_spec(raw_spec)?;
+    let mut decisions = Vec::new();
+    let mut decide = |step, choice: String, reason: String| {
+        decisions.push(Decision {
+            step,
+            choice,
+            reason,
+        })
+    };
+
+    match config {
+        Some(cfg) => decide(
+            "Config",
+            cfg.path.display().to_string(),
+            "project config in the current directory".to_string(),
+        ),
+        None => decide(
+            "Config",
+            "none".to_string(),
+            "no qbit.yml/qbit.toml in the current directory, so only built-in defaults apply"
+                .to_string(),
+        ),
+    }
+    match config.and_then(|cfg| cfg.install_target_case_insensitive(&target)) {
+        Some((name, _)) if name == target => decide(
+            "Install entry",
+            name.to_string(),
+            "exact match under `install:`".to_string(),
+        ),
+        Some((name, _)) => decide(
+            "Install entry",
+            name.to_string(),
+            format!("matches `{target}` case-insensitively under `install:`"),
+        ),
+        None => decide(
+            "Install entry",
+            "none".to_string(),
+            format!("no `install:` entry is named `{target}`"),
+        ),
+    }
+
+    if let Some(route) = version_manager_route(raw_spec, config, options)? {
+        decide(
+            "Version manager",
+            route.manager.name().to_string(),
+            format!(
+                "the project uses {} and the target is pinned to {} (set {VERSION_MANAGER_OVERRIDE}=none to use the OS package manager)",
+                route.manager.name(),
+                route.version
+            ),
+        );
+        decide(
+            "Identifier",
+            route.tool.clone(),
+            explain_version_manager_tool(&route),
+        );
+        decide(
+            "Command",
+            route.command.render(),
+            "what `qbit install` would run".to_string(),
+        );
+        return print_decisions(&target, &decisions, output);
+    }
+
+    let lock_path = lock_path(config);
+    let lock = InstallLock::load(&lock_path)?;
+    let ManagerChoice {
+        manager, reason, ..
+    } = select_manager(config, options, lock.as_ref())?;
+    decide("Package manager", manager.name().to_string(), reason);
+
+    let mut plan = build_install_plan(raw_spec, config, manager.as_ref(), options)?;
+    let entry = lock.as_ref().and_then(|lock| lock.entry(&plan.target));
+    let locked = locked_version(&plan.target, &plan.locked_target(), entry, false)?;
+    match (entry, &locked) {
+        (_, Some(version)) => decide(
+            "Lock",
+            version.clone(),
+            format!(
+                "{} records this version and nothing else pins one",
+                lock_path.display()
+            ),
+        ),
+        (Some(entry), None) => match lock_mismatch(&plan.locked_target(), entry) {
+            Some(mismatch) => decide(
+                "Lock",
+                "stale".to_string(),
+                format!("{mismatch}; the entry is replaced after the next install"),
+            ),
+            None => decide(
+                "Lock",
+                "up to date".to_string(),
+                "the entry matches and the version is pinned elsewhere".to_string(),
+            ),
+        },
+        (None, None) => decide(
+            "Lock",
+            "none".to_string(),
+            format!("{} has no entry for `{}`", lock_path.display(), plan.target),
+        ),
+    }
+    if let Some(version) = &locked {
+        let pinned = format!("{}:{version}", plan.target);
+        plan = build_install_plan(&pinned, config, manager.as_ref(), options)?;
+    }
+
+    decide(
+        "Identifier",
+        plan.identifier.clone(),
+        plan.identifier_source.describe(&plan.manager_name),
+    );
+    let version_reason = if locked.is_some() {
+        format!("locked in {LOCK_FILE}")
+    } else if plan.inline_overrode_config {
+        "given inline, overriding the install entry's version".to_string()
+    } else if inline_version.is_some() {
+        "given inline".to_string()
+    } else if plan.requested_version.is_some() {
+        "the install entry's `version`".to_string()
+    } else {
+        "nothing pins one, so the package manager picks its latest".to_string()
+    };
+    decide(
+        "Version",
+        plan.requested_version
+            .clone()
+            .unwrap_or_else(|| "latest".to_string()),
+        version_reason,
+    );
+    decide(
+        "Command",
+        plan.command.render(),
+        "what `qbit install` would run".to_string(),
+    );
+    print_decisions(&target, &decisions, output)
+}
+
+fn explain_version_manager_tool(route: &VersionManagerRoute) -> String {
+    if route.tool == route.target {
+        "the target name, since the entry has no key for the version manager".to_string()
+    } else {
+        format!("the entry's `{}` key", route.manager.name())
+    }
+}
+
+fn print_decisions(target: &str, decisions: &[Decision], output: OutputMode) -> Result<()> {
+    if output.is_json() {
+        return output.json(&WhyReport { target, decisions });
+    }
+    println!("Why `{target}` resolves the way it does:");
+    for decision in decisions {
+        println!("  {}: {}", decision.step, decision.choice);
+        println!("    {}", decision.reason);
+    }
+    Ok(())
+}
+
+/// One manager tried by `qbit install --try-all`.
+#[derive(Debug, Serialize)]
+struct AttemptReport {
+    manager: &'static str,
+    command: Option<String>,
+    error: Option<String>,
+}
+
+/// JSON view of a `--try-all` run.
+#[derive(Debug, Serialize)]
+struct FallbackReport<'a> {
+    target: &'a str,
+    succeeded: Option<&'static str>,
+    attempts: &'a [AttemptReport],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    postinstall: Option<ScriptReport>,
+}
+
+/// Entry point from CLI. `config` is the project config loaded once by the caller.
+pub fn install_target(
+    raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> Result<()> {
+    // Fail on a malformed target before detecting any manager.
+    parse_target_spec(raw_spec)?;
+    let lock_path = lock_path(config);
+    let lock = InstallLock::load(&lock_path)?;
+    if options.frozen && lock.is_none() {
+        bail!(
+            "--frozen needs {}, but it does not exist. Run `qbit install` without --frozen to create it first.",
+            lock_path.display()
+        );
+    }
+    if let Some(route) = version_manager_route(raw_spec, config, options)? {
+        return install_with_version_manager(
+            route,
+            config,
+            &lock_path,
+            lock.as_ref(),
+            options,
+            output,
+        );
+    }
+
+    if options.try_all {
+        let managers = available_package_managers();
+        if managers.is_empty() {
+            // Reuse the single-manager error, which lists what was checked.
+            select_manager(config, options, None)?;
+        }
+        return install_with_fallback(raw_spec, config, &managers, options, output, |command| {
+            execute_install(command, output)
+        });
+    }
+
+    let choice = select_manager(config, options, lock.as_ref())?;
+    let selected_manager = choice.manager;
+    let detected_manager = choice.detected.then(|| selected_manager.name());
+    let mut plan = build_install_plan(raw_spec, config, selected_manager.as_ref(), options)?;
+    let entry = lock.as_ref().and_then(|lock| lock.entry(&plan.target));
+    if let Some(version) =
+        locked_version(&plan.target, &plan.locked_target(), entry, options.frozen)?
+    {
+        let pinned = format!("{}:{version}", plan.target);
+        plan = build_install_plan(&pinned, config, selected_manager.as_ref(), options)?;
+        output.info(format!("Using version {version} locked in {LOCK_FILE}"));
+    }
 
+    if let Some((entry_name, path)) = plan.config_source.as_ref() {
+        output.info(format!(
+            "Using install config `{}` from {}",
+            entry_name,
+            path.display()
+        ));
+    }
     if plan.inline_overrode_config {
-        println!("Inline version override applied.");
+        output.info("Inline version override applied.");
     }
 
-    println!("Selected package manager: {}", plan.manager_name);
-    println!("Resolved identifier: {}", plan.identifier);
+    output.info(format!("Selected package manager: {}", plan.manager_name));
+    output.info(format!("Resolved identifier: {}", plan.identifier));
     if let Some(version) = plan.requested_version.as_deref() {
-        println!("Resolved version: {version}");
+        output.info(format!("Resolved version: {version}"));
     } else {
-        println!("Resolved version: latest available from package manager");
+        output.info("Resolved version: latest available from package manager");
+    }
+    if let Some(note) = plan.note.as_deref() {
+        output.info(format!("Note: {note}"));
+    }
+
+    let already_installed = should_skip_install(&plan, selected_manager.as_ref(), options);
+    if already_installed {
+        output.info(format!(
+            "`{}` is already installed; skipping (use --force to reinstall).",
+            plan.identifier
+        ));
+    }
+
+    let mut outcome = if already_installed {
+        Ok(())
+    } else {
+        refresh_index(selected_manager.as_ref(), options, output, |command| {
+            execute_install(command, output)
+        })
+        .and_then(|()| {
+            execute_or_print_dry_run(&plan.command, options.dry_run, output, |command| {
+                execute_install(command, output)
+            })
+        })
+    };
+
+    if outcome.is_ok() && !options.dry_run && !options.frozen {
+        let mut locked = plan.locked_target();
+        if locked.version.is_none() {
+            locked.version = selected_manager.installed_version(&plan.identifier);
+        }
+        outcome = record_in_lock(
+            config.is_some(),
+            &lock_path,
+            &plan.target,
+            locked,
+            detected_manager,
+        );
+    }
+    let (postinstall, outcome) = match outcome {
+        Ok(()) if !already_installed => run_postinstall(&plan.target, config, options, output),
+        outcome => (None, outcome),
+    };
+
+    output.json(&InstallReport {
+        target: &plan.target,
+        identifier: &plan.identifier,
+        version: plan.requested_version.as_deref(),
+        manager: &plan.manager_name,
+        command: plan.command.render(),
+        note: plan.note.as_deref(),
+        dry_run: options.dry_run,
+        already_installed,
+        postinstall,
+    })?;
+    outcome
+}
+
+/// Record `target` in qbit.lock, along with the manager auto-detection
+/// picked (`detected_manager`) so later runs can skip detection. Outside a
+/// project (no config and no existing lock) nothing is written, so a one-off
+/// `qbit install` doesn't leave a qbit.lock behind.
+fn record_in_lock(
+    in_project: bool,
+    lock_path: &Path,
+    target: &str,
+    locked: LockedTarget,
+    detected_manager: Option<&str>,
+) -> Result<()> {
+    // Re-read under the guard so parallel jobs don't drop each other's entries.
+    let _guard = LOCK_FILE_GUARD
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut lock = match InstallLock::load(lock_path)? {
+        Some(lock) => lock,
+        None if in_project => InstallLock::default(),
+        None => return Ok(()),
+    };
+    if let Some(manager) = detected_manager {
+        lock.manager = Some(manager.to_string());
+    }
+    lock.record(target, locked);
+    lock.save(lock_path)
+}
+
+/// Run the `postinstall` commands of `target`'s install entry once it has
+/// been installed. Under `--dry-run` they are only printed. The run's report
+/// is returned for the caller's JSON output rather than printed on its own.
+fn run_postinstall(
+    target: &str,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> (Option<ScriptReport>, Result<()>) {
+    let steps = match config.map(|cfg| cfg.postinstall_steps(target)).transpose() {
+        Ok(steps) => steps.flatten(),
+        Err(err) => return (None, Err(err)),
+    };
+    let Some(steps) = steps else {
+        return (None, Ok(()));
+    };
+    if options.dry_run {
+        for step in &steps {
+            output.info(format!("[dry-run] postinstall: {}", step.describe()));
+        }
+        return (None, Ok(()));
+    }
+    let (report, outcome) = shell::run_commands_reported(
+        &format!("postinstall:{target}"),
+        &steps,
+        output,
+        RunOptions::default(),
+    );
+    (
+        Some(report),
+        outcome.with_context(|| format!("`{target}` was installed, but its postinstall failed")),
+    )
+}
+
+/// Check the lock entry for `target` against what qbit.yml resolves now
+/// (`wanted`). Returns the locked version to reuse when the entry is current
+/// and nothing else pins one. A missing or stale entry fails under
+/// `--frozen`; otherwise it is replaced once the install succeeds.
+fn locked_version(
+    target: &str,
+    wanted: &LockedTarget,
+    entry: Option<&LockedTarget>,
+    frozen: bool,
+) -> Result<Option<String>> {
+    let Some(entry) = entry else {
+        if frozen {
+            bail!(
+                "`{target}` has no entry in {LOCK_FILE}. Run `qbit install {target}` without --frozen to record it."
+            );
+        }
+        return Ok(None);
+    };
+    if let Some(reason) = lock_mismatch(wanted, entry) {
+        if frozen {
+            bail!(
+                "{LOCK_FILE} is stale for `{target}`: {reason}. Run `qbit install {target}` without --frozen to update it."
+            );
+        }
+        return Ok(None);
+    }
+    Ok(match wanted.version {
+        Some(_) => None,
+        None => entry.version.clone(),
+    })
+}
+
+fn lock_mismatch(wanted: &LockedTarget, entry: &LockedTarget) -> Option<String> {
+    if entry.manager != wanted.manager {
+        return Some(format!(
+            "it was locked with {}, but this machine uses {}",
+            entry.manager, wanted.manager
+        ));
+    }
+    if entry.identifier != wanted.identifier {
+        return Some(format!(
+            "it locks identifier `{}`, but qbit.yml now resolves `{}`",
+            entry.identifier, wanted.identifier
+        ));
+    }
+    match wanted.version.as_deref() {
+        Some(version) if entry.version.as_deref() != Some(version) => Some(format!(
+            "it locks version {}, but {version} is requested",
+            entry.version.as_deref().unwrap_or("latest")
+        )),
+        _ => None,
+    }
+}
+
+/// How many of `target_count` installs `--jobs` may run at once with the
+/// selected manager. Managers that hold a global lock are held to one.
+pub fn install_jobs(
+    requested: usize,
+    target_count: usize,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+) -> Result<usize> {
+    if requested <= 1 || target_count <= 1 {
+        return Ok(1);
+    }
+    let lock = InstallLock::load(&lock_path(config))?;
+    let manager = select_manager(config, options, lock.as_ref())?.manager;
+    Ok(jobs_for_manager(requested, manager.as_ref()))
+}
+
+fn jobs_for_manager(requested: usize, manager: &dyn PackageManager) -> usize {
+    if requested > 1 && !manager.supports_parallel_installs() {
+        eprintln!(
+            "warning: {} takes a global lock, so targets are installed one at a time (--jobs 1)",
+            manager.name()
+        );
+        return 1;
+    }
+    requested
+}
+
+/// Install a pinned target with asdf/mise, so the version lands behind the
+/// manager's shims instead of conflicting with them. qbit.lock is checked
+/// and updated the same way as for OS package managers.
+fn install_with_version_manager(
+    route: VersionManagerRoute,
+    config: Option<&LoadedProjectConfig>,
+    lock_path: &Path,
+    lock: Option<&InstallLock>,
+    options: &InstallOptions,
+    output: OutputMode,
+) -> Result<()> {
+    let locked = route.locked_target();
+    let entry = lock.and_then(|lock| lock.entry(&route.target));
+    locked_version(&route.target, &locked, entry, options.frozen)?;
+    let VersionManagerRoute {
+        manager,
+        target: logical_target,
+        tool,
+        version,
+        command,
+    } = route;
+
+    output.info(format!(
+        "Selected version manager: {} (set {VERSION_MANAGER_OVERRIDE}=none to use the OS package manager)",
+        manager.name()
+    ));
+    output.info(format!("Resolved version: {version}"));
+    let mut outcome = execute_or_print_dry_run(&command, options.dry_run, output, |command| {
+        execute_install(command, output)
+    });
+    if outcome.is_ok() && !options.dry_run && !options.frozen {
+        outcome = record_in_lock(config.is_some(), lock_path, &logical_target, locked, None);
+    }
+    let (postinstall, outcome) = match outcome {
+        Ok(()) => run_postinstall(&logical_target, config, options, output),
+        outcome => (None, outcome),
+    };
+
+    output.json(&InstallReport {
+        target: &logical_target,
+        identifier: &tool,
+        version: Some(&version),
+        manager: manager.name(),
+        command: command.render(),
+        note: None,
+        dry_run: options.dry_run,
+        already_installed: false,
+        postinstall,
+    })?;
+    outcome
+}
+
+/// A pinned target handed to asdf/mise instead of the OS package manager.
+struct VersionManagerRoute {
+    manager: VersionManager,
+    target: String,
+    tool: String,
+    version: String,
+    command: InstallCommand,
+}
+
+impl VersionManagerRoute {
+    fn locked_target(&self) -> LockedTarget {
+        LockedTarget {
+            manager: self.manager.name().to_string(),
+            identifier: self.tool.clone(),
+            version: Some(self.version.clone()),
+            command: self.command.render(),
+        }
+    }
+}
+
+/// Where `raw_spec` goes when the project uses a version manager and the
+/// target is pinned (inline or in qbit.yml); `None` otherwise, including
+/// whenever `--manager` or `install.manager` names a package manager.
+fn version_manager_route(
+    raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+) -> Result<Option<VersionManagerRoute>> {
+    if options.manager.is_some() || configured_manager(config).is_some() {
+        return Ok(None);
     }
+    let project_dir = config
+        .and_then(|cfg| cfg.path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let Some(manager) = detect_version_manager(project_dir)? else {
+        return Ok(None);
+    };
 
-    execute_or_print_dry_run(&plan.command, dry_run, execute_install)
+    let (logical_target, inline_version) = parse_target_spec(raw_spec)?;
+    let spec = config.and_then(|cfg| cfg.install_target_case_insensitive(&logical_target));
+    let Some(version) =
+        inline_version.or_else(|| spec.and_then(|(_, spec)| spec.version().map(str::to_string)))
+    else {
+        return Ok(None);
+    };
+    let tool = spec
+        .and_then(|(_, spec)| spec.identifier(manager.name()))
+        .unwrap_or(&logical_target)
+        .to_string();
+    let command = manager.install_command(&tool, &version);
+    Ok(Some(VersionManagerRoute {
+        manager,
+        target: logical_target,
+        tool,
+        version,
+        command,
+    }))
+}
+
+fn configured_manager(config: Option<&LoadedProjectConfig>) -> Option<&str> {
+    config.and_then(|cfg| cfg.data.install_manager.as_deref())
+}
+
+/// The package manager for this run. The one `lock` cached from an earlier
+/// auto-detection only counts when nothing names a manager explicitly.
+fn select_manager(
+    config: Option<&LoadedProjectConfig>,
+    options: &InstallOptions,
+    lock: Option<&InstallLock>,
+) -> Result<ManagerChoice> {
+    package_manager_choice(ManagerSettings {
+        requested: options.manager.as_deref(),
+        configured: configured_manager(config),
+        cached: lock.and_then(|lock| lock.manager.as_deref()),
+    })
+}
+
+/// Try each manager in turn until one installs the target, reporting every
+/// attempt. Planning errors (e.g. unsupported version pinning) count as failures.
+fn install_with_fallback<F>(
+    raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
+    managers: &[Box<dyn PackageManager>],
+    options: &InstallOptions,
+    output: OutputMode,
+    mut executor: F,
+) -> Result<()>
+where
+    F: FnMut(&InstallCommand) -> Result<()>,
+{
+    let mut attempts = Vec::new();
+    let mut succeeded = None;
+    let mut installed = None;
+
+    for manager in managers {
+        output.info(format!("Trying package manager: {}", manager.name()));
+        let plan = match build_install_plan(raw_spec, config, manager.as_ref(), options) {
+            Ok(plan) => plan,
+            Err(e) => {
+                output.info(format!("{} failed: {e:#}", manager.name()));
+                attempts.push(AttemptReport {
+                    manager: manager.name(),
+                    command: None,
+                    error: Some(format!("{e:#}")),
+                });
+                continue;
+            }
+        };
+
+        let skipped = should_skip_install(&plan, manager.as_ref(), options);
+        let outcome = if skipped {
+            output.info(format!(
+                "`{}` is already installed; skipping (use --force to reinstall).",
+                plan.identifier
+            ));
+            Ok(())
+        } else {
+            refresh_index(manager.as_ref(), options, output, &mut executor).and_then(|()| {
+                execute_or_print_dry_run(&plan.command, options.dry_run, output, &mut executor)
+            })
+        };
+        let error = outcome.err().map(|e| format!("{e:#}"));
+        if let Some(error) = &error {
+            output.info(format!("{} failed: {error}", manager.name()));
+        }
+        let failed = error.is_some();
+        attempts.push(AttemptReport {
+            manager: manager.name(),
+            command: Some(plan.command.render()),
+            error,
+        });
+        if !failed {
+            succeeded = Some(manager.name());
+            installed = (!skipped).then_some(plan.target);
+            break;
+        }
+    }
+
+    let (postinstall, outcome) = match installed {
+        Some(target) => run_postinstall(&target, config, options, output),
+        None => (None, Ok(())),
+    };
+    output.json(&FallbackReport {
+        target: raw_spec.trim(),
+        succeeded,
+        attempts: &attempts,
+        postinstall,
+    })?;
+
+    if succeeded.is_none() {
+        let chain = attempts
+            .iter()
+            .map(|attempt| {
+                format!(
+                    "{}: {}",
+                    attempt.manager,
+                    attempt.error.as_deref().unwrap_or("failed")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        bail!(
+            "Every package manager failed to install `{}`. Attempts: {chain}",
+            raw_spec.trim()
+        );
+    }
+    outcome
+}
+
+/// Skip only when the manager positively reports the package as present and
+/// the user neither pinned a version nor asked to force.
+fn should_skip_install(
+    plan: &InstallPlan,
+    manager: &dyn PackageManager,
+    options: &InstallOptions,
+) -> bool {
+    if options.force || plan.requested_version.is_some() {
+        return false;
+    }
+    manager.is_installed(&plan.identifier) == Some(true)
 }
 
 fn parse_target_spec(spec: &str) -> Result<(String, Option<String>)> {
@@ -53,112 +1096,212 @@ fn parse_target_spec(spec: &str) -> Result<(String, Option<String>)> {
                 "Version after `:` is empty. Use `qbit install {logical_name}` or provide a version."
             );
         }
+        validate_target_name(logical_name, trimmed)?;
         return Ok((
             logical_name.to_string(),
             Some(requested_version.to_string()),
         ));
     }
 
+    validate_target_name(trimmed, trimmed)?;
     Ok((trimmed.to_string(), None))
 }
 
+/// Reject target names no package manager or `install:` entry could use, so
+/// the mistake is reported against what the user typed (`spec`) instead of
+/// surfacing as a manager error.
+fn validate_target_name(name: &str, spec: &str) -> Result<()> {
+    if name.chars().any(char::is_whitespace) {
+        bail!(
+            "Install target `{spec}` contains whitespace. Pass each target as its own argument, e.g. `qbit install git curl`."
+        );
+    }
+    if let Some(bad) = name
+        .chars()
+        .find(|c| !c.is_alphanumeric() && !TARGET_NAME_PUNCTUATION.contains(*c))
+    {
+        bail!(
+            "Install target `{spec}` contains `{bad}`, which is not allowed in a target name. Use letters, digits, and `{TARGET_NAME_PUNCTUATION}`."
+        );
+    }
+    Ok(())
+}
+
 fn resolve_identifier(
     spec: &InstallSpec,
     manager: &dyn PackageManager,
     logical_target: &str,
-) -> String {
+) -> (String, IdentifierSource) {
     if let Some(identifier) = spec.global_identifier() {
-        return identifier.to_string();
+        return (identifier.to_string(), IdentifierSource::Entry);
     }
 
     for key in manager.config_keys() {
         if let Some(identifier) = spec.identifier(key) {
-            return identifier.to_string();
+            return (identifier.to_string(), IdentifierSource::ManagerKey(key));
         }
     }
 
     if let Some(default_identifier) = spec.identifier("default") {
-        return default_identifier.to_string();
+        return (default_identifier.to_string(), IdentifierSource::DefaultKey);
     }
 
-    logical_target.to_string()
+    (logical_target.to_string(), IdentifierSource::TargetName)
 }
 
 fn build_install_plan(
     raw_spec: &str,
+    config: Option<&LoadedProjectConfig>,
     manager: &dyn PackageManager,
-    yes: bool,
+    options: &InstallOptions,
 ) -> Result<InstallPlan> {
     let (logical_target, inline_version) = parse_target_spec(raw_spec)?;
 
     let mut configured_version: Option<String> = None;
     let mut identifier = logical_target.clone();
+    let mut identifier_source = IdentifierSource::TargetName;
+    let mut config_source = None;
+    let mut note = None;
+    let mut winget_match = WingetMatch::Id;
 
-    if let Some(cfg) = load_project_config()? {
+    if let Some(cfg) = config {
         if let Some((entry_name, spec)) = cfg.install_target_case_insensitive(&logical_target) {
             configured_version = spec.version().map(|version| version.to_string());
-            identifier = resolve_identifier(spec, manager, &logical_target);
-            println!(
-                "Using install config `{}` from {}",
-                entry_name,
-                cfg.path.display()
-            );
+            note = spec.note().map(|note| note.to_string());
+            winget_match = spec.winget_match();
+            (identifier, identifier_source) = resolve_identifier(spec, manager, &logical_target);
+            config_source = Some((entry_name.to_string(), cfg.path.clone()));
         }
     }
 
+    let note = note.or_else(|| builtin_note(&logical_target).map(str::to_string));
     let inline_overrode_config = inline_version.is_some() && configured_version.is_some();
     let requested_version = inline_version.or(configured_version.clone());
 
-    let mut command = manager.build_install_cmd(&identifier, requested_version.as_deref())?;
-    if yes {
+    // `dnf reinstall` and `brew reinstall` fail on a package that isn't
+    // installed yet, so a forced first install stays a plain install.
+    let mut command = if options.force && manager.is_installed(&identifier) == Some(true) {
+        manager.build_reinstall_cmd(&identifier, requested_version.as_deref())?
+    } else {
+        manager.build_install_cmd(&identifier, requested_version.as_deref())?
+    };
+    if options.yes {
         manager.apply_yes_flag(&mut command);
     }
+    if options.accept_licenses {
+        manager.apply_accept_licenses(&mut command);
+    }
+    if winget_match == WingetMatch::Name {
+        manager.apply_match_by_name(&mut command);
+    }
+    let command = with_optional_sudo(manager, command, options.no_sudo);
 
     Ok(InstallPlan {
+        target: logical_target,
         manager_name: manager.name().to_string(),
         identifier,
+        identifier_source,
         requested_version,
         inline_overrode_config,
+        config_source,
         command,
+        note,
     })
 }
 
-fn execute_install(command: &InstallCommand) -> Result<()> {
-    let status = Command::new(&command.program)
-        .args(&command.args)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status()
+/// Follow-up advice for well-known targets, used when qbit.yml has no `note`.
+fn builtin_note(target: &str) -> Option<&'static str> {
+    match target.to_ascii_lowercase().as_str() {
+        "java" | "jdk" => Some("Set JAVA_HOME to the installed JDK if your build tools need it."),
+        "python" => Some("Run `qbit py init` to create a project venv with this interpreter."),
+        _ => None,
+    }
+}
+
+/// Refresh the manager's package index before the first install of this run
+/// when it needs one, or whenever `--refresh` asks for it. The manager only
+/// counts as refreshed once the update succeeds, so a failed update is retried
+/// before the next target.
+fn refresh_index<F>(
+    manager: &dyn PackageManager,
+    options: &InstallOptions,
+    output: OutputMode,
+    executor: F,
+) -> Result<()>
+where
+    F: FnOnce(&InstallCommand) -> Result<()>,
+{
+    let Some(update) = index_update_command(manager, options) else {
+        return Ok(());
+    };
+    // Held across the update so a parallel install waits for it to finish.
+    let mut refreshed = REFRESHED_INDEXES
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if refreshed.contains(&manager.name()) {
+        return Ok(());
+    }
+    execute_or_print_dry_run(&update, options.dry_run, output, executor)?;
+    refreshed.push(manager.name());
+    Ok(())
+}
+
+fn index_update_command(
+    manager: &dyn PackageManager,
+    options: &InstallOptions,
+) -> Option<InstallCommand> {
+    if !options.refresh && !manager.needs_index_update() {
+        return None;
+    }
+    Some(with_optional_sudo(
+        manager,
+        manager.build_update_cmd()?,
+        options.no_sudo,
+    ))
+}
+
+fn execute_install(command: &InstallCommand, output: OutputMode) -> Result<()> {
+    ensure_sudo_wont_block(command)?;
+    let status = output
+        .run_child(
+            Command::new(&command.program)
+                .args(&command.args)
+                .stdin(Stdio::inherit())
+                .stderr(Stdio::inherit()),
+        )
         .with_context(|| format!("running installer command: {}", command.render()))?;
 
     if !status.success() {
-        bail!(
-            "Installer command failed (exit code {}): {}",
-            status.code().unwrap_or(1),
-            command.render()
-        );
+        bail!(QbitError::InstallerFailed {
+            command: command.render(),
+            code: status.code(),
+        });
     }
 
     Ok(())
 }
 
-fn execute_or_print_dry_run<F>(command: &InstallCommand, dry_run: bool, executor: F) -> Result<()>
+fn execute_or_print_dry_run<F>(
+    command: &InstallCommand,
+    dry_run: bool,
+    output: OutputMode,
+    executor: F,
+) -> Result<()>
 where
     F: FnOnce(&InstallCommand) -> Result<()>,
 {
     if dry_run {
-        println!("[dry-run] {}", command.render());
+        output.info(format!("[dry-run] {}", command.render()));
         return Ok(());
     }
 
-    println!("Executing: {}", command.render());
+    output.info(format!("Executing: {}", command.render()));
     executor(command)
 }
 
 #[cfg(test)]
 mod tests {
-    use std::ffi::OsString;
+    use crate::utils::test_env::EnvGuard;
 
     use proptest::prelude::*;
     use serial_test::serial;
@@ -193,42 +1336,257 @@ mod tests {
         }
     }
 
-    struct EnvGuard {
-        key: &'static str,
-        original: Option<OsString>,
+    fn plan_from_env_override(raw_spec: &str, yes: bool) -> Result<InstallPlan> {
+        let raw = std::env::var("QBIT_PACKAGE_MANAGER")
+            .expect("QBIT_PACKAGE_MANAGER must be set for this test");
+        let manager = package_manager_from_name(&raw)
+            .ok_or_else(|| anyhow::anyhow!("unknown package manager in test: {raw}"))?;
+        let options = InstallOptions {
+            yes,
+            ..Default::default()
+        };
+        build_install_plan(raw_spec, None, manager.as_ref(), &options)
     }
 
-    impl EnvGuard {
-        fn set(key: &'static str, value: &str) -> Self {
-            let original = std::env::var_os(key);
-            // SAFETY: tests using this helper are marked `serial`, so there is no
-            // concurrent environment mutation within this process.
-            unsafe { std::env::set_var(key, value) };
-            Self { key, original }
+    struct InstalledPm;
+
+    impl PackageManager for InstalledPm {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn executable(&self) -> &'static str {
+            "fake"
+        }
+
+        fn config_keys(&self) -> &'static [&'static str] {
+            &["fake"]
+        }
+
+        fn build_install_cmd(
+            &self,
+            identifier: &str,
+            _version: Option<&str>,
+        ) -> Result<InstallCommand> {
+            Ok(InstallCommand::new(
+                "fake",
+                vec!["install".to_string(), identifier.to_string()],
+            ))
+        }
+
+        fn is_installed(&self, _identifier: &str) -> Option<bool> {
+            Some(true)
         }
     }
 
-    impl Drop for EnvGuard {
-        fn drop(&mut self) {
-            match &self.original {
-                Some(value) => {
-                    // SAFETY: see `EnvGuard::set`; restoration happens in the same serial test.
-                    unsafe { std::env::set_var(self.key, value) };
-                }
-                None => {
-                    // SAFETY: see `EnvGuard::set`; restoration happens in the same serial test.
-                    unsafe { std::env::remove_var(self.key) };
-                }
-            }
+    fn plan_for(identifier: &str, version: Option<&str>) -> InstallPlan {
+        InstallPlan {
+            target: identifier.to_string(),
+            manager_name: "fake".to_string(),
+            identifier: identifier.to_string(),
+            identifier_source: IdentifierSource::TargetName,
+            requested_version: version.map(str::to_string),
+            inline_overrode_config: false,
+            config_source: None,
+            command: InstallCommand::new("fake", vec!["install".to_string()]),
+            note: None,
         }
     }
 
-    fn plan_from_env_override(raw_spec: &str, yes: bool) -> Result<InstallPlan> {
-        let raw = std::env::var("QBIT_PACKAGE_MANAGER")
-            .expect("QBIT_PACKAGE_MANAGER must be set for this test");
-        let manager = package_manager_from_name(&raw)
-            .ok_or_else(|| anyhow::anyhow!("unknown package manager in test: {raw}"))?;
-        build_install_plan(raw_spec, manager.as_ref(), yes)
+    #[test]
+    fn skips_install_when_already_present() {
+        let plan = plan_for("python", None);
+        assert!(should_skip_install(
+            &plan,
+            &InstalledPm,
+            &InstallOptions::default()
+        ));
+    }
+
+    #[test]
+    fn force_or_pinned_version_bypasses_skip() {
+        let forced = InstallOptions {
+            force: true,
+            ..Default::default()
+        };
+        assert!(!should_skip_install(
+            &plan_for("python", None),
+            &InstalledPm,
+            &forced
+        ));
+        assert!(!should_skip_install(
+            &plan_for("python", Some("3.12")),
+            &InstalledPm,
+            &InstallOptions::default()
+        ));
+        assert!(!should_skip_install(
+            &plan_for("python", None),
+            &DummyPm,
+            &InstallOptions::default()
+        ));
+    }
+
+    /// Has a dedicated reinstall mode, like dnf, and a fixed install state.
+    struct ReinstallPm(Option<bool>);
+
+    impl PackageManager for ReinstallPm {
+        fn name(&self) -> &'static str {
+            "dnf"
+        }
+
+        fn executable(&self) -> &'static str {
+            "dnf"
+        }
+
+        fn config_keys(&self) -> &'static [&'static str] {
+            &["dnf"]
+        }
+
+        fn build_install_cmd(
+            &self,
+            identifier: &str,
+            _version: Option<&str>,
+        ) -> Result<InstallCommand> {
+            Ok(InstallCommand::new(
+                "dnf",
+                vec!["install".to_string(), identifier.to_string()],
+            ))
+        }
+
+        fn build_reinstall_cmd(
+            &self,
+            identifier: &str,
+            _version: Option<&str>,
+        ) -> Result<InstallCommand> {
+            Ok(InstallCommand::new(
+                "dnf",
+                vec!["reinstall".to_string(), identifier.to_string()],
+            ))
+        }
+
+        fn is_installed(&self, _identifier: &str) -> Option<bool> {
+            self.0
+        }
+    }
+
+    #[test]
+    fn force_reinstalls_only_packages_that_are_installed() {
+        let options = InstallOptions {
+            force: true,
+            no_sudo: true,
+            ..Default::default()
+        };
+        let command = |installed| {
+            build_install_plan("git", None, &ReinstallPm(installed), &options)
+                .expect("plan")
+                .command
+                .render()
+        };
+        assert_eq!(command(Some(true)), "dnf reinstall git");
+        assert_eq!(command(Some(false)), "dnf install git");
+        assert_eq!(command(None), "dnf install git");
+    }
+
+    struct NamedPm(&'static str);
+
+    impl PackageManager for NamedPm {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        fn executable(&self) -> &'static str {
+            self.0
+        }
+
+        fn config_keys(&self) -> &'static [&'static str] {
+            &[]
+        }
+
+        fn build_install_cmd(
+            &self,
+            identifier: &str,
+            _version: Option<&str>,
+        ) -> Result<InstallCommand> {
+            Ok(InstallCommand::new(
+                self.0,
+                vec!["install".to_string(), identifier.to_string()],
+            ))
+        }
+    }
+
+    #[test]
+    fn try_all_falls_back_to_next_manager() {
+        let managers: Vec<Box<dyn PackageManager>> =
+            vec![Box::new(NamedPm("first")), Box::new(NamedPm("second"))];
+        let mut executed = Vec::new();
+        install_with_fallback(
+            "foo",
+            None,
+            &managers,
+            &InstallOptions::default(),
+            OutputMode::Human,
+            |command| {
+                executed.push(command.program.clone());
+                if command.program == "first" {
+                    bail!("package not found");
+                }
+                Ok(())
+            },
+        )
+        .expect("second manager succeeds");
+        assert_eq!(executed, vec!["first".to_string(), "second".to_string()]);
+    }
+
+    #[test]
+    fn postinstall_runs_in_project_dir_only_after_an_install() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        std::fs::write(
+            tmp.path().join("qbit.yml"),
+            "install:\n  foo:\n    postinstall: \"echo done > postinstall.txt\"\n",
+        )
+        .expect("write qbit.yml");
+        let config = crate::config::load_project_config_from_dir(tmp.path())
+            .expect("load")
+            .expect("config present");
+        let marker = tmp.path().join("postinstall.txt");
+        let install = |manager: Box<dyn PackageManager>| {
+            install_with_fallback(
+                "foo",
+                Some(&config),
+                &[manager],
+                &InstallOptions::default(),
+                OutputMode::Human,
+                |_| {
+                    assert!(!marker.exists(), "postinstall ran before the install");
+                    Ok(())
+                },
+            )
+        };
+
+        install(Box::new(InstalledPm)).expect("skipped install");
+        assert!(!marker.exists());
+
+        install(Box::new(NamedPm("first"))).expect("install");
+        let written = std::fs::read_to_string(&marker).expect("postinstall output");
+        assert_eq!(written.trim(), "done");
+    }
+
+    #[test]
+    fn try_all_reports_every_failed_attempt() {
+        let managers: Vec<Box<dyn PackageManager>> =
+            vec![Box::new(NamedPm("first")), Box::new(NamedPm("second"))];
+        let err = install_with_fallback(
+            "foo",
+            None,
+            &managers,
+            &InstallOptions::default(),
+            OutputMode::Human,
+            |command| bail!("{} has no foo", command.program),
+        )
+        .expect_err("all managers fail");
+        let message = err.to_string();
+        assert!(message.contains("first: first has no foo"), "{message}");
+        assert!(message.contains("second: second has no foo"), "{message}");
     }
 
     #[test]
@@ -252,6 +1610,52 @@ mod tests {
         assert_eq!(parsed.1, None);
     }
 
+    #[test]
+    fn parse_target_rejects_empty_and_spaced_names() {
+        let err = parse_target_spec("   ").expect_err("must fail");
+        assert!(err.to_string().contains("Install target is empty"), "{err}");
+
+        let err = parse_target_spec(" :3.12").expect_err("must fail");
+        assert!(
+            err.to_string().contains("name is empty before `:`"),
+            "{err}"
+        );
+
+        let err = parse_target_spec("visual studio code").expect_err("must fail");
+        assert!(
+            err.to_string()
+                .contains("Install target `visual studio code` contains whitespace"),
+            "{err}"
+        );
+
+        let err = parse_target_spec("node js:20").expect_err("must fail");
+        assert!(
+            err.to_string()
+                .contains("Install target `node js:20` contains whitespace"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn parse_target_rejects_shell_characters_but_keeps_real_names() {
+        let err = parse_target_spec("git;rm").expect_err("must fail");
+        assert!(
+            err.to_string()
+                .contains("Install target `git;rm` contains `;`"),
+            "{err}"
+        );
+        for name in [
+            "python3.12",
+            "postgresql@15",
+            "extras/vscode",
+            "g++",
+            "Git.Git",
+            "node_js",
+        ] {
+            assert_eq!(parse_target_spec(name).expect(name).0, name);
+        }
+    }
+
     #[test]
     fn parse_target_rejects_empty_version() {
         let err = parse_target_spec("python:").expect_err("must fail");
@@ -268,17 +1672,36 @@ mod tests {
             ]
             .into_iter()
             .collect(),
+            note: None,
+            postinstall: None,
+            winget_match: WingetMatch::Id,
         };
 
-        let resolved = resolve_identifier(&spec, &DummyPm, "python");
+        let (resolved, source) = resolve_identifier(&spec, &DummyPm, "python");
         assert_eq!(resolved, "Python.Python.3.12");
+        assert_eq!(source, IdentifierSource::ManagerKey("winget"));
+
+        let fallback = InstallSpec::Detailed {
+            version: None,
+            identifiers: [("default".to_string(), "python3".to_string())]
+                .into_iter()
+                .collect(),
+            note: None,
+            postinstall: None,
+            winget_match: WingetMatch::Id,
+        };
+        assert_eq!(
+            resolve_identifier(&fallback, &DummyPm, "python"),
+            ("python3".to_string(), IdentifierSource::DefaultKey)
+        );
     }
 
     #[test]
     fn resolve_identifier_uses_global_string_without_changes() {
         let spec = InstallSpec::Identifier("My.Mixed.Case.Identifier".to_string());
-        let resolved = resolve_identifier(&spec, &DummyPm, "python");
+        let (resolved, source) = resolve_identifier(&spec, &DummyPm, "python");
         assert_eq!(resolved, "My.Mixed.Case.Identifier");
+        assert_eq!(source, IdentifierSource::Entry);
     }
 
     #[test]
@@ -287,7 +1710,7 @@ mod tests {
             "fake-installer",
             vec!["install".to_string(), "pkg".to_string()],
         );
-        let result = execute_or_print_dry_run(&command, true, |_| {
+        let result = execute_or_print_dry_run(&command, true, OutputMode::Human, |_| {
             panic!("executor must not be called in dry-run mode");
         });
         assert!(result.is_ok());
@@ -299,7 +1722,7 @@ mod tests {
             "fake-installer",
             vec!["install".to_string(), "pkg".to_string()],
         );
-        let result = execute_or_print_dry_run(&command, false, |_| Ok(()));
+        let result = execute_or_print_dry_run(&command, false, OutputMode::Human, |_| Ok(()));
         assert!(result.is_ok());
     }
 
@@ -355,6 +1778,191 @@ mod tests {
         assert!(plan.command.render().contains("python=3.12"));
     }
 
+    struct IndexedPm {
+        name: &'static str,
+        needs_update: bool,
+    }
+
+    impl PackageManager for IndexedPm {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn executable(&self) -> &'static str {
+            "fake"
+        }
+
+        fn config_keys(&self) -> &'static [&'static str] {
+            &[]
+        }
+
+        fn build_install_cmd(
+            &self,
+            identifier: &str,
+            _version: Option<&str>,
+        ) -> Result<InstallCommand> {
+            Ok(InstallCommand::new(
+                "fake",
+                vec!["install".to_string(), identifier.to_string()],
+            ))
+        }
+
+        fn needs_index_update(&self) -> bool {
+            self.needs_update
+        }
+
+        fn build_update_cmd(&self) -> Option<InstallCommand> {
+            Some(InstallCommand::new("fake", vec!["update".to_string()]))
+        }
+    }
+
+    #[test]
+    fn index_update_runs_once_per_manager_when_needed_or_requested() {
+        let options = InstallOptions::default();
+        let needy = IndexedPm {
+            name: "needs-index-update",
+            needs_update: true,
+        };
+        let mut ran = Vec::new();
+        for _ in 0..2 {
+            refresh_index(&needy, &options, OutputMode::Human, |update| {
+                ran.push(update.render());
+                Ok(())
+            })
+            .expect("refresh");
+        }
+        assert_eq!(ran, ["fake update"]);
+
+        let optional = IndexedPm {
+            name: "optional-index-update",
+            needs_update: false,
+        };
+        assert!(index_update_command(&optional, &options).is_none());
+        let refresh = InstallOptions {
+            refresh: true,
+            ..Default::default()
+        };
+        assert!(index_update_command(&optional, &refresh).is_some());
+        assert!(index_update_command(&DummyPm, &refresh).is_none());
+    }
+
+    #[test]
+    fn failed_index_update_is_retried_by_the_next_install() {
+        let options = InstallOptions::default();
+        let flaky = IndexedPm {
+            name: "flaky-index-update",
+            needs_update: true,
+        };
+        refresh_index(&flaky, &options, OutputMode::Human, |_| {
+            bail!("mirror unreachable")
+        })
+        .expect_err("update fails");
+
+        let mut retried = false;
+        refresh_index(&flaky, &options, OutputMode::Human, |_| {
+            retried = true;
+            Ok(())
+        })
+        .expect("second refresh");
+        assert!(retried);
+    }
+
+    #[test]
+    #[serial]
+    fn validate_reports_targets_the_manager_cannot_install() {
+        let cache = tempfile::tempdir().expect("tempdir");
+        let _cache = EnvGuard::set(
+            "QBIT_PACMAN_CACHE_DIR",
+            cache.path().to_str().expect("utf-8 path"),
+        );
+        let config = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: crate::config::parse_yaml_str(
+                "install:\n  postgres:\n    version: \"15\"\n  git: git\n",
+            )
+            .expect("yaml parse"),
+        };
+        let pacman = package_manager_from_name("pacman").expect("pacman");
+        let targets = ["git".to_string(), "postgres".to_string()];
+
+        let results = validate_with(
+            &targets,
+            Some(&config),
+            pacman.as_ref(),
+            &InstallOptions::default(),
+        );
+        let command = results[0].command.as_deref().expect("git installs");
+        assert!(command.ends_with("pacman -S git"), "{command}");
+        assert_eq!(results[0].error, None);
+        assert_eq!(results[1].command, None);
+        let error = results[1].error.as_deref().expect("version refused");
+        assert!(
+            error.contains("`pacman` cannot pin `postgres` to version `15`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn no_sudo_plan_runs_system_manager_directly() {
+        let apt = package_manager_from_name("apt-get").expect("apt-get");
+        let options = InstallOptions {
+            no_sudo: true,
+            yes: true,
+            ..Default::default()
+        };
+        let plan = build_install_plan("git", None, apt.as_ref(), &options).expect("plan");
+        assert_eq!(plan.command.render(), "apt-get install -y git");
+    }
+
+    #[test]
+    fn plan_note_prefers_config_and_falls_back_to_builtin_hints() {
+        let config = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: crate::config::parse_yaml_str(
+                "install:\n  java:\n    hint: \"Use the team JDK mirror.\"\n",
+            )
+            .expect("yaml parse"),
+        };
+        let options = InstallOptions::default();
+
+        let plan = build_install_plan("java", Some(&config), &DummyPm, &options).expect("plan");
+        assert_eq!(plan.note.as_deref(), Some("Use the team JDK mirror."));
+
+        let plan = build_install_plan("python", Some(&config), &DummyPm, &options).expect("plan");
+        assert!(plan.note.is_some_and(|note| note.contains("qbit py init")));
+
+        let plan = build_install_plan("ripgrep", None, &DummyPm, &options).expect("plan");
+        assert_eq!(plan.note, None);
+    }
+
+    #[test]
+    fn winget_match_name_searches_by_display_name() {
+        let config = LoadedProjectConfig {
+            path: PathBuf::from("qbit.yml"),
+            data: crate::config::parse_yaml_str(
+                "install:\n  vscode:\n    winget_match: name\n    identifiers:\n      winget: \"Microsoft Visual Studio Code\"\n  git:\n    identifiers:\n      winget: Git.Git\n",
+            )
+            .expect("yaml parse"),
+        };
+        let winget = package_manager_from_name("winget").expect("winget");
+        let options = InstallOptions::default();
+
+        let plan =
+            build_install_plan("vscode", Some(&config), winget.as_ref(), &options).expect("plan");
+        assert_eq!(
+            plan.command.render(),
+            "winget install --name \"Microsoft Visual Studio Code\" --exact --accept-source-agreements --accept-package-agreements"
+        );
+
+        let plan =
+            build_install_plan("git", Some(&config), winget.as_ref(), &options).expect("plan");
+        assert!(
+            plan.command
+                .render()
+                .starts_with("winget install --id Git.Git --exact")
+        );
+    }
+
     #[test]
     #[ignore = "Documenting intended behavior: install identifiers must preserve exact casing."]
     fn identifiers_preserve_casing_in_plan() {
@@ -363,12 +1971,55 @@ mod tests {
             identifiers: [("winget".to_string(), "Python.Python.3.12".to_string())]
                 .into_iter()
                 .collect(),
+            note: None,
+            postinstall: None,
+            winget_match: WingetMatch::Id,
         };
 
-        let resolved = resolve_identifier(&spec, &DummyPm, "python");
+        let (resolved, _) = resolve_identifier(&spec, &DummyPm, "python");
         assert_eq!(resolved, "Python.Python.3.12");
     }
 
+    #[test]
+    fn jobs_are_limited_to_one_for_locking_managers() {
+        let apt = package_manager_from_name("apt-get").expect("apt-get");
+        let brew = package_manager_from_name("brew").expect("brew");
+        assert_eq!(jobs_for_manager(4, apt.as_ref()), 1);
+        assert_eq!(jobs_for_manager(4, brew.as_ref()), 4);
+        assert_eq!(jobs_for_manager(1, brew.as_ref()), 1);
+    }
+
+    #[test]
+    fn lock_entry_supplies_version_until_qbit_yml_disagrees() {
+        let options = InstallOptions::default();
+        let open = build_install_plan("foo", None, &DummyPm, &options).expect("plan");
+        let pinned = build_install_plan("foo:2.0", None, &DummyPm, &options).expect("plan");
+        let entry = LockedTarget {
+            manager: "winget".to_string(),
+            identifier: "foo".to_string(),
+            version: Some("1.0".to_string()),
+            command: "winget install foo".to_string(),
+        };
+
+        assert_eq!(
+            locked_version("foo", &open.locked_target(), Some(&entry), true).expect("fresh lock"),
+            Some("1.0".to_string())
+        );
+        assert_eq!(
+            locked_version("foo", &pinned.locked_target(), Some(&entry), false).expect("replaced"),
+            None
+        );
+        let stale = locked_version("foo", &pinned.locked_target(), Some(&entry), true).unwrap_err();
+        assert!(stale.to_string().contains("stale"), "{stale}");
+        assert!(locked_version("foo", &open.locked_target(), None, true).is_err());
+
+        let other_manager = LockedTarget {
+            manager: "brew".to_string(),
+            ..entry
+        };
+        assert!(locked_version("foo", &open.locked_target(), Some(&other_manager), true).is_err());
+    }
+
     proptest! {
         #[test]
         fn parse_target_spec_property_never_panics(
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::LoadedProjectConfig;
+
+/// Lock file written next to qbit.yml (or in the current directory without one).
+pub const LOCK_FILE: &str = "qbit.lock";
+
+const LOCK_HEADER: &str = "# Generated by `qbit install`; commit it for reproducible installs.\n";
+
+/// Resolved install targets, keyed by logical target name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallLock {
+    /// Package manager auto-detection picked on an earlier run, reused while
+    /// it stays installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manager: Option<String>,
+    #[serde(default)]
+    pub targets: BTreeMap<String, LockedTarget>,
+}
+
+/// What `qbit install <target>` resolved to the last time it succeeded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedTarget {
+    pub manager: String,
+    pub identifier: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub command: String,
+}
+
+impl InstallLock {
+    /// Read the lock at `path`; `None` when there isn't one yet.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("reading lock file at {}", path.display()))?;
+        let lock = toml::from_str(&content).with_context(|| {
+            format!(
+                "parsing lock file at {}; delete it and rerun `qbit install` to regenerate it",
+                path.display()
+            )
+        })?;
+        Ok(Some(lock))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let body = toml::to_string_pretty(self).context("serializing qbit.lock")?;
+        fs::write(path, format!("{LOCK_HEADER}{body}"))
+            .with_context(|| format!("writing lock file at {}", path.display()))
+    }
+
+    /// Lock entries are matched case-insensitively, like `install:` entries.
+    pub fn entry(&self, target: &str) -> Option<&LockedTarget> {
+        self.targets
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(target))
+            .map(|(_, entry)| entry)
+    }
+
+    /// Insert or replace the entry for `target`, reusing an existing key that
+    /// differs only in case.
+    pub fn record(&mut self, target: &str, entry: LockedTarget) {
+        let key = self
+            .targets
+            .keys()
+            .find(|name| name.eq_ignore_ascii_case(target))
+            .cloned()
+            .unwrap_or_else(|| target.to_string());
+        self.targets.insert(key, entry);
+    }
+}
+
+/// `qbit.lock` beside the loaded config, or in the current directory.
+pub fn lock_path(config: Option<&LoadedProjectConfig>) -> PathBuf {
+    config
+        .and_then(|cfg| cfg.path.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .join(LOCK_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn entry(version: Option<&str>) -> LockedTarget {
+        LockedTarget {
+            manager: "apt-get".to_string(),
+            identifier: "postgresql".to_string(),
+            version: version.map(str::to_string),
+            command: "apt-get install postgresql".to_string(),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let tmp = tempdir().expect("tempdir");
+        let path = tmp.path().join(LOCK_FILE);
+        assert_eq!(InstallLock::load(&path).expect("load"), None);
+
+        let mut lock = InstallLock::default();
+        lock.record("postgres", entry(Some("15")));
+        lock.record("redis", entry(None));
+        lock.save(&path).expect("save");
+
+        let content = fs::read_to_string(&path).expect("read lock");
+        assert!(content.starts_with(LOCK_HEADER), "{content}");
+        assert!(content.contains("[targets.postgres]"), "{content}");
+        assert_eq!(InstallLock::load(&path).expect("load"), Some(lock));
+    }
+
+    #[test]
+    fn records_and_finds_entries_case_insensitively() {
+        let mut lock = InstallLock::default();
+        lock.record("Postgres", entry(Some("15")));
+        lock.record("postgres", entry(Some("16")));
+        assert_eq!(lock.targets.len(), 1);
+        assert_eq!(
+            lock.entry("POSTGRES").and_then(|e| e.version.as_deref()),
+            Some("16")
+        );
+    }
+}
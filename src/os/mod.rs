@@ -1,3 +1,5 @@
 pub mod install;
+pub mod lockfile;
 pub mod package_manager;
 pub mod upgrade;
+pub mod version_manager;
@@ -1,8 +1,17 @@
 use std::env;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use anyhow::{Result, bail};
 
+use crate::error::QbitError;
+use crate::utils::net::is_truthy;
+use crate::utils::paths::find_executable;
+
+/// A structured installer invocation. It is always executed directly (program +
+/// argv) rather than through a shell, so identifiers are never word-split.
 #[derive(Debug, Clone)]
 pub struct InstallCommand {
     pub program: String,
@@ -17,6 +26,7 @@ impl InstallCommand {
         }
     }
 
+    /// Human-readable rendering for logs and `--dry-run`; never fed to a shell.
     pub fn render(&self) -> String {
         let mut parts = Vec::with_capacity(self.args.len() + 1);
         parts.push(quote_for_display(&self.program));
@@ -38,48 +48,241 @@ pub trait PackageManager {
 
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand>;
 
+    /// Command used for `qbit install --force`. Managers with a dedicated
+    /// reinstall mode override this; the rest fall back to a normal install.
+    fn build_reinstall_cmd(
+        &self,
+        identifier: &str,
+        version: Option<&str>,
+    ) -> Result<InstallCommand> {
+        self.build_install_cmd(identifier, version)
+    }
+
+    /// System package managers write outside the user's home and run via
+    /// `sudo` (see [`with_optional_sudo`]); user-scoped ones don't.
+    fn needs_root(&self) -> bool {
+        false
+    }
+
     fn apply_yes_flag(&self, _command: &mut InstallCommand) {}
-}
 
-pub fn detect_package_manager() -> Result<Box<dyn PackageManager>> {
-    if let Ok(raw_override) = env::var("QBIT_PACKAGE_MANAGER") {
-        let override_name = raw_override.trim();
-        if override_name.is_empty() {
-            bail!(
-                "QBIT_PACKAGE_MANAGER is set but empty. Set it to a supported manager name (for example `apt-get`, `brew`, `winget`) or unset it."
-            );
+    /// Pre-accept package license prompts (`qbit install --accept-licenses`).
+    /// Managers that never prompt, or that always accept (winget), keep the
+    /// command as is.
+    fn apply_accept_licenses(&self, _command: &mut InstallCommand) {}
+
+    /// Look the identifier up by display name instead of package ID
+    /// (`winget_match: name`). Managers without that distinction keep the
+    /// command as is.
+    fn apply_match_by_name(&self, _command: &mut InstallCommand) {}
+
+    /// Whether several installs may run at once (`qbit install --jobs`). System
+    /// managers hold a global lock (dpkg, rpm, MSI), so only user-local ones opt in.
+    fn supports_parallel_installs(&self) -> bool {
+        false
+    }
+
+    /// Whether installs fail on a stale package index unless it's refreshed
+    /// first (apt-get on a fresh image). Such managers refresh once per run.
+    fn needs_index_update(&self) -> bool {
+        false
+    }
+
+    /// Command that refreshes the package index (`qbit install --refresh`).
+    fn build_update_cmd(&self) -> Option<InstallCommand> {
+        None
+    }
+
+    /// Cheap read-only command whose success means `identifier` is installed.
+    fn installed_probe_cmd(&self, _identifier: &str) -> Option<InstallCommand> {
+        None
+    }
+
+    /// Whether a successful probe's output really means installed. Probes
+    /// whose exit status alone answers keep the default.
+    fn probe_reports_installed(&self, _stdout: &str) -> bool {
+        true
+    }
+
+    /// `Some(true/false)` when the manager can tell whether `identifier` is
+    /// installed, `None` when it can't (no probe, or the probe failed to spawn).
+    fn is_installed(&self, identifier: &str) -> Option<bool> {
+        let probe = self.installed_probe_cmd(identifier)?;
+        Command::new(&probe.program)
+            .args(&probe.args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .map(|output| {
+                output.status.success()
+                    && self.probe_reports_installed(&String::from_utf8_lossy(&output.stdout))
+            })
+    }
+
+    /// Read-only command whose output ends with the installed version of
+    /// `identifier`, so unpinned installs can be locked to what they got.
+    fn installed_version_cmd(&self, _identifier: &str) -> Option<InstallCommand> {
+        None
+    }
+
+    /// The installed version of `identifier`, when the manager can report one.
+    fn installed_version(&self, identifier: &str) -> Option<String> {
+        let query = self.installed_version_cmd(identifier)?;
+        let output = Command::new(&query.program)
+            .args(&query.args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
         }
+        parse_installed_version(&String::from_utf8_lossy(&output.stdout))
+    }
+}
 
-        let pm = package_manager_from_name(override_name).ok_or_else(|| {
-            anyhow::anyhow!(
-                "Unknown package manager `{}` in QBIT_PACKAGE_MANAGER. Supported values: apt-get, dnf, pacman, zypper, brew, winget, choco, scoop.",
-                override_name
-            )
-        })?;
-
-        if !pm.is_available() {
-            bail!(
-                "QBIT_PACKAGE_MANAGER is `{}`, but executable `{}` is not available in PATH. Install it or unset QBIT_PACKAGE_MANAGER.",
-                override_name,
-                pm.executable()
-            );
+/// The last field of the first line: `1.2-3` from dpkg-query/rpm, `name 1.2-3`
+/// from pacman, `name|1.2.3` from choco.
+fn parse_installed_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .next()?
+        .rsplit(|c: char| c.is_whitespace() || c == '|')
+        .find(|field| !field.is_empty())
+        .map(str::to_string)
+}
+
+/// Where the package manager for a run may come from, besides
+/// `QBIT_PACKAGE_MANAGER` and auto-detection.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ManagerSettings<'a> {
+    /// `qbit install --manager`.
+    pub requested: Option<&'a str>,
+    /// The project's `install.manager`.
+    pub configured: Option<&'a str>,
+    /// The manager an earlier run auto-detected, as cached in qbit.lock.
+    pub cached: Option<&'a str>,
+}
+
+/// The selected package manager and why it was picked.
+pub struct ManagerChoice {
+    pub manager: Box<dyn PackageManager>,
+    pub reason: String,
+    /// Picked by auto-detection, now or on an earlier run, rather than by a
+    /// setting; only such choices are cached.
+    pub detected: bool,
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Number of auto-detection passes on this thread, so tests can assert
+    /// that a cached manager skips detection.
+    pub(crate) static DETECTIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Pick the package manager: `--manager` first, then `QBIT_PACKAGE_MANAGER`,
+/// then the project's `install.manager`, then the cached manager while it is
+/// still installed, then auto-detection.
+pub fn package_manager_choice(settings: ManagerSettings) -> Result<ManagerChoice> {
+    choose_package_manager(
+        settings,
+        env::var("QBIT_PACKAGE_MANAGER").ok().as_deref(),
+        detection_candidates(),
+        |pm| pm.is_available(),
+    )
+}
+
+fn choose_package_manager(
+    settings: ManagerSettings,
+    env_override: Option<&str>,
+    candidates: Vec<Box<dyn PackageManager>>,
+    is_available: impl Fn(&dyn PackageManager) -> bool,
+) -> Result<ManagerChoice> {
+    let explicit = [
+        (settings.requested, "--manager", "is"),
+        (env_override, "QBIT_PACKAGE_MANAGER", "is set to"),
+        (
+            settings.configured,
+            "install.manager in the project config",
+            "is",
+        ),
+    ];
+    for (value, source, verb) in explicit {
+        if let Some(name) = value {
+            let manager = manager_from_setting(name, source, &is_available)?;
+            return Ok(ManagerChoice {
+                manager,
+                reason: format!("{source} {verb} `{}`", name.trim()),
+                detected: false,
+            });
         }
+    }
 
-        return Ok(pm);
+    // A cached manager that has since been uninstalled (or renamed by hand)
+    // falls through to a fresh detection.
+    if let Some(manager) = settings
+        .cached
+        .and_then(package_manager_from_name)
+        .filter(|pm| is_available(pm.as_ref()))
+    {
+        return Ok(ManagerChoice {
+            manager,
+            reason: "auto-detected on an earlier run and cached in qbit.lock".to_string(),
+            detected: true,
+        });
     }
 
+    #[cfg(test)]
+    DETECTIONS.with(|runs| runs.set(runs.get() + 1));
     let mut checked = Vec::new();
-    for pm in detection_candidates() {
-        checked.push(pm.name());
-        if pm.is_available() {
-            return Ok(pm);
+    for manager in candidates {
+        checked.push(manager.name().to_string());
+        if is_available(manager.as_ref()) {
+            let reason = format!(
+                "auto-detected as the first one available in PATH (checked {})",
+                checked.join(", ")
+            );
+            return Ok(ManagerChoice {
+                manager,
+                reason,
+                detected: true,
+            });
         }
     }
 
-    bail!(
-        "No supported package manager detected in PATH. Checked: {}. Install one of them or set QBIT_PACKAGE_MANAGER.",
-        checked.join(", ")
-    )
+    bail!(QbitError::NoManagerDetected { checked })
+}
+
+/// Resolve an explicitly configured manager name, failing clearly when it is
+/// empty, unknown, or not installed. `source` names where the setting came from.
+fn manager_from_setting(
+    raw: &str,
+    source: &str,
+    is_available: impl Fn(&dyn PackageManager) -> bool,
+) -> Result<Box<dyn PackageManager>> {
+    let name = raw.trim();
+    if name.is_empty() {
+        bail!(
+            "{source} is set but empty. Set it to a supported manager name (for example `apt-get`, `brew`, `winget`) or remove it."
+        );
+    }
+
+    let pm = package_manager_from_name(name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown package manager `{name}` in {source}. Supported values: apt-get, dnf, pacman, zypper, brew, winget, choco, scoop."
+        )
+    })?;
+
+    if !is_available(pm.as_ref()) {
+        bail!(QbitError::ManagerUnavailable {
+            manager: name.to_string(),
+            executable: pm.executable().to_string(),
+            setting: source.to_string(),
+        });
+    }
+
+    Ok(pm)
 }
 
 pub(crate) fn package_manager_from_name(name: &str) -> Option<Box<dyn PackageManager>> {
@@ -96,6 +299,35 @@ pub(crate) fn package_manager_from_name(name: &str) -> Option<Box<dyn PackageMan
     }
 }
 
+/// Every package manager qbit knows about, in detection-preference order.
+pub fn all_package_managers() -> Vec<Box<dyn PackageManager>> {
+    vec![
+        Box::new(AptGet),
+        Box::new(Dnf),
+        Box::new(Pacman),
+        Box::new(Zypper),
+        Box::new(Brew),
+        Box::new(Winget),
+        Box::new(Chocolatey),
+        Box::new(Scoop),
+    ]
+}
+
+/// Auto-detection candidates for this OS that are present in PATH, in
+/// preference order. Used by `qbit install --try-all`.
+pub fn available_package_managers() -> Vec<Box<dyn PackageManager>> {
+    detection_candidates()
+        .into_iter()
+        .filter(|pm| pm.is_available())
+        .collect()
+}
+
+/// Whether auto-detection on this OS considers the manager (others are only
+/// reachable through `QBIT_PACKAGE_MANAGER`).
+pub fn is_detection_candidate(name: &str) -> bool {
+    detection_candidates().iter().any(|pm| pm.name() == name)
+}
+
 fn detection_candidates() -> Vec<Box<dyn PackageManager>> {
     #[cfg(target_os = "linux")]
     let candidates: Vec<Box<dyn PackageManager>> = vec![
@@ -113,21 +345,17 @@ fn detection_candidates() -> Vec<Box<dyn PackageManager>> {
         vec![Box::new(Winget), Box::new(Chocolatey), Box::new(Scoop)];
 
     #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
-    let candidates: Vec<Box<dyn PackageManager>> = vec![
-        Box::new(AptGet),
-        Box::new(Dnf),
-        Box::new(Pacman),
-        Box::new(Zypper),
-        Box::new(Brew),
-        Box::new(Winget),
-        Box::new(Chocolatey),
-        Box::new(Scoop),
-    ];
+    let candidates = all_package_managers();
 
     candidates
 }
 
+/// Scan `PATH` rather than spawning every manager during detection; only
+/// when there is no `PATH` to scan does the OS get asked by running it.
 fn command_exists(executable: &str) -> bool {
+    if env::var_os("PATH").is_some_and(|path| !path.is_empty()) {
+        return find_executable(executable).is_some();
+    }
     Command::new(executable)
         .arg("--version")
         .stdin(Stdio::null())
@@ -167,19 +395,70 @@ fn validate_version<'a>(version: Option<&'a str>, manager: &str) -> Result<Optio
     Ok(Some(trimmed))
 }
 
-fn with_optional_sudo(executable: &str, args: Vec<String>) -> InstallCommand {
-    if cfg!(windows) {
-        return InstallCommand::new(executable.to_string(), args);
+/// `QBIT_NO_SUDO=1` (like `qbit install --no-sudo`) runs system package
+/// managers without the `sudo` prefix, for root or rootless containers.
+fn sudo_disabled() -> bool {
+    env::var("QBIT_NO_SUDO")
+        .map(|value| is_truthy(&value))
+        .unwrap_or(false)
+}
+
+/// Prefix `command` with `sudo` when the manager installs system-wide and
+/// sudo should be used: not on Windows, not with `--no-sudo`/`QBIT_NO_SUDO=1`,
+/// and only if `sudo` exists.
+pub fn with_optional_sudo(
+    manager: &dyn PackageManager,
+    command: InstallCommand,
+    no_sudo: bool,
+) -> InstallCommand {
+    if !manager.needs_root()
+        || !should_use_sudo(no_sudo || sudo_disabled(), || command_exists("sudo"))
+    {
+        return command;
     }
+    prefix_sudo(command)
+}
+
+fn should_use_sudo(disabled: bool, sudo_available: impl FnOnce() -> bool) -> bool {
+    !cfg!(windows) && !disabled && sudo_available()
+}
+
+fn prefix_sudo(command: InstallCommand) -> InstallCommand {
+    let mut args = Vec::with_capacity(command.args.len() + 1);
+    args.push(command.program);
+    args.extend(command.args);
+    InstallCommand::new("sudo", args)
+}
 
-    if command_exists("sudo") {
-        let mut sudo_args = Vec::with_capacity(args.len() + 1);
-        sudo_args.push(executable.to_string());
-        sudo_args.extend(args);
-        return InstallCommand::new("sudo", sudo_args);
+/// Fail fast instead of hanging on a hidden password prompt: a `sudo`
+/// command only runs when sudo is passwordless or a terminal can answer it.
+pub fn ensure_sudo_wont_block(command: &InstallCommand) -> Result<()> {
+    if command.program != "sudo" {
+        return Ok(());
+    }
+    let interactive = std::io::stdin().is_terminal();
+    if sudo_would_block(interactive, sudo_is_passwordless) {
+        bail!(
+            "`{}` needs sudo, which would prompt for a password but stdin is not a terminal. Run `sudo -v` first, configure passwordless sudo, or set QBIT_NO_SUDO=1 to run without sudo (or pass --no-sudo).",
+            command.render()
+        );
     }
+    Ok(())
+}
 
-    InstallCommand::new(executable.to_string(), args)
+/// Only non-interactive sessions need the (slower) `sudo -n true` probe.
+fn sudo_would_block(interactive: bool, passwordless: impl FnOnce() -> bool) -> bool {
+    !interactive && !passwordless()
+}
+
+fn sudo_is_passwordless() -> bool {
+    Command::new("sudo")
+        .args(["-n", "true"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
 }
 
 fn insert_after_subcommand(command: &mut InstallCommand, subcommand: &str, flag: &str) {
@@ -194,6 +473,12 @@ fn insert_after_subcommand(command: &mut InstallCommand, subcommand: &str, flag:
     }
 }
 
+fn replace_subcommand(command: &mut InstallCommand, from: &str, to: &str) {
+    if let Some(arg) = command.args.iter_mut().find(|arg| *arg == from) {
+        *arg = to.to_string();
+    }
+}
+
 fn quote_for_display(input: &str) -> String {
     if input.is_empty() {
         return "\"\"".to_string();
@@ -224,6 +509,10 @@ impl PackageManager for AptGet {
         &["apt-get", "apt"]
     }
 
+    fn needs_root(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
         let identifier = validate_identifier(identifier, self.name())?;
         let version = validate_version(version, self.name())?;
@@ -232,7 +521,7 @@ impl PackageManager for AptGet {
             None => identifier.to_string(),
         };
 
-        Ok(with_optional_sudo(
+        Ok(InstallCommand::new(
             self.executable(),
             vec!["install".to_string(), package_spec],
         ))
@@ -241,6 +530,55 @@ impl PackageManager for AptGet {
     fn apply_yes_flag(&self, command: &mut InstallCommand) {
         insert_after_subcommand(command, "install", "-y");
     }
+
+    fn needs_index_update(&self) -> bool {
+        true
+    }
+
+    fn build_update_cmd(&self) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            self.executable(),
+            vec!["update".to_string()],
+        ))
+    }
+
+    /// `dpkg -s` also succeeds for packages removed with their config files
+    /// left behind, so the probe reads the status instead.
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "dpkg-query",
+            vec![
+                "-W".to_string(),
+                "-f=${Status}".to_string(),
+                identifier.trim().to_string(),
+            ],
+        ))
+    }
+
+    fn probe_reports_installed(&self, stdout: &str) -> bool {
+        stdout.trim() == "install ok installed"
+    }
+
+    fn installed_version_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "dpkg-query",
+            vec![
+                "-W".to_string(),
+                "-f=${Version}".to_string(),
+                identifier.trim().to_string(),
+            ],
+        ))
+    }
+
+    fn build_reinstall_cmd(
+        &self,
+        identifier: &str,
+        version: Option<&str>,
+    ) -> Result<InstallCommand> {
+        let mut command = self.build_install_cmd(identifier, version)?;
+        insert_after_subcommand(&mut command, "install", "--reinstall");
+        Ok(command)
+    }
 }
 
 struct Dnf;
@@ -258,6 +596,10 @@ impl PackageManager for Dnf {
         &["dnf"]
     }
 
+    fn needs_root(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
         let identifier = validate_identifier(identifier, self.name())?;
         let version = validate_version(version, self.name())?;
@@ -266,17 +608,63 @@ impl PackageManager for Dnf {
             None => identifier.to_string(),
         };
 
-        Ok(with_optional_sudo(
+        Ok(InstallCommand::new(
             self.executable(),
             vec!["install".to_string(), package_spec],
         ))
     }
 
     fn apply_yes_flag(&self, command: &mut InstallCommand) {
-        insert_after_subcommand(command, "install", "-y");
+        let subcommand = if command.args.iter().any(|arg| arg == "reinstall") {
+            "reinstall"
+        } else {
+            "install"
+        };
+        insert_after_subcommand(command, subcommand, "-y");
+    }
+
+    fn build_update_cmd(&self) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            self.executable(),
+            vec!["makecache".to_string()],
+        ))
+    }
+
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "rpm",
+            vec!["-q".to_string(), identifier.trim().to_string()],
+        ))
+    }
+
+    fn installed_version_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        rpm_version_query(identifier)
+    }
+
+    fn build_reinstall_cmd(
+        &self,
+        identifier: &str,
+        version: Option<&str>,
+    ) -> Result<InstallCommand> {
+        let mut command = self.build_install_cmd(identifier, version)?;
+        replace_subcommand(&mut command, "install", "reinstall");
+        Ok(command)
     }
 }
 
+/// `rpm -q` printing `version-release`, the form dnf and zypper accept as a pin.
+fn rpm_version_query(identifier: &str) -> Option<InstallCommand> {
+    Some(InstallCommand::new(
+        "rpm",
+        vec![
+            "-q".to_string(),
+            "--qf".to_string(),
+            "%{VERSION}-%{RELEASE}".to_string(),
+            identifier.trim().to_string(),
+        ],
+    ))
+}
+
 struct Pacman;
 
 impl PackageManager for Pacman {
@@ -292,25 +680,114 @@ impl PackageManager for Pacman {
         &["pacman"]
     }
 
+    fn needs_root(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
-        let identifier = validate_identifier(identifier, self.name())?;
-        if version.is_some() {
-            bail!(
-                "`pacman` does not support reliable direct version pinning in a single install command. Remove `:<version>` or install the required package version manually."
-            );
-        }
+        build_pacman_install(identifier, version, &pacman_cache_dir())
+    }
 
-        Ok(with_optional_sudo(
-            self.executable(),
-            vec!["-S".to_string(), identifier.to_string()],
+    fn apply_yes_flag(&self, command: &mut InstallCommand) {
+        let subcommand = if command.args.iter().any(|arg| arg == "-U") {
+            "-U"
+        } else {
+            "-S"
+        };
+        insert_after_subcommand(command, subcommand, "--noconfirm");
+    }
+
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "pacman",
+            vec!["-Q".to_string(), identifier.trim().to_string()],
         ))
     }
 
-    fn apply_yes_flag(&self, command: &mut InstallCommand) {
-        insert_after_subcommand(command, "-S", "--noconfirm");
+    /// `pacman -Q` prints `name pkgver-pkgrel`, which matches the cached
+    /// package names a pinned install looks for.
+    fn installed_version_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        self.installed_probe_cmd(identifier)
     }
 }
 
+const DEFAULT_PACMAN_CACHE_DIR: &str = "/var/cache/pacman/pkg";
+
+/// Package cache searched for pinned versions (`QBIT_PACMAN_CACHE_DIR` overrides).
+fn pacman_cache_dir() -> PathBuf {
+    env::var_os("QBIT_PACMAN_CACHE_DIR")
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_PACMAN_CACHE_DIR))
+}
+
+/// Pacman can't pin versions from the sync repos, but a cached
+/// `<id>-<version>-*.pkg.tar.{zst,xz}` can be installed with `pacman -U`.
+fn build_pacman_install(
+    identifier: &str,
+    version: Option<&str>,
+    cache_dir: &Path,
+) -> Result<InstallCommand> {
+    let identifier = validate_identifier(identifier, "pacman")?;
+    let Some(version) = validate_version(version, "pacman")? else {
+        return Ok(InstallCommand::new(
+            "pacman",
+            vec!["-S".to_string(), identifier.to_string()],
+        ));
+    };
+
+    let Some(package) = find_cached_pacman_package(cache_dir, identifier, version) else {
+        bail!(
+            "`pacman` cannot pin `{identifier}` to version `{version}`: no `{identifier}-{version}-*.pkg.tar.zst` in {} (set QBIT_PACMAN_CACHE_DIR to search elsewhere). Remove `:<version>` or install the required package version manually.",
+            cache_dir.display()
+        );
+    };
+    Ok(InstallCommand::new(
+        "pacman",
+        vec!["-U".to_string(), package.display().to_string()],
+    ))
+}
+
+fn find_cached_pacman_package(
+    cache_dir: &Path,
+    identifier: &str,
+    version: &str,
+) -> Option<PathBuf> {
+    let prefix = format!("{identifier}-{version}-");
+    let mut matches: Vec<PathBuf> = fs::read_dir(cache_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(&prefix)
+                        && (name.ends_with(".pkg.tar.zst") || name.ends_with(".pkg.tar.xz"))
+                })
+        })
+        .collect();
+    // Newest pkgrel wins, compared numerically like `vercmp` (10 after 9).
+    matches.sort_by_cached_key(|path| {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        (pkgrel_key(&name[prefix.len()..]), path.clone())
+    });
+    matches.pop()
+}
+
+/// Numeric segments of the pkgrel that starts `rest` (`<pkgrel>-<arch>...`),
+/// so `1.10` sorts after `1.9`.
+fn pkgrel_key(rest: &str) -> Vec<u64> {
+    rest.split('-')
+        .next()
+        .unwrap_or("")
+        .split('.')
+        .map(|segment| segment.parse().unwrap_or(0))
+        .collect()
+}
+
 struct Zypper;
 
 impl PackageManager for Zypper {
@@ -326,6 +803,10 @@ impl PackageManager for Zypper {
         &["zypper"]
     }
 
+    fn needs_root(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
         let identifier = validate_identifier(identifier, self.name())?;
         let version = validate_version(version, self.name())?;
@@ -334,7 +815,7 @@ impl PackageManager for Zypper {
             None => identifier.to_string(),
         };
 
-        Ok(with_optional_sudo(
+        Ok(InstallCommand::new(
             self.executable(),
             vec!["install".to_string(), package_spec],
         ))
@@ -343,6 +824,24 @@ impl PackageManager for Zypper {
     fn apply_yes_flag(&self, command: &mut InstallCommand) {
         insert_after_subcommand(command, "install", "-y");
     }
+
+    fn build_update_cmd(&self) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            self.executable(),
+            vec!["refresh".to_string()],
+        ))
+    }
+
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "rpm",
+            vec!["-q".to_string(), identifier.trim().to_string()],
+        ))
+    }
+
+    fn installed_version_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        rpm_version_query(identifier)
+    }
 }
 
 struct Brew;
@@ -360,6 +859,10 @@ impl PackageManager for Brew {
         &["brew", "homebrew"]
     }
 
+    fn supports_parallel_installs(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
         let identifier = validate_identifier(identifier, self.name())?;
         let version = validate_version(version, self.name())?;
@@ -370,6 +873,38 @@ impl PackageManager for Brew {
             vec!["install".to_string(), package_spec],
         ))
     }
+
+    /// Homebrew never prompts for confirmation, so `--yes` only trims the
+    /// progress output to keep batch logs readable.
+    fn apply_yes_flag(&self, command: &mut InstallCommand) {
+        let subcommand = if command.args.iter().any(|arg| arg == "reinstall") {
+            "reinstall"
+        } else {
+            "install"
+        };
+        insert_after_subcommand(command, subcommand, "--quiet");
+    }
+
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "brew",
+            vec![
+                "list".to_string(),
+                "--versions".to_string(),
+                identifier.trim().to_string(),
+            ],
+        ))
+    }
+
+    fn build_reinstall_cmd(
+        &self,
+        identifier: &str,
+        version: Option<&str>,
+    ) -> Result<InstallCommand> {
+        let mut command = self.build_install_cmd(identifier, version)?;
+        replace_subcommand(&mut command, "install", "reinstall");
+        Ok(command)
+    }
 }
 
 fn build_brew_identifier(identifier: &str, version: Option<&str>) -> Result<String> {
@@ -433,6 +968,23 @@ impl PackageManager for Winget {
     fn apply_yes_flag(&self, command: &mut InstallCommand) {
         insert_after_subcommand(command, "install", "--silent");
     }
+
+    /// `--exact` stays, so the name must match in full rather than as a substring.
+    fn apply_match_by_name(&self, command: &mut InstallCommand) {
+        replace_subcommand(command, "--id", "--name");
+    }
+
+    fn installed_probe_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            "winget",
+            vec![
+                "list".to_string(),
+                "--id".to_string(),
+                identifier.trim().to_string(),
+                "--exact".to_string(),
+            ],
+        ))
+    }
 }
 
 struct Chocolatey;
@@ -466,6 +1018,22 @@ impl PackageManager for Chocolatey {
     fn apply_yes_flag(&self, command: &mut InstallCommand) {
         insert_after_subcommand(command, "install", "-y");
     }
+
+    fn apply_accept_licenses(&self, command: &mut InstallCommand) {
+        insert_after_subcommand(command, "install", "--accept-license");
+    }
+
+    fn installed_version_cmd(&self, identifier: &str) -> Option<InstallCommand> {
+        Some(InstallCommand::new(
+            self.executable(),
+            vec![
+                "list".to_string(),
+                "--exact".to_string(),
+                identifier.trim().to_string(),
+                "--limit-output".to_string(),
+            ],
+        ))
+    }
 }
 
 struct Scoop;
@@ -483,25 +1051,365 @@ impl PackageManager for Scoop {
         &["scoop"]
     }
 
+    fn supports_parallel_installs(&self) -> bool {
+        true
+    }
+
     fn build_install_cmd(&self, identifier: &str, version: Option<&str>) -> Result<InstallCommand> {
         let identifier = validate_identifier(identifier, self.name())?;
-        if version.is_some() {
-            bail!(
-                "`scoop` version pinning is not reliable through a single install command. Remove `:<version>` and install the required bucket/package version manually, or switch to `winget`/`choco`."
-            );
-        }
+        let version = validate_version(version, self.name())?;
+        let package_spec = build_scoop_identifier(identifier, version)?;
 
         Ok(InstallCommand::new(
             self.executable().to_string(),
-            vec!["install".to_string(), identifier.to_string()],
+            vec!["install".to_string(), package_spec],
         ))
     }
 }
 
+/// Scoop installs `[bucket/]app@version` by generating a versioned manifest.
+/// Manifest URLs and file paths can't carry a version, so those still refuse.
+fn build_scoop_identifier(identifier: &str, version: Option<&str>) -> Result<String> {
+    let Some(version) = version else {
+        return Ok(identifier.to_string());
+    };
+
+    let is_manifest =
+        identifier.contains("://") || identifier.ends_with(".json") || identifier.contains('\\');
+    if is_manifest || identifier.matches('/').count() > 1 {
+        bail!(
+            "`scoop` can't pin a version for manifest `{identifier}`. Point `identifiers.scoop` at a `bucket/app` name, or remove `:<version>` and install the required version manually."
+        );
+    }
+
+    if let Some((_, existing)) = identifier.rsplit_once('@') {
+        if existing == version {
+            return Ok(identifier.to_string());
+        }
+        bail!(
+            "Scoop identifier `{identifier}` already includes version `{existing}`. Remove inline version `:{version}` or update your `identifiers.scoop` value."
+        );
+    }
+
+    Ok(format!("{identifier}@{version}"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn pacman_installs_pinned_version_from_cache() {
+        let cache = tempfile::tempdir().expect("cache dir");
+        for name in [
+            "python-3.12.1-1-x86_64.pkg.tar.zst",
+            "python-3.12.1-1-x86_64.pkg.tar.zst.sig",
+            "python-3.11.0-1-x86_64.pkg.tar.zst",
+        ] {
+            fs::write(cache.path().join(name), b"").expect("write cached package");
+        }
+
+        let mut command =
+            build_pacman_install("python", Some("3.12.1"), cache.path()).expect("command");
+        Pacman.apply_yes_flag(&mut command);
+        let expected = cache.path().join("python-3.12.1-1-x86_64.pkg.tar.zst");
+        let args = command.args.join(" ");
+        assert!(
+            args.ends_with(&format!("-U --noconfirm {}", expected.display())),
+            "args: {args}"
+        );
+    }
+
+    #[test]
+    fn pacman_prefers_the_highest_cached_pkgrel() {
+        let cache = tempfile::tempdir().expect("cache dir");
+        for name in [
+            "python-3.12.1-9-x86_64.pkg.tar.zst",
+            "python-3.12.1-10-x86_64.pkg.tar.zst",
+        ] {
+            fs::write(cache.path().join(name), b"").expect("write cached package");
+        }
+
+        let package =
+            find_cached_pacman_package(cache.path(), "python", "3.12.1").expect("cached package");
+        assert_eq!(
+            package,
+            cache.path().join("python-3.12.1-10-x86_64.pkg.tar.zst")
+        );
+    }
+
+    #[test]
+    fn pacman_refuses_version_missing_from_cache() {
+        let cache = tempfile::tempdir().expect("cache dir");
+        let err =
+            build_pacman_install("python", Some("3.12.1"), cache.path()).expect_err("must fail");
+        assert!(err.to_string().contains("cannot pin `python`"));
+    }
+
+    #[test]
+    fn scoop_pins_versions_with_app_at_version() {
+        let command = Scoop
+            .build_install_cmd("extras/vscode", Some("1.90.0"))
+            .expect("command");
+        assert_eq!(command.render(), "scoop install extras/vscode@1.90.0");
+    }
+
+    #[test]
+    fn scoop_refuses_versions_for_manifest_urls() {
+        let err = Scoop
+            .build_install_cmd("https://example.test/app.json", Some("1.0"))
+            .expect_err("must fail");
+        assert!(err.to_string().contains("can't pin a version"));
+    }
+
+    #[test]
+    fn configured_manager_is_used_when_available() {
+        let pm = manager_from_setting("dnf", "install.manager", |pm| pm.name() == "dnf")
+            .expect("dnf selected");
+        assert_eq!(pm.name(), "dnf");
+    }
+
+    #[test]
+    fn configured_manager_must_be_known_and_available() {
+        let err = manager_from_setting("snap", "install.manager", |_| true)
+            .err()
+            .expect("unknown manager");
+        assert!(
+            err.to_string()
+                .contains("Unknown package manager `snap` in install.manager")
+        );
+
+        let err = manager_from_setting("dnf", "install.manager", |_| false)
+            .err()
+            .expect("unavailable manager");
+        assert!(
+            matches!(
+                err.downcast_ref::<QbitError>(),
+                Some(QbitError::ManagerUnavailable { manager, .. }) if manager == "dnf"
+            ),
+            "{err:#}"
+        );
+    }
+
+    fn choose_among_apt_and_dnf(
+        settings: ManagerSettings,
+        env_override: Option<&str>,
+        is_available: impl Fn(&dyn PackageManager) -> bool,
+    ) -> Result<ManagerChoice> {
+        choose_package_manager(
+            settings,
+            env_override,
+            vec![Box::new(AptGet), Box::new(Dnf)],
+            is_available,
+        )
+    }
+
+    #[test]
+    fn manager_flag_wins_over_env_config_and_detection() {
+        let choose = |requested, env_override, configured| {
+            let settings = ManagerSettings {
+                requested,
+                configured,
+                cached: None,
+            };
+            choose_among_apt_and_dnf(settings, env_override, |_| true).expect("manager chosen")
+        };
+
+        let choice = choose(Some("dnf"), Some("apt-get"), Some("apt"));
+        assert_eq!(choice.manager.name(), "dnf");
+        assert_eq!(choice.reason, "--manager is `dnf`");
+        assert!(!choice.detected);
+        assert_eq!(choose(None, Some("dnf"), Some("apt")).manager.name(), "dnf");
+        assert_eq!(choose(None, None, Some("dnf")).manager.name(), "dnf");
+        assert_eq!(choose(None, None, None).manager.name(), "apt-get");
+
+        let settings = ManagerSettings {
+            requested: Some("dnf"),
+            ..ManagerSettings::default()
+        };
+        let err = choose_among_apt_and_dnf(settings, None, |pm| pm.name() == "apt-get")
+            .err()
+            .expect("dnf unavailable");
+        assert!(
+            err.to_string()
+                .contains("--manager is `dnf`, but executable `dnf` is not available"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn cached_manager_skips_detection_until_it_disappears() {
+        let cached = ManagerSettings {
+            cached: Some("dnf"),
+            ..ManagerSettings::default()
+        };
+        DETECTIONS.with(|runs| runs.set(0));
+
+        let choice = choose_among_apt_and_dnf(cached, None, |_| true).expect("cached manager");
+        assert_eq!(choice.manager.name(), "dnf");
+        assert!(choice.detected);
+        assert!(
+            choice.reason.contains("cached in qbit.lock"),
+            "{}",
+            choice.reason
+        );
+        assert_eq!(DETECTIONS.with(|runs| runs.get()), 0);
+
+        let choice = choose_among_apt_and_dnf(cached, None, |pm| pm.name() == "apt-get")
+            .expect("re-detected manager");
+        assert_eq!(choice.manager.name(), "apt-get");
+        assert_eq!(DETECTIONS.with(|runs| runs.get()), 1);
+
+        let choice =
+            choose_among_apt_and_dnf(cached, Some("apt-get"), |_| true).expect("explicit setting");
+        assert_eq!(choice.manager.name(), "apt-get");
+        assert!(!choice.detected);
+        assert_eq!(DETECTIONS.with(|runs| runs.get()), 1);
+    }
+
+    #[test]
+    fn sudo_blocks_only_without_tty_or_passwordless_sudo() {
+        assert!(sudo_would_block(false, || false));
+        assert!(!sudo_would_block(false, || true));
+        assert!(!sudo_would_block(true, || false));
+        assert!(!sudo_would_block(true, || panic!(
+            "tty sessions skip the probe"
+        )));
+    }
+
+    #[test]
+    fn non_sudo_commands_never_block() {
+        let command = InstallCommand::new("brew", vec!["install".to_string()]);
+        assert!(ensure_sudo_wont_block(&command).is_ok());
+    }
+
+    #[test]
+    fn apt_refreshes_index_and_brew_does_not() {
+        assert!(AptGet.needs_index_update());
+        let update = AptGet.build_update_cmd().expect("apt update command");
+        assert_eq!(prefix_sudo(update).render(), "sudo apt-get update");
+
+        assert!(!Brew.needs_index_update());
+        assert!(Brew.build_update_cmd().is_none());
+    }
+
+    #[test]
+    fn sudo_is_used_by_default_when_available_and_skipped_under_no_sudo() {
+        if !cfg!(windows) {
+            assert!(should_use_sudo(false, || true));
+        }
+        assert!(!should_use_sudo(false, || false));
+        assert!(!should_use_sudo(true, || panic!(
+            "no-sudo skips the lookup"
+        )));
+
+        let command = AptGet.build_install_cmd("git", None).expect("command");
+        assert_eq!(
+            with_optional_sudo(&AptGet, command.clone(), true).render(),
+            "apt-get install git"
+        );
+        assert_eq!(prefix_sudo(command).render(), "sudo apt-get install git");
+        let brew = Brew.build_install_cmd("git", None).expect("command");
+        assert_eq!(
+            with_optional_sudo(&Brew, brew, false).render(),
+            "brew install git"
+        );
+    }
+
+    #[test]
+    fn apt_probe_uses_dpkg_status() {
+        let probe = AptGet.installed_probe_cmd("python3").expect("probe");
+        assert_eq!(probe.args, ["-W", "-f=${Status}", "python3"]);
+        assert!(AptGet.probe_reports_installed("install ok installed"));
+        assert!(!AptGet.probe_reports_installed("deinstall ok config-files"));
+    }
+
+    #[test]
+    fn brew_probe_lists_versions() {
+        let probe = Brew.installed_probe_cmd("python@3.12").expect("probe");
+        assert_eq!(probe.render(), "brew list --versions python@3.12");
+    }
+
+    #[test]
+    fn apt_reinstall_adds_reinstall_flag() {
+        let mut command = AptGet.build_reinstall_cmd("git", None).expect("command");
+        AptGet.apply_yes_flag(&mut command);
+        let rendered = command.render();
+        assert!(
+            rendered.ends_with("apt-get install -y --reinstall git"),
+            "rendered: {rendered}"
+        );
+    }
+
+    #[test]
+    fn brew_reinstall_uses_reinstall_subcommand() {
+        let command = Brew
+            .build_reinstall_cmd("python", Some("3.12"))
+            .expect("command");
+        assert_eq!(command.render(), "brew reinstall python@3.12");
+    }
+
+    #[test]
+    fn brew_yes_flag_adds_quiet_once() {
+        let mut command = Brew.build_install_cmd("git", None).expect("command");
+        Brew.apply_yes_flag(&mut command);
+        Brew.apply_yes_flag(&mut command);
+        assert_eq!(command.render(), "brew install --quiet git");
+
+        let mut command = Brew.build_reinstall_cmd("git", None).expect("command");
+        Brew.apply_yes_flag(&mut command);
+        assert_eq!(command.render(), "brew reinstall --quiet git");
+    }
+
+    #[test]
+    fn dnf_reinstall_keeps_yes_flag_after_subcommand() {
+        let mut command = Dnf.build_reinstall_cmd("git", None).expect("command");
+        Dnf.apply_yes_flag(&mut command);
+        assert!(command.render().ends_with("dnf reinstall -y git"));
+    }
+
+    #[test]
+    fn reinstall_defaults_to_install_command() {
+        let command = Scoop.build_reinstall_cmd("git", None).expect("command");
+        assert_eq!(command.render(), "scoop install git");
+    }
+
+    #[test]
+    fn managers_without_probe_report_unknown() {
+        assert!(Scoop.installed_probe_cmd("git").is_none());
+        assert_eq!(Scoop.is_installed("git"), None);
+    }
+
+    #[test]
+    fn installed_version_is_the_last_field_of_the_first_line() {
+        assert_eq!(
+            parse_installed_version("1:2.39.2-1\n").as_deref(),
+            Some("1:2.39.2-1")
+        );
+        assert_eq!(
+            parse_installed_version("git 2.43.0-1\n").as_deref(),
+            Some("2.43.0-1")
+        );
+        assert_eq!(
+            parse_installed_version("git|2.43.0\n").as_deref(),
+            Some("2.43.0")
+        );
+        assert_eq!(parse_installed_version(""), None);
+        assert!(Scoop.installed_version_cmd("git").is_none());
+    }
+
+    #[test]
+    fn identifier_with_space_stays_a_single_argument() {
+        let command = Chocolatey
+            .build_install_cmd("my package; rm -rf /", None)
+            .expect("command");
+        assert_eq!(
+            command.args,
+            vec!["install".to_string(), "my package; rm -rf /".to_string()]
+        );
+        assert_eq!(command.render(), "choco install \"my package; rm -rf /\"");
+    }
+
     #[test]
     fn brew_builds_versioned_formula_when_not_already_versioned() {
         let id = build_brew_identifier("python", Some("3.12")).expect("brew id");
@@ -513,4 +1421,34 @@ mod tests {
         let err = build_brew_identifier("python@3.11", Some("3.12")).expect_err("must fail");
         assert!(err.to_string().contains("already includes version"));
     }
+
+    #[test]
+    fn choco_accept_licenses_inserts_flag_once_after_install() {
+        let mut command = Chocolatey
+            .build_install_cmd("git", Some("2.45.0"))
+            .expect("command");
+        Chocolatey.apply_accept_licenses(&mut command);
+        Chocolatey.apply_accept_licenses(&mut command);
+        assert_eq!(
+            command.render(),
+            "choco install --accept-license git --version 2.45.0"
+        );
+
+        Chocolatey.apply_yes_flag(&mut command);
+        assert!(command.args.contains(&"--accept-license".to_string()));
+        assert!(command.args.contains(&"-y".to_string()));
+    }
+
+    #[test]
+    fn accept_licenses_leaves_winget_and_scoop_unchanged() {
+        let mut winget = Winget.build_install_cmd("Git.Git", None).expect("command");
+        let before = winget.render();
+        Winget.apply_accept_licenses(&mut winget);
+        assert_eq!(winget.render(), before);
+        assert!(before.contains("--accept-package-agreements"));
+
+        let mut scoop = Scoop.build_install_cmd("git", None).expect("command");
+        Scoop.apply_accept_licenses(&mut scoop);
+        assert_eq!(scoop.render(), "scoop install git");
+    }
 }
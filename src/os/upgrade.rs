@@ -1,26 +1,64 @@
 use std::fs::{self, File};
 use std::io::{self, Write};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::{Command, Stdio};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result, bail};
 use flate2::read::GzDecoder;
-use reqwest::blocking::Client;
 use semver::Version;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tar::Archive;
 use zip::ZipArchive;
 
+use crate::error::QbitError;
+use crate::utils::net::{ensure_online, http_client, is_truthy};
+use crate::utils::run_log::utc_timestamp;
+
 const DEFAULT_REPOSITORY: &str = "qbit-click/qbit-cli";
+const DEFAULT_CACHE_TTL_SECS: u64 = 3600;
+/// Release notes longer than this are cut off with a link to the full text.
+const MAX_NOTES_LINES: usize = 30;
+const MAX_NOTES_CHARS: usize = 2000;
+
+/// Flags from `qbit upgrade`.
+#[derive(Debug, Clone, Default)]
+pub struct UpgradeOptions {
+    /// Refuse network access.
+    pub offline: bool,
+    /// Only report whether an update is available; don't install.
+    pub check: bool,
+    /// Ignore the cached latest-release lookup.
+    pub refresh: bool,
+    /// Consider pre-release tags when picking the newest version.
+    pub prerelease: bool,
+    /// `owner/name` to upgrade from, overriding `QBIT_UPGRADE_REPO`.
+    pub repo: Option<String>,
+    /// Release archive (path or http(s) URL) to install instead of the
+    /// latest GitHub release.
+    pub from: Option<String>,
+}
+
+/// True when `QBIT_UPGRADE_PRERELEASE` opts into the pre-release channel.
+pub fn prerelease_from_env() -> bool {
+    std::env::var("QBIT_UPGRADE_PRERELEASE")
+        .map(|value| is_truthy(&value))
+        .unwrap_or(false)
+}
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 struct GithubRelease {
     tag_name: String,
     assets: Vec<GithubAsset>,
+    /// Release notes (Markdown).
+    #[serde(default)]
+    body: Option<String>,
+    /// The release's page on GitHub.
+    #[serde(default)]
+    html_url: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct GithubAsset {
     name: String,
     browser_download_url: String,
@@ -54,47 +92,266 @@ impl Drop for TempDirGuard {
     }
 }
 
-pub fn upgrade() -> Result<()> {
-    let repository = upgrade_repository();
+/// Copy of the running executable taken before the platform installer runs,
+/// so a failed install can be rolled back.
+struct ExecutableBackup {
+    original: PathBuf,
+    backup: PathBuf,
+}
+
+impl ExecutableBackup {
+    fn create(original: &Path) -> Result<Self> {
+        let mut name = original
+            .file_name()
+            .context("current executable has no file name")?
+            .to_os_string();
+        name.push(".bak");
+        let backup = original.with_file_name(name);
+        fs::copy(original, &backup).with_context(|| {
+            format!("backing up {} to {}", original.display(), backup.display())
+        })?;
+        Ok(Self {
+            original: original.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Put the backup back in place. The (possibly broken) current file is moved
+    /// aside first because a running executable can't be overwritten on Windows.
+    fn restore(self) -> Result<()> {
+        let mut failed_name = self.original.as_os_str().to_os_string();
+        failed_name.push(".failed");
+        let failed = PathBuf::from(failed_name);
+        if self.original.exists() {
+            fs::rename(&self.original, &failed).with_context(|| {
+                format!("moving aside failed binary {}", self.original.display())
+            })?;
+        }
+        fs::rename(&self.backup, &self.original).with_context(|| {
+            format!(
+                "restoring {} from {}",
+                self.original.display(),
+                self.backup.display()
+            )
+        })?;
+        let _ = fs::remove_file(&failed);
+        Ok(())
+    }
+
+    fn discard(self) -> Result<()> {
+        fs::remove_file(&self.backup)
+            .with_context(|| format!("removing backup {}", self.backup.display()))
+    }
+}
+
+fn run_installer_with_rollback(extracted_dir: &Path) -> Result<()> {
+    let backup = match std::env::current_exe() {
+        Ok(exe) => match ExecutableBackup::create(&exe) {
+            Ok(backup) => Some(backup),
+            Err(e) => {
+                eprintln!("warning: continuing without rollback backup: {e:#}");
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!("warning: cannot locate current executable for backup: {e}");
+            None
+        }
+    };
+
+    match run_platform_installer(extracted_dir) {
+        Ok(()) => {
+            if let Some(backup) = backup {
+                if let Err(e) = backup.discard() {
+                    eprintln!("warning: {e:#}");
+                }
+            }
+            Ok(())
+        }
+        Err(install_err) => {
+            let Some(backup) = backup else {
+                return Err(install_err);
+            };
+            backup.restore().with_context(|| {
+                format!("installer failed ({install_err:#}) and rollback also failed")
+            })?;
+            bail!("{install_err:#}. The upgrade was rolled back to the previous qbit binary.");
+        }
+    }
+}
+
+pub fn upgrade(options: &UpgradeOptions) -> Result<()> {
+    let offline = options.offline;
+    if let Some(source) = &options.from {
+        return upgrade_from_archive(source, offline);
+    }
+    let repository = upgrade_repository(options.repo.as_deref())?;
     let current = parse_version(env!("CARGO_PKG_VERSION"))
         .context("parsing current qbit version from build metadata")?;
 
     println!("Checking for updates from GitHub repo: {repository}");
-    let release = fetch_latest_release(&repository)?;
+    // Only the read-only check may be served from cache; a real upgrade
+    // always confirms against GitHub.
+    let use_cache = options.check && !options.refresh;
+    if options.check && options.prerelease {
+        let channels = fetch_release_channels(&repository, offline, use_cache)?;
+        for line in channel_report(&current, &channels)? {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+    let release = fetch_latest_release(&repository, offline, options.prerelease, use_cache)?;
     let latest = parse_version(&release.tag_name)
         .with_context(|| format!("parsing latest tag `{}`", release.tag_name))?;
 
     println!("Current version: {current}");
     println!("Latest version:  {latest}");
 
-    if latest <= current {
+    if !is_upgrade_available(&current, &latest, options.prerelease) {
+        if !latest.pre.is_empty() && !options.prerelease {
+            println!("Ignoring pre-release {latest} (pass --pre to opt in).");
+        }
         println!("qbit is already up to date.");
         return Ok(());
     }
 
-    let expected_asset_name = platform_asset_name();
-    let asset = find_release_asset(&release, expected_asset_name)?;
+    for line in release_notes(&release, &latest) {
+        println!("{line}");
+    }
+
+    if options.check {
+        println!("A newer qbit is available. Run `qbit upgrade` to install it.");
+        return Ok(());
+    }
+
+    let asset = find_release_asset(&release, &platform_asset())?;
     println!("Downloading asset: {}", asset.name);
 
     let temp = TempDirGuard::new()?;
     let archive_path = temp.path().join(&asset.name);
-    download_to_file(&asset.browser_download_url, &archive_path)?;
+    download_to_file(&asset.browser_download_url, &archive_path, offline)?;
     extract_archive(&archive_path, temp.path())?;
-    run_platform_installer(temp.path())?;
+    run_installer_with_rollback(temp.path())?;
 
     println!("Upgrade installed successfully to version {latest}.");
     Ok(())
 }
 
-fn upgrade_repository() -> String {
-    std::env::var("QBIT_UPGRADE_REPO")
-        .ok()
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty())
-        .unwrap_or_else(|| DEFAULT_REPOSITORY.to_string())
+/// `qbit upgrade --from`: install a release archive that hasn't been
+/// published, skipping the GitHub lookup and version comparison.
+fn upgrade_from_archive(source: &str, offline: bool) -> Result<()> {
+    println!("Installing qbit from {source} instead of the latest GitHub release.");
+    let temp = TempDirGuard::new()?;
+    let installer = stage_archive(source, temp.path(), offline)?;
+    println!("Running installer: {}", installer.display());
+    run_installer_with_rollback(temp.path())?;
+    println!("Upgrade installed successfully from {source}.");
+    Ok(())
+}
+
+/// Extract the archive at `source` (a path, or an http(s) URL that is
+/// downloaded first) into `dir` and return the installer script it contains.
+fn stage_archive(source: &str, dir: &Path, offline: bool) -> Result<PathBuf> {
+    let is_url = source.starts_with("http://") || source.starts_with("https://");
+    let file_name = if is_url {
+        let path = source.split(['?', '#']).next().unwrap_or(source);
+        path.rsplit('/').next().unwrap_or_default()
+    } else {
+        Path::new(source)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+    };
+    if !(file_name.ends_with(".tar.gz") || file_name.ends_with(".zip")) {
+        bail!(
+            "`{source}` is not a release archive: --from takes a .tar.gz or .zip file, like `{}`.",
+            platform_asset().name
+        );
+    }
+
+    let archive = if is_url {
+        let archive = dir.join(file_name);
+        println!("Downloading {source}");
+        download_to_file(source, &archive, offline)?;
+        archive
+    } else {
+        let archive = PathBuf::from(source);
+        if !archive.is_file() {
+            bail!("Release archive {} does not exist.", archive.display());
+        }
+        archive
+    };
+    extract_archive(&archive, dir)?;
+    find_installer_script(dir)
+}
+
+/// The release's notes, indented under a heading. Long notes stop after
+/// `MAX_NOTES_LINES` lines or `MAX_NOTES_CHARS` characters and point at the
+/// release page. Empty when the release has no notes.
+fn release_notes(release: &GithubRelease, version: &Version) -> Vec<String> {
+    let Some(body) = release
+        .body
+        .as_deref()
+        .map(str::trim)
+        .filter(|body| !body.is_empty())
+    else {
+        return Vec::new();
+    };
+    let mut lines = vec![format!("Release notes for {version}:")];
+    let mut chars = 0;
+    let mut truncated = false;
+    for (index, line) in body.lines().enumerate() {
+        chars += line.chars().count();
+        if index == MAX_NOTES_LINES || chars > MAX_NOTES_CHARS {
+            truncated = true;
+            break;
+        }
+        lines.push(format!("  {line}").trim_end().to_string());
+    }
+    if truncated {
+        lines.push(match &release.html_url {
+            Some(url) => format!("  ... see full notes at {url}"),
+            None => "  ... (notes truncated)".to_string(),
+        });
+    }
+    lines
+}
+
+fn upgrade_repository(flag: Option<&str>) -> Result<String> {
+    resolve_repository(flag, std::env::var("QBIT_UPGRADE_REPO").ok().as_deref())
+}
+
+/// `--repo` wins over `QBIT_UPGRADE_REPO`, which wins over the default.
+fn resolve_repository(flag: Option<&str>, env: Option<&str>) -> Result<String> {
+    let flag = flag.map(str::trim).filter(|v| !v.is_empty());
+    let env = env.map(str::trim).filter(|v| !v.is_empty());
+    let (repository, source) = match (flag, env) {
+        (Some(repo), _) => (repo, "--repo"),
+        (None, Some(repo)) => (repo, "QBIT_UPGRADE_REPO"),
+        (None, None) => return Ok(DEFAULT_REPOSITORY.to_string()),
+    };
+    if !is_repository_slug(repository) {
+        bail!(
+            "Invalid {source} value `{repository}`: expected `owner/name`, like `{DEFAULT_REPOSITORY}`."
+        );
+    }
+    Ok(repository.to_string())
+}
+
+fn is_repository_slug(value: &str) -> bool {
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    value
+        .split_once('/')
+        .is_some_and(|(owner, name)| valid_part(owner) && valid_part(name))
 }
 
-fn parse_version(input: &str) -> Result<Version> {
+/// Parse a semantic version, accepting an optional leading `v`.
+pub fn parse_version(input: &str) -> Result<Version> {
     let trimmed = input.trim();
     let normalized = if let Some(rest) = trimmed.strip_prefix('v') {
         rest
@@ -104,84 +361,443 @@ fn parse_version(input: &str) -> Result<Version> {
     Version::parse(normalized).with_context(|| format!("invalid semantic version: `{trimmed}`"))
 }
 
-fn github_api_url(repository: &str) -> String {
-    format!("https://api.github.com/repos/{repository}/releases/latest")
+fn github_api_url(repository: &str, prerelease: bool) -> String {
+    if prerelease {
+        // `releases/latest` never returns pre-releases, so list them all.
+        format!("https://api.github.com/repos/{repository}/releases")
+    } else {
+        format!("https://api.github.com/repos/{repository}/releases/latest")
+    }
+}
+
+/// Whether `latest` should replace `current`. Pre-release candidates are
+/// ignored unless opted in, while a pre-release build always sees its matching
+/// stable release as newer (`1.2.0-rc.1 < 1.2.0` in semver ordering).
+fn is_upgrade_available(current: &Version, latest: &Version, allow_prerelease: bool) -> bool {
+    if !latest.pre.is_empty() && !allow_prerelease {
+        return false;
+    }
+    latest > current
+}
+
+/// Pick the release with the highest semantic version. Tags that don't parse
+/// are skipped, and pre-release versions only count when `allow_prerelease`.
+fn select_release(releases: Vec<GithubRelease>, allow_prerelease: bool) -> Option<GithubRelease> {
+    releases
+        .into_iter()
+        .filter_map(|release| {
+            let version = parse_version(&release.tag_name).ok()?;
+            (allow_prerelease || version.pre.is_empty()).then_some((version, release))
+        })
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, release)| release)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedRelease {
+    fetched_at: u64,
+    release: GithubRelease,
+}
+
+fn cache_ttl_secs() -> u64 {
+    match std::env::var("QBIT_UPGRADE_CACHE_TTL") {
+        Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+            eprintln!(
+                "warning: ignoring QBIT_UPGRADE_CACHE_TTL=`{raw}` (expected seconds); using {DEFAULT_CACHE_TTL_SECS}"
+            );
+            DEFAULT_CACHE_TTL_SECS
+        }),
+        Err(_) => DEFAULT_CACHE_TTL_SECS,
+    }
+}
+
+/// Platform cache directory for qbit (`$XDG_CACHE_HOME/qbit`, `~/Library/Caches/qbit`,
+/// or `%LOCALAPPDATA%\qbit\cache`).
+fn cache_dir() -> Option<PathBuf> {
+    let non_empty = |key: &str| {
+        std::env::var_os(key)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    };
+
+    #[cfg(target_os = "windows")]
+    let base = non_empty("LOCALAPPDATA").map(|dir| dir.join("qbit").join("cache"));
+
+    #[cfg(target_os = "macos")]
+    let base = non_empty("HOME").map(|home| home.join("Library").join("Caches").join("qbit"));
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let base = non_empty("XDG_CACHE_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".cache")))
+        .map(|dir| dir.join("qbit"));
+
+    base
+}
+
+fn release_cache_path(repository: &str, prerelease: bool) -> Option<PathBuf> {
+    let key: String = repository
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let channel = if prerelease { "prerelease" } else { "release" };
+    cache_dir().map(|dir| dir.join(format!("latest-{channel}-{key}.json")))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// A cache entry is fresh when it was written no more than `ttl` seconds ago
+/// (and not in the future, which would indicate clock skew).
+fn is_cache_fresh(fetched_at: u64, now: u64, ttl: u64) -> bool {
+    now >= fetched_at && now - fetched_at < ttl
 }
 
-fn fetch_latest_release(repository: &str) -> Result<GithubRelease> {
-    let client = Client::builder()
-        .build()
-        .context("building HTTP client for upgrade")?;
+fn read_cached_release(repository: &str, prerelease: bool) -> Option<GithubRelease> {
+    let path = release_cache_path(repository, prerelease)?;
+    let content = fs::read_to_string(path).ok()?;
+    let cached: CachedRelease = serde_json::from_str(&content).ok()?;
+    is_cache_fresh(cached.fetched_at, unix_now(), cache_ttl_secs()).then_some(cached.release)
+}
 
-    let response = client
-        .get(github_api_url(repository))
+fn write_cached_release(repository: &str, prerelease: bool, release: &GithubRelease) {
+    let Some(path) = release_cache_path(repository, prerelease) else {
+        return;
+    };
+    let cached = CachedRelease {
+        fetched_at: unix_now(),
+        release: release.clone(),
+    };
+    let write = || -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_vec(&cached)?)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        eprintln!(
+            "warning: could not cache release lookup at {}: {e}",
+            path.display()
+        );
+    }
+}
+
+fn fetch_latest_release(
+    repository: &str,
+    offline: bool,
+    prerelease: bool,
+    use_cache: bool,
+) -> Result<GithubRelease> {
+    if use_cache {
+        if let Some(release) = read_cached_release(repository, prerelease) {
+            println!("Using cached release information (use --refresh to bypass).");
+            return Ok(release);
+        }
+    }
+
+    let release = fetch_latest_release_uncached(repository, offline, prerelease)?;
+    write_cached_release(repository, prerelease, &release);
+    Ok(release)
+}
+
+fn fetch_latest_release_uncached(
+    repository: &str,
+    offline: bool,
+    prerelease: bool,
+) -> Result<GithubRelease> {
+    if !prerelease {
+        return github_api_request(repository, offline, false)?
+            .json::<GithubRelease>()
+            .context("decoding GitHub release response JSON");
+    }
+
+    select_release(fetch_release_list(repository, offline)?, true)
+        .with_context(|| format!("No release with a semantic version tag found for {repository}"))
+}
+
+/// Every release of `repository`, pre-releases included.
+fn fetch_release_list(repository: &str, offline: bool) -> Result<Vec<GithubRelease>> {
+    github_api_request(repository, offline, true)?
+        .json::<Vec<GithubRelease>>()
+        .context("decoding GitHub releases list JSON")
+}
+
+fn github_api_request(
+    repository: &str,
+    offline: bool,
+    prerelease: bool,
+) -> Result<reqwest::blocking::Response> {
+    ensure_online(offline)?;
+    let client = http_client(offline).context("preparing HTTP client for upgrade")?;
+
+    let url = github_api_url(repository, prerelease);
+    let mut request = client
+        .get(&url)
         .header(reqwest::header::USER_AGENT, "qbit-cli-upgrader")
-        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+    if let Some(token) = github_token() {
+        request = request.bearer_auth(token);
+    }
+    let response = request
         .send()
-        .with_context(|| format!("requesting latest release for {repository}"))?
+        .map_err(|source| network_error(&url, source))
+        .with_context(|| format!("requesting latest release for {repository}"))?;
+    if let Some(message) = rate_limit_message(response.status(), response.headers(), unix_now()) {
+        bail!(message);
+    }
+    response
         .error_for_status()
-        .with_context(|| format!("GitHub API returned an error for repo {repository}"))?;
+        .map_err(|source| network_error(&url, source))
+        .with_context(|| format!("GitHub API returned an error for repo {repository}"))
+}
 
-    response
-        .json::<GithubRelease>()
-        .context("decoding GitHub release response JSON")
+fn network_error(url: &str, source: reqwest::Error) -> QbitError {
+    QbitError::Network {
+        url: url.to_string(),
+        source,
+    }
 }
 
-fn platform_asset_name() -> &'static str {
-    #[cfg(target_os = "windows")]
-    {
-        "qbit-windows-setup.zip"
+/// Newest release on each channel, for `qbit upgrade --check --pre`. The
+/// pre-release channel is what `qbit upgrade --pre` would install, so it
+/// equals `stable` when no newer pre-release exists.
+#[derive(Debug)]
+struct ReleaseChannels {
+    stable: Option<GithubRelease>,
+    prerelease: Option<GithubRelease>,
+}
+
+impl ReleaseChannels {
+    fn from_releases(releases: Vec<GithubRelease>) -> Self {
+        Self {
+            stable: select_release(releases.clone(), false),
+            prerelease: select_release(releases, true),
+        }
     }
-    #[cfg(target_os = "macos")]
-    {
-        "qbit-macos-setup.tar.gz"
+}
+
+/// Both channels from one releases-list request, or from the cache when both
+/// cached lookups are still fresh.
+fn fetch_release_channels(
+    repository: &str,
+    offline: bool,
+    use_cache: bool,
+) -> Result<ReleaseChannels> {
+    if use_cache {
+        if let (Some(stable), Some(prerelease)) = (
+            read_cached_release(repository, false),
+            read_cached_release(repository, true),
+        ) {
+            println!("Using cached release information (use --refresh to bypass).");
+            return Ok(ReleaseChannels {
+                stable: Some(stable),
+                prerelease: Some(prerelease),
+            });
+        }
     }
-    #[cfg(target_os = "linux")]
-    {
-        "qbit-linux-setup.tar.gz"
+
+    let channels = ReleaseChannels::from_releases(fetch_release_list(repository, offline)?);
+    if channels.prerelease.is_none() {
+        bail!("No release with a semantic version tag found for {repository}");
     }
-    #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+    for (prerelease, release) in [(false, &channels.stable), (true, &channels.prerelease)] {
+        if let Some(release) = release {
+            write_cached_release(repository, prerelease, release);
+        }
+    }
+    Ok(channels)
+}
+
+/// The `--check --pre` report: one line per channel, then what to run.
+fn channel_report(current: &Version, channels: &ReleaseChannels) -> Result<Vec<String>> {
+    let version = |release: &Option<GithubRelease>| -> Result<Option<Version>> {
+        release
+            .as_ref()
+            .map(|release| {
+                parse_version(&release.tag_name)
+                    .with_context(|| format!("parsing latest tag `{}`", release.tag_name))
+            })
+            .transpose()
+    };
+    let stable = version(&channels.stable)?;
+    let prerelease = version(&channels.prerelease)?.filter(|pre| !pre.pre.is_empty());
+
+    let mut lines = vec![format!("Current version:     {current}")];
+    lines.push(match &stable {
+        Some(stable) => format!("Latest stable:       {stable}"),
+        None => "Latest stable:       none published".to_string(),
+    });
+    lines.push(match (&prerelease, &stable) {
+        (Some(pre), _) => format!("Latest pre-release:  {pre}"),
+        (None, Some(stable)) => format!("Latest pre-release:  none newer than {stable}"),
+        (None, None) => "Latest pre-release:  none published".to_string(),
+    });
+
+    lines.push(
+        if stable
+            .as_ref()
+            .is_some_and(|stable| is_upgrade_available(current, stable, false))
+        {
+            "A newer stable qbit is available. Run `qbit upgrade` to install it."
+        } else if prerelease
+            .as_ref()
+            .is_some_and(|pre| is_upgrade_available(current, pre, true))
+        {
+            "A newer pre-release is available. Run `qbit upgrade --pre` to install it."
+        } else {
+            "qbit is already up to date."
+        }
+        .to_string(),
+    );
+    Ok(lines)
+}
+
+/// `QBIT_GITHUB_TOKEN`, sent with API requests to lift the anonymous rate
+/// limit and reach private repositories.
+fn github_token() -> Option<String> {
+    std::env::var("QBIT_GITHUB_TOKEN")
+        .ok()
+        .map(|token| token.trim().to_string())
+        .filter(|token| !token.is_empty())
+}
+
+/// A targeted error for a GitHub rate-limit response (403 or 429 with
+/// `X-RateLimit-Remaining: 0`), naming when the limit resets. `None` for any
+/// other response, which gets the generic status error.
+fn rate_limit_message(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    now: u64,
+) -> Option<String> {
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS
     {
-        "qbit-linux-setup.tar.gz"
+        return None;
+    }
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    if header("x-ratelimit-remaining") != Some("0") {
+        return None;
+    }
+    let retry = match header("x-ratelimit-reset").and_then(|reset| reset.parse::<u64>().ok()) {
+        Some(reset) => {
+            let at = utc_timestamp(UNIX_EPOCH + Duration::from_secs(reset));
+            let minutes = reset.saturating_sub(now).div_ceil(60);
+            match minutes {
+                0 => format!("retry after {at}"),
+                1 => format!("retry after {at} (in 1 minute)"),
+                _ => format!("retry after {at} (in {minutes} minutes)"),
+            }
+        }
+        None => "retry later".to_string(),
+    };
+    Some(format!(
+        "GitHub API rate limit exceeded; set QBIT_GITHUB_TOKEN or {retry}."
+    ))
+}
+
+/// The release asset for this platform: the exact name qbit publishes, plus
+/// the platform keywords and archive extension used to recognize a release
+/// whose asset name drifted (different case, a version suffix).
+#[derive(Debug, Clone, Copy)]
+struct AssetSpec {
+    name: &'static str,
+    platforms: &'static [&'static str],
+    extension: &'static str,
+}
+
+impl AssetSpec {
+    const LINUX: AssetSpec = AssetSpec {
+        name: "qbit-linux-setup.tar.gz",
+        platforms: &["linux"],
+        extension: ".tar.gz",
+    };
+    const MACOS: AssetSpec = AssetSpec {
+        name: "qbit-macos-setup.tar.gz",
+        platforms: &["macos", "darwin", "osx"],
+        extension: ".tar.gz",
+    };
+    const WINDOWS: AssetSpec = AssetSpec {
+        name: "qbit-windows-setup.zip",
+        platforms: &["windows", "win64"],
+        extension: ".zip",
+    };
+
+    fn matches_loosely(&self, asset_name: &str) -> bool {
+        let lower = asset_name.to_ascii_lowercase();
+        lower.ends_with(self.extension)
+            && self
+                .platforms
+                .iter()
+                .any(|platform| lower.contains(platform))
+    }
+}
+
+fn platform_asset() -> AssetSpec {
+    match std::env::consts::OS {
+        "windows" => AssetSpec::WINDOWS,
+        "macos" => AssetSpec::MACOS,
+        _ => AssetSpec::LINUX,
     }
 }
 
-fn find_release_asset<'a>(
-    release: &'a GithubRelease,
-    expected_name: &str,
-) -> Result<&'a GithubAsset> {
-    release
+/// Find the asset named `spec.name`, ignoring case. Otherwise fall back to
+/// any asset naming the platform with the right extension; among several,
+/// the shortest name wins, then the alphabetically first.
+fn find_release_asset<'a>(release: &'a GithubRelease, spec: &AssetSpec) -> Result<&'a GithubAsset> {
+    let exact = release
         .assets
         .iter()
-        .find(|asset| asset.name == expected_name)
-        .ok_or_else(|| {
-            let available = if release.assets.is_empty() {
-                "<no assets>".to_string()
-            } else {
-                release
-                    .assets
-                    .iter()
-                    .map(|asset| asset.name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            };
-            anyhow::anyhow!(
-                "Release asset `{expected_name}` was not found. Available assets: {available}"
-            )
-        })
+        .find(|asset| asset.name.eq_ignore_ascii_case(spec.name));
+    let loose = || {
+        release
+            .assets
+            .iter()
+            .filter(|asset| spec.matches_loosely(&asset.name))
+            .min_by(|a, b| {
+                a.name
+                    .len()
+                    .cmp(&b.name.len())
+                    .then_with(|| a.name.cmp(&b.name))
+            })
+    };
+    exact.or_else(loose).ok_or_else(|| {
+        let available = if release.assets.is_empty() {
+            "<no assets>".to_string()
+        } else {
+            release
+                .assets
+                .iter()
+                .map(|asset| asset.name.clone())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        anyhow::anyhow!(
+            "Release asset `{}` was not found. Available assets: {available}",
+            spec.name
+        )
+    })
 }
 
-fn download_to_file(url: &str, destination: &Path) -> Result<()> {
-    let client = Client::builder()
-        .build()
-        .context("building HTTP client for release download")?;
+fn download_to_file(url: &str, destination: &Path, offline: bool) -> Result<()> {
+    ensure_online(offline)?;
+    let client = http_client(offline).context("preparing HTTP client for release download")?;
 
     let mut response = client
         .get(url)
         .header(reqwest::header::USER_AGENT, "qbit-cli-upgrader")
         .send()
+        .map_err(|source| network_error(url, source))
         .with_context(|| format!("downloading release archive from {url}"))?
         .error_for_status()
+        .map_err(|source| network_error(url, source))
         .with_context(|| format!("failed to download release archive from {url}"))?;
 
     let mut file = File::create(destination)
@@ -218,9 +834,32 @@ fn extract_tar_gz(archive_path: &Path, destination: &Path) -> Result<()> {
         .with_context(|| format!("opening archive {}", archive_path.display()))?;
     let gz = GzDecoder::new(file);
     let mut archive = Archive::new(gz);
-    archive
-        .unpack(destination)
-        .with_context(|| format!("extracting tar.gz archive into {}", destination.display()))?;
+    archive.set_preserve_permissions(true);
+    let entries = archive
+        .entries()
+        .with_context(|| format!("reading tar.gz archive {}", archive_path.display()))?;
+
+    for entry in entries {
+        let mut entry = entry.context("reading tar entry")?;
+        let rel_path = entry.path().context("reading tar entry path")?.into_owned();
+        if !is_enclosed_relative_path(&rel_path) {
+            bail!(
+                "Refusing to extract archive entry `{}` outside {}",
+                rel_path.display(),
+                destination.display()
+            );
+        }
+        let unpacked = entry
+            .unpack_in(destination)
+            .with_context(|| format!("extracting {}", rel_path.display()))?;
+        if !unpacked {
+            bail!(
+                "Refusing to extract archive entry `{}` outside {}",
+                rel_path.display(),
+                destination.display()
+            );
+        }
+    }
     Ok(())
 }
 
@@ -228,45 +867,102 @@ fn extract_zip(archive_path: &Path, destination: &Path) -> Result<()> {
     let file = File::open(archive_path)
         .with_context(|| format!("opening archive {}", archive_path.display()))?;
     let mut zip = ZipArchive::new(file).context("opening zip archive")?;
+    let destination = destination
+        .canonicalize()
+        .with_context(|| format!("resolving extraction directory {}", destination.display()))?;
 
     for idx in 0..zip.len() {
         let mut entry = zip
             .by_index(idx)
             .with_context(|| format!("reading zip entry #{idx}"))?;
-        let Some(rel_path) = entry.enclosed_name().map(|p| p.to_path_buf()) else {
-            continue;
+        let Some(rel_path) = entry
+            .enclosed_name()
+            .filter(|p| is_enclosed_relative_path(p))
+        else {
+            bail!(
+                "Refusing to extract zip entry `{}` outside {}",
+                entry.name(),
+                destination.display()
+            );
         };
 
-        let out_path = destination.join(rel_path);
+        let out_path = destination.join(&rel_path);
         if entry.is_dir() {
             fs::create_dir_all(&out_path)
                 .with_context(|| format!("creating directory {}", out_path.display()))?;
+            ensure_within(&out_path, &destination)?;
             continue;
         }
 
         if let Some(parent) = out_path.parent() {
             fs::create_dir_all(parent)
                 .with_context(|| format!("creating directory {}", parent.display()))?;
+            ensure_within(parent, &destination)?;
         }
         let mut out_file = File::create(&out_path)
             .with_context(|| format!("creating extracted file {}", out_path.display()))?;
         io::copy(&mut entry, &mut out_file)
             .with_context(|| format!("extracting file {}", out_path.display()))?;
+
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&out_path, fs::Permissions::from_mode(mode & 0o7777))
+                .with_context(|| format!("setting permissions on {}", out_path.display()))?;
+        }
     }
 
     Ok(())
 }
 
+/// Archive member paths must be relative and free of `..` so they can't
+/// escape the extraction directory.
+fn is_enclosed_relative_path(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Re-verify after directories exist on disk that `path` resolves inside
+/// `root` (guards against symlinked parents).
+fn ensure_within(path: &Path, root: &Path) -> Result<()> {
+    let resolved = path
+        .canonicalize()
+        .with_context(|| format!("resolving extracted path {}", path.display()))?;
+    if !resolved.starts_with(root) {
+        bail!(
+            "Refusing to extract `{}` outside {}",
+            resolved.display(),
+            root.display()
+        );
+    }
+    Ok(())
+}
+
+/// Installer script a release archive ships for this platform, and the
+/// platform's name for error messages.
+#[cfg(target_os = "windows")]
+const INSTALLER_SCRIPT: (&str, &str) = ("install.ps1", "Windows");
+#[cfg(target_os = "macos")]
+const INSTALLER_SCRIPT: (&str, &str) = ("install_macos.sh", "macOS");
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const INSTALLER_SCRIPT: (&str, &str) = ("install.sh", "Linux");
+
+fn find_installer_script(extracted_dir: &Path) -> Result<PathBuf> {
+    let (name, platform) = INSTALLER_SCRIPT;
+    let script = extracted_dir.join(name);
+    if !script.exists() {
+        bail!(
+            "{platform} installer not found after extraction: {}",
+            script.display()
+        );
+    }
+    Ok(script)
+}
+
 fn run_platform_installer(extracted_dir: &Path) -> Result<()> {
     #[cfg(target_os = "windows")]
     {
-        let script = extracted_dir.join("install.ps1");
-        if !script.exists() {
-            bail!(
-                "Windows installer not found after extraction: {}",
-                script.display()
-            );
-        }
+        let script = find_installer_script(extracted_dir)?;
 
         let shell = if command_exists("pwsh") {
             "pwsh"
@@ -300,13 +996,7 @@ fn run_platform_installer(extracted_dir: &Path) -> Result<()> {
 
     #[cfg(target_os = "macos")]
     {
-        let script = extracted_dir.join("install_macos.sh");
-        if !script.exists() {
-            bail!(
-                "macOS installer not found after extraction: {}",
-                script.display()
-            );
-        }
+        let script = find_installer_script(extracted_dir)?;
 
         let status = Command::new("sh")
             .arg(&script)
@@ -328,13 +1018,7 @@ fn run_platform_installer(extracted_dir: &Path) -> Result<()> {
 
     #[cfg(target_os = "linux")]
     {
-        let script = extracted_dir.join("install.sh");
-        if !script.exists() {
-            bail!(
-                "Linux installer not found after extraction: {}",
-                script.display()
-            );
-        }
+        let script = find_installer_script(extracted_dir)?;
 
         let status = Command::new("sh")
             .arg(&script)
@@ -379,6 +1063,53 @@ fn command_exists(binary: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn rate_limit_message_names_the_reset_time() {
+        use reqwest::StatusCode;
+        use reqwest::header::{HeaderMap, HeaderValue};
+
+        let headers = |remaining: &'static str, reset: Option<&'static str>| {
+            let mut map = HeaderMap::new();
+            map.insert("x-ratelimit-remaining", HeaderValue::from_static(remaining));
+            if let Some(reset) = reset {
+                map.insert("x-ratelimit-reset", HeaderValue::from_static(reset));
+            }
+            map
+        };
+        let now = 1_700_000_000;
+
+        let message = rate_limit_message(
+            StatusCode::FORBIDDEN,
+            &headers("0", Some("1700000720")),
+            now,
+        )
+        .expect("rate limited");
+        assert_eq!(
+            message,
+            "GitHub API rate limit exceeded; set QBIT_GITHUB_TOKEN or retry after 2023-11-14T22:25:20Z (in 12 minutes)."
+        );
+        let message = rate_limit_message(StatusCode::TOO_MANY_REQUESTS, &headers("0", None), now)
+            .expect("rate limited");
+        assert!(message.ends_with("or retry later."), "{message}");
+
+        assert_eq!(
+            rate_limit_message(
+                StatusCode::FORBIDDEN,
+                &headers("12", Some("1700000720")),
+                now
+            ),
+            None
+        );
+        assert_eq!(
+            rate_limit_message(StatusCode::FORBIDDEN, &HeaderMap::new(), now),
+            None
+        );
+        assert_eq!(
+            rate_limit_message(StatusCode::NOT_FOUND, &headers("0", None), now),
+            None
+        );
+    }
+
     #[test]
     fn parse_version_accepts_v_prefix() {
         let version = parse_version("v1.2.3").expect("version");
@@ -391,6 +1122,359 @@ mod tests {
         assert!(err.to_string().contains("invalid semantic version"));
     }
 
+    fn releases(tags: &[&str]) -> Vec<GithubRelease> {
+        tags.iter()
+            .map(|tag| GithubRelease {
+                tag_name: tag.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_release_skips_prereleases_by_default() {
+        let tags = ["v1.2.0", "v1.3.0-beta.1", "not-a-version", "v1.1.9"];
+        let picked = select_release(releases(&tags), false).expect("stable release");
+        assert_eq!(picked.tag_name, "v1.2.0");
+    }
+
+    #[test]
+    fn select_release_orders_prereleases_by_semver() {
+        let tags = [
+            "v1.2.0",
+            "v1.3.0-beta.2",
+            "v1.3.0-beta.10",
+            "v1.3.0-alpha.5",
+        ];
+        let picked = select_release(releases(&tags), true).expect("prerelease");
+        assert_eq!(picked.tag_name, "v1.3.0-beta.10");
+
+        let tags = ["v1.3.0-rc.1", "v1.3.0"];
+        let picked = select_release(releases(&tags), true).expect("stable wins");
+        assert_eq!(picked.tag_name, "v1.3.0");
+    }
+
+    #[test]
+    fn check_with_pre_reports_stable_and_prerelease_separately() {
+        let current = parse_version("1.2.0").unwrap();
+        let channels = ReleaseChannels::from_releases(releases(&[
+            "v1.2.0",
+            "v1.3.0",
+            "v1.4.0-beta.2",
+            "v1.4.0-beta.1",
+        ]));
+        let lines = channel_report(&current, &channels).expect("report");
+        assert_eq!(
+            lines,
+            vec![
+                "Current version:     1.2.0",
+                "Latest stable:       1.3.0",
+                "Latest pre-release:  1.4.0-beta.2",
+                "A newer stable qbit is available. Run `qbit upgrade` to install it.",
+            ]
+        );
+
+        let channels = ReleaseChannels::from_releases(releases(&["v1.3.0-rc.1", "v1.3.0"]));
+        let lines = channel_report(&parse_version("1.3.0").unwrap(), &channels).expect("report");
+        assert_eq!(lines[2], "Latest pre-release:  none newer than 1.3.0");
+        assert_eq!(lines[3], "qbit is already up to date.");
+
+        let channels = ReleaseChannels::from_releases(releases(&["v1.2.0", "v1.3.0-beta.1"]));
+        let lines = channel_report(&current, &channels).expect("report");
+        assert!(lines[3].contains("qbit upgrade --pre"), "{lines:?}");
+    }
+
+    #[test]
+    fn release_notes_are_indented_and_truncated_with_a_link() {
+        let version = Version::new(1, 3, 0);
+        let mut release = GithubRelease {
+            tag_name: "v1.3.0".to_string(),
+            body: Some("## What's changed\r\n\r\n- Faster installs\r\n".to_string()),
+            html_url: Some("https://github.com/qbit-click/qbit-cli/releases/v1.3.0".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            release_notes(&release, &version),
+            vec![
+                "Release notes for 1.3.0:",
+                "  ## What's changed",
+                "",
+                "  - Faster installs",
+            ]
+        );
+
+        let long: Vec<String> = (1..=100).map(|n| format!("- change {n}")).collect();
+        release.body = Some(long.join("\n"));
+        let notes = release_notes(&release, &version);
+        assert_eq!(notes.len(), 1 + MAX_NOTES_LINES + 1);
+        assert_eq!(
+            notes.last().map(String::as_str),
+            Some("  ... see full notes at https://github.com/qbit-click/qbit-cli/releases/v1.3.0")
+        );
+
+        release.body = Some("  \n".to_string());
+        assert!(release_notes(&release, &version).is_empty());
+    }
+
+    #[test]
+    fn prerelease_build_upgrades_to_matching_stable() {
+        let current = parse_version("1.2.0-rc.1").expect("version");
+        let latest = parse_version("v1.2.0").expect("version");
+        assert!(is_upgrade_available(&current, &latest, false));
+        assert!(is_upgrade_available(&current, &latest, true));
+    }
+
+    #[test]
+    fn stable_build_ignores_prerelease_unless_opted_in() {
+        let current = parse_version("1.2.0").expect("version");
+        let same_line = parse_version("v1.2.0-rc.2").expect("version");
+        assert!(!is_upgrade_available(&current, &same_line, false));
+        assert!(!is_upgrade_available(&current, &same_line, true));
+
+        let next = parse_version("v1.3.0-beta.1").expect("version");
+        assert!(!is_upgrade_available(&current, &next, false));
+        assert!(is_upgrade_available(&current, &next, true));
+    }
+
+    #[test]
+    fn upgrade_in_offline_mode_fails_before_any_request() {
+        let options = UpgradeOptions {
+            offline: true,
+            ..Default::default()
+        };
+        let err = upgrade(&options).expect_err("must fail");
+        assert!(
+            matches!(QbitError::find(&err), Some(QbitError::Offline)),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn failed_download_is_a_typed_network_error() {
+        // Bind and drop a listener so the port refuses connections.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port")
+            .port();
+        let url = format!("http://127.0.0.1:{port}/qbit.tar.gz");
+        let temp = TempDirGuard::new().expect("temp dir");
+
+        let err = download_to_file(&url, &temp.path().join("qbit.tar.gz"), false)
+            .expect_err("connection refused");
+        assert!(
+            matches!(
+                err.downcast_ref::<QbitError>(),
+                Some(QbitError::Network { url: failed, .. }) if *failed == url
+            ),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn extract_zip_rejects_parent_dir_entries() {
+        let temp = TempDirGuard::new().expect("temp dir");
+        let archive_path = temp.path().join("evil.zip");
+        {
+            let file = File::create(&archive_path).expect("create zip");
+            let mut writer = zip::ZipWriter::new(file);
+            writer
+                .start_file("../evil", zip::write::SimpleFileOptions::default())
+                .expect("start entry");
+            writer.write_all(b"pwned").expect("write entry");
+            writer.finish().expect("finish zip");
+        }
+
+        let out_dir = temp.path().join("out");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        let err = extract_zip(&archive_path, &out_dir).expect_err("must fail");
+        assert!(err.to_string().contains("Refusing to extract"));
+        assert!(!temp.path().join("evil").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_zip_preserves_unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDirGuard::new().expect("temp dir");
+        let archive_path = temp.path().join("setup.zip");
+        {
+            let file = File::create(&archive_path).expect("create zip");
+            let mut writer = zip::ZipWriter::new(file);
+            let options = zip::write::SimpleFileOptions::default().unix_permissions(0o755);
+            writer
+                .start_file("install.sh", options)
+                .expect("start entry");
+            writer.write_all(b"#!/bin/sh\n").expect("write entry");
+            writer.finish().expect("finish zip");
+        }
+
+        let out_dir = temp.path().join("out");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        extract_zip(&archive_path, &out_dir).expect("extract zip");
+
+        let mode = fs::metadata(out_dir.join("install.sh"))
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn upgrade_from_local_archive_finds_installer_without_network() {
+        let temp = TempDirGuard::new().expect("temp dir");
+        let archive_path = temp.path().join("qbit-linux-setup.tar.gz");
+        {
+            let file = File::create(&archive_path).expect("create archive");
+            let gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            let mut builder = tar::Builder::new(gz);
+            let body = b"echo installing\n";
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, INSTALLER_SCRIPT.0, &body[..])
+                .expect("append entry");
+            builder
+                .into_inner()
+                .expect("finish tar")
+                .finish()
+                .expect("finish gzip");
+        }
+
+        // Offline mode proves the local path never reaches for the network.
+        let out_dir = temp.path().join("out");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        let source = archive_path.to_str().expect("utf-8 path");
+        let installer = stage_archive(source, &out_dir, true).expect("stage archive");
+        assert_eq!(installer, out_dir.join(INSTALLER_SCRIPT.0));
+        assert!(installer.is_file());
+
+        let err = stage_archive("setup.tar.bz2", &out_dir, true).expect_err("unsupported");
+        assert!(err.to_string().contains(".tar.gz or .zip"), "{err}");
+        let err = stage_archive(
+            "https://example.test/qbit-linux-setup.tar.gz?x=1",
+            &out_dir,
+            true,
+        )
+        .expect_err("offline download");
+        assert!(
+            matches!(err.downcast_ref::<QbitError>(), Some(QbitError::Offline)),
+            "{err:#}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_tar_gz_preserves_unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = TempDirGuard::new().expect("temp dir");
+        let archive_path = temp.path().join("setup.tar.gz");
+        {
+            let file = File::create(&archive_path).expect("create archive");
+            let gz = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            let mut builder = tar::Builder::new(gz);
+            let body = b"#!/bin/sh\n";
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "qbit-cli", &body[..])
+                .expect("append entry");
+            builder
+                .into_inner()
+                .expect("finish tar")
+                .finish()
+                .expect("finish gzip");
+        }
+
+        let out_dir = temp.path().join("out");
+        fs::create_dir_all(&out_dir).expect("create out dir");
+        extract_tar_gz(&archive_path, &out_dir).expect("extract tar.gz");
+
+        let mode = fs::metadata(out_dir.join("qbit-cli"))
+            .expect("metadata")
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+
+    #[test]
+    fn executable_backup_restores_original_contents() {
+        let temp = TempDirGuard::new().expect("temp dir");
+        let exe = temp.path().join("qbit-cli");
+        fs::write(&exe, b"old").expect("write exe");
+
+        let backup = ExecutableBackup::create(&exe).expect("backup");
+        assert!(temp.path().join("qbit-cli.bak").exists());
+
+        fs::write(&exe, b"half-written").expect("clobber exe");
+        backup.restore().expect("restore");
+
+        assert_eq!(fs::read(&exe).expect("read exe"), b"old");
+        assert!(!temp.path().join("qbit-cli.bak").exists());
+        assert!(!temp.path().join("qbit-cli.failed").exists());
+    }
+
+    #[test]
+    fn executable_backup_discard_removes_copy() {
+        let temp = TempDirGuard::new().expect("temp dir");
+        let exe = temp.path().join("qbit-cli");
+        fs::write(&exe, b"new").expect("write exe");
+
+        let backup = ExecutableBackup::create(&exe).expect("backup");
+        backup.discard().expect("discard");
+
+        assert!(!temp.path().join("qbit-cli.bak").exists());
+        assert_eq!(fs::read(&exe).expect("read exe"), b"new");
+    }
+
+    #[test]
+    fn enclosed_relative_paths_reject_escapes() {
+        assert!(is_enclosed_relative_path(Path::new("bin/qbit-cli")));
+        assert!(!is_enclosed_relative_path(Path::new("../evil")));
+        assert!(!is_enclosed_relative_path(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn repository_flag_beats_env_which_beats_default() {
+        assert_eq!(
+            resolve_repository(Some("me/qbit-fork"), Some("corp/qbit-mirror")).unwrap(),
+            "me/qbit-fork"
+        );
+        assert_eq!(
+            resolve_repository(None, Some("corp/qbit-mirror")).unwrap(),
+            "corp/qbit-mirror"
+        );
+        assert_eq!(
+            resolve_repository(Some("  "), None).unwrap(),
+            DEFAULT_REPOSITORY
+        );
+        assert_eq!(resolve_repository(None, None).unwrap(), DEFAULT_REPOSITORY);
+    }
+
+    #[test]
+    fn repository_must_be_owner_slash_name() {
+        for invalid in ["qbit", "owner/", "/name", "a/b/c", "owner/na me"] {
+            let err = resolve_repository(Some(invalid), None).unwrap_err();
+            assert!(err.to_string().contains("--repo"), "{invalid}: {err}");
+        }
+        let err = resolve_repository(None, Some("bad")).unwrap_err();
+        assert!(err.to_string().contains("QBIT_UPGRADE_REPO"), "{err}");
+    }
+
+    #[test]
+    fn cache_freshness_respects_ttl() {
+        assert!(is_cache_fresh(1_000, 1_000, 3600));
+        assert!(is_cache_fresh(1_000, 4_599, 3600));
+        assert!(!is_cache_fresh(1_000, 4_600, 3600));
+        assert!(!is_cache_fresh(5_000, 1_000, 3600));
+        assert!(!is_cache_fresh(1_000, 1_000, 0));
+    }
+
     #[test]
     fn find_release_asset_matches_expected_name() {
         let release = GithubRelease {
@@ -405,9 +1489,52 @@ mod tests {
                     browser_download_url: "https://example.test/windows".to_string(),
                 },
             ],
+            ..Default::default()
         };
 
-        let found = find_release_asset(&release, "qbit-windows-setup.zip").expect("asset");
+        let found = find_release_asset(&release, &AssetSpec::WINDOWS).expect("asset");
         assert_eq!(found.browser_download_url, "https://example.test/windows");
     }
+
+    fn release_with(names: &[&str]) -> GithubRelease {
+        GithubRelease {
+            tag_name: "v1.0.0".to_string(),
+            assets: names
+                .iter()
+                .map(|name| GithubAsset {
+                    name: name.to_string(),
+                    browser_download_url: format!("https://example.test/{name}"),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn find_release_asset_ignores_case() {
+        let release = release_with(&["qbit-Linux-setup.tar.gz", "qbit-windows-setup.zip"]);
+        let found = find_release_asset(&release, &AssetSpec::LINUX).expect("asset");
+        assert_eq!(found.name, "qbit-Linux-setup.tar.gz");
+    }
+
+    #[test]
+    fn find_release_asset_falls_back_to_platform_and_extension() {
+        let release = release_with(&[
+            "qbit-linux-setup-v1.0.0.zip",
+            "qbit-linux-setup-v1.0.0.tar.gz",
+            "qbit-linux-setup-v1.0.0-debug.tar.gz",
+            "qbit-darwin-v1.0.0.tar.gz",
+        ]);
+        let linux = find_release_asset(&release, &AssetSpec::LINUX).expect("linux asset");
+        assert_eq!(linux.name, "qbit-linux-setup-v1.0.0.tar.gz");
+        let macos = find_release_asset(&release, &AssetSpec::MACOS).expect("macos asset");
+        assert_eq!(macos.name, "qbit-darwin-v1.0.0.tar.gz");
+
+        let err = find_release_asset(&release, &AssetSpec::WINDOWS).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Available assets: qbit-linux-setup-v1.0.0.zip"),
+            "{err}"
+        );
+    }
 }
@@ -0,0 +1,151 @@
+use std::path::Path;
+
+use anyhow::{Result, bail};
+
+use crate::os::package_manager::InstallCommand;
+use crate::utils::paths::find_executable;
+
+/// Env var forcing a version manager (`asdf`, `mise`) or disabling the
+/// routing entirely (`none`).
+pub const VERSION_MANAGER_OVERRIDE: &str = "QBIT_VERSION_MANAGER";
+
+/// Tool version managers whose shims would shadow an OS-installed runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionManager {
+    Asdf,
+    Mise,
+}
+
+impl VersionManager {
+    /// Checked in order: mise also reads `.tool-versions`, so a `mise.toml`
+    /// next to one means the project is on mise.
+    const ALL: [VersionManager; 2] = [VersionManager::Mise, VersionManager::Asdf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VersionManager::Asdf => "asdf",
+            VersionManager::Mise => "mise",
+        }
+    }
+
+    fn markers(self) -> &'static [&'static str] {
+        match self {
+            VersionManager::Asdf => &[".tool-versions"],
+            VersionManager::Mise => &["mise.toml", ".mise.toml"],
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|manager| manager.name().eq_ignore_ascii_case(name))
+    }
+
+    /// `asdf install <tool> <version>` or `mise install <tool>@<version>`.
+    pub fn install_command(self, tool: &str, version: &str) -> InstallCommand {
+        let args = match self {
+            VersionManager::Asdf => {
+                vec!["install".to_string(), tool.to_string(), version.to_string()]
+            }
+            VersionManager::Mise => vec!["install".to_string(), format!("{tool}@{version}")],
+        };
+        InstallCommand::new(self.name(), args)
+    }
+}
+
+/// The version manager `qbit install` should route pinned targets to, if any.
+/// `QBIT_VERSION_MANAGER` wins; otherwise a marker file in `project_dir` plus
+/// the manager's executable on PATH turns routing on.
+pub fn detect_version_manager(project_dir: &Path) -> Result<Option<VersionManager>> {
+    let raw_override = std::env::var(VERSION_MANAGER_OVERRIDE).ok();
+    match raw_override.as_deref().map(str::trim) {
+        None | Some("") => Ok(detect_from_markers(project_dir, |manager| {
+            find_executable(manager.name()).is_some()
+        })),
+        Some(value) if value.eq_ignore_ascii_case("none") => Ok(None),
+        Some(value) => {
+            let Some(manager) = VersionManager::from_name(value) else {
+                bail!(
+                    "Unsupported {VERSION_MANAGER_OVERRIDE} value `{value}`. Supported values: asdf, mise, none."
+                );
+            };
+            if find_executable(manager.name()).is_none() {
+                bail!(
+                    "{VERSION_MANAGER_OVERRIDE} is set to `{value}`, but `{}` is not available in PATH. Install it or unset {VERSION_MANAGER_OVERRIDE}.",
+                    manager.name()
+                );
+            }
+            Ok(Some(manager))
+        }
+    }
+}
+
+fn detect_from_markers(
+    project_dir: &Path,
+    is_available: impl Fn(VersionManager) -> bool,
+) -> Option<VersionManager> {
+    VersionManager::ALL.into_iter().find(|&manager| {
+        manager
+            .markers()
+            .iter()
+            .any(|marker| project_dir.join(marker).is_file())
+            && is_available(manager)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn builds_asdf_install_command() {
+        let command = VersionManager::Asdf.install_command("python", "3.12.4");
+        assert_eq!(command.render(), "asdf install python 3.12.4");
+    }
+
+    #[test]
+    fn builds_mise_install_command() {
+        let command = VersionManager::Mise.install_command("node", "20");
+        assert_eq!(command.render(), "mise install node@20");
+    }
+
+    #[test]
+    fn detects_manager_from_marker_files() {
+        let tmp = tempdir().expect("tempdir");
+        assert_eq!(detect_from_markers(tmp.path(), |_| true), None);
+
+        fs::write(tmp.path().join(".tool-versions"), "python 3.12.4\n").unwrap();
+        assert_eq!(
+            detect_from_markers(tmp.path(), |_| true),
+            Some(VersionManager::Asdf)
+        );
+        assert_eq!(detect_from_markers(tmp.path(), |_| false), None);
+
+        fs::write(tmp.path().join("mise.toml"), "[tools]\n").unwrap();
+        assert_eq!(
+            detect_from_markers(tmp.path(), |_| true),
+            Some(VersionManager::Mise)
+        );
+        assert_eq!(
+            detect_from_markers(tmp.path(), |manager| manager == VersionManager::Asdf),
+            Some(VersionManager::Asdf)
+        );
+    }
+
+    #[test]
+    fn parses_override_names_case_insensitively() {
+        assert_eq!(
+            VersionManager::from_name("ASDF"),
+            Some(VersionManager::Asdf)
+        );
+        assert_eq!(
+            VersionManager::from_name("mise"),
+            Some(VersionManager::Mise)
+        );
+        assert_eq!(VersionManager::from_name("rtx"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+/// Well-known generated directories, keyed by the marker files that show the
+/// project uses that ecosystem.
+const ARTIFACTS: &[(&str, &[&str])] = &[
+    // `./venv` is where qbit keeps the managed virtualenv, so it counts as its
+    // own marker.
+    ("venv", &["requirements.txt", "pyproject.toml", "venv"]),
+    ("node_modules", &["package.json"]),
+    ("target", &["Cargo.toml"]),
+    (".dart_tool", &["pubspec.yaml"]),
+];
+
+/// Artifact directories present in `dir` for the project types detected there.
+/// Symlinks are skipped so cleaning never reaches outside the project.
+pub fn find_artifacts(dir: &Path) -> Vec<PathBuf> {
+    ARTIFACTS
+        .iter()
+        .filter(|(_, markers)| markers.iter().any(|marker| dir.join(marker).exists()))
+        .map(|(artifact, _)| dir.join(artifact))
+        .filter(|path| {
+            fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir() && !meta.is_symlink())
+        })
+        .collect()
+}
+
+/// Remove the detected artifact directories from the current directory after
+/// confirming (skipped with `yes`). Without `interactive` (or a terminal) an
+/// unconfirmed clean fails rather than prompting.
+pub fn clean(yes: bool, interactive: bool) -> Result<()> {
+    let cwd = std::env::current_dir().context("resolving current directory")?;
+    if cwd.parent().is_none() {
+        bail!("Refusing to clean the filesystem root. Run `qbit clean` from a project directory.");
+    }
+
+    let artifacts = find_artifacts(&cwd);
+    if artifacts.is_empty() {
+        println!("Nothing to clean.");
+        return Ok(());
+    }
+
+    println!("Will remove:");
+    for path in &artifacts {
+        println!("  {}", display_name(path, &cwd));
+    }
+    if !yes {
+        if !interactive || !io::stdin().is_terminal() {
+            bail!(
+                "Not removing anything without confirmation. Pass `--yes` to clean non-interactively."
+            );
+        }
+        if !confirm()? {
+            println!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    for path in &artifacts {
+        fs::remove_dir_all(path).with_context(|| format!("removing {}", path.display()))?;
+        println!("Removed {}", display_name(path, &cwd));
+    }
+    Ok(())
+}
+
+fn display_name(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn confirm() -> Result<bool> {
+    print!("Proceed? [y/N]: ");
+    io::stdout().flush().context("flushing prompt")?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("reading confirmation")?;
+    Ok(is_yes(&answer))
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn finds_artifacts_only_for_detected_project_types() {
+        let tmp = tempdir().expect("tempdir");
+        for dir in ["node_modules", "target", ".dart_tool"] {
+            fs::create_dir(tmp.path().join(dir)).expect("create artifact dir");
+        }
+        assert!(find_artifacts(tmp.path()).is_empty());
+
+        fs::write(tmp.path().join("package.json"), "{}").expect("write package.json");
+        fs::write(tmp.path().join("Cargo.toml"), "").expect("write Cargo.toml");
+        assert_eq!(
+            find_artifacts(tmp.path()),
+            vec![tmp.path().join("node_modules"), tmp.path().join("target")]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_symlinked_artifact_dirs() {
+        let tmp = tempdir().expect("tempdir");
+        let outside = tempdir().expect("outside dir");
+        fs::write(tmp.path().join("package.json"), "{}").expect("write package.json");
+        std::os::unix::fs::symlink(outside.path(), tmp.path().join("node_modules"))
+            .expect("symlink node_modules");
+
+        assert!(find_artifacts(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn confirmation_defaults_to_no() {
+        assert!(is_yes("Y\n"));
+        assert!(is_yes(" yes "));
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("nope"));
+    }
+}
@@ -0,0 +1,440 @@
+//! `qbit config get/set`: read or change one scalar value in qbit.yml by its
+//! dotted key. `set` rewrites only the line holding the value (or adds one
+//! line for a new key), so comments, key order, and quoting elsewhere in the
+//! file stay as they were.
+
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_yaml::Value;
+
+use crate::config::{ConfigFormat, find_project_config_file, parse_yaml_str};
+use crate::error::QbitError;
+
+/// Top-level sections whose values `qbit config` reads and writes.
+const EDITABLE_SECTIONS: [&str; 2] = ["install", "scripts"];
+
+/// Print the value at `key`, e.g. `install.postgres.version`.
+pub fn get(key: &str) -> Result<()> {
+    let (path, content) = read_config()?;
+    println!("{}", get_value(&content, &path, key)?);
+    Ok(())
+}
+
+/// Set the scalar at `key` to `value`, adding the key when its parent
+/// section exists but doesn't have it yet.
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let (path, content) = read_config()?;
+    let updated = set_value(&content, &path, key, value)?;
+    fs::write(&path, updated).with_context(|| format!("writing {}", path.display()))?;
+    println!("Set `{key}` to `{value}` in {}.", path.display());
+    Ok(())
+}
+
+fn read_config() -> Result<(PathBuf, String)> {
+    let dir = std::env::current_dir().context("resolving current directory for config")?;
+    let Some((path, format)) = find_project_config_file(&dir) else {
+        bail!(QbitError::no_config());
+    };
+    if matches!(format, ConfigFormat::Toml) {
+        bail!(
+            "`qbit config` only edits YAML configs; change {} manually.",
+            path.display()
+        );
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("reading project config at {}", path.display()))?;
+    Ok((path, content))
+}
+
+fn get_value(content: &str, path: &Path, key: &str) -> Result<String> {
+    let keys = parse_key(key)?;
+    let doc = parse_document(content, path)?;
+    let (_, value) = resolve(&doc, &keys, path)?;
+    match value {
+        None => bail!("`{key}` isn't set in {}.", path.display()),
+        Some(Value::String(text)) => Ok(text.clone()),
+        Some(Value::Number(number)) => Ok(number.to_string()),
+        Some(Value::Bool(flag)) => Ok(flag.to_string()),
+        Some(Value::Null) => bail!("`{key}` has no value in {}.", path.display()),
+        Some(_) => bail!(
+            "`{key}` is a section or list, not a single value; ask for one of its keys (`{key}.<name>`) or read {}.",
+            path.display()
+        ),
+    }
+}
+
+fn set_value(content: &str, path: &Path, key: &str, value: &str) -> Result<String> {
+    let keys = parse_key(key)?;
+    let doc = parse_document(content, path)?;
+    let (spelled, existing) = resolve(&doc, &keys, path)?;
+    let manual = || {
+        anyhow!(
+            "`{key}` is written in a form `qbit config` can't change safely (a section, list, multi-line or inline value); edit {} manually.",
+            path.display()
+        )
+    };
+
+    // The new value keeps the type of the old one: `timeout: 30` stays a
+    // number, everything else is a string.
+    let expected = match existing {
+        Some(Value::Mapping(_) | Value::Sequence(_) | Value::Tagged(_)) => return Err(manual()),
+        Some(Value::Number(_)) => match serde_yaml::from_str::<Value>(value) {
+            Ok(number @ Value::Number(_)) => number,
+            _ => bail!("`{key}` holds a number; `{value}` isn't one."),
+        },
+        Some(Value::Bool(_)) => match serde_yaml::from_str::<Value>(value) {
+            Ok(flag @ Value::Bool(_)) => flag,
+            _ => bail!("`{key}` holds true or false; `{value}` is neither."),
+        },
+        Some(Value::String(_) | Value::Null) | None => Value::String(value.to_string()),
+    };
+
+    let mut lines: Vec<String> = content.split('\n').map(str::to_string).collect();
+    let mut range = 0..lines.len();
+    let mut indent = 0;
+    for (depth, name) in spelled.iter().enumerate() {
+        let leaf = depth + 1 == spelled.len();
+        let Some(index) = find_key_line(&lines, range.clone(), indent, name) else {
+            if !leaf || existing.is_some() {
+                return Err(manual());
+            }
+            let at = last_content_line(&lines, range.clone()).ok_or_else(manual)? + 1;
+            let rendered = match &expected {
+                Value::String(text) => render_string(text, None),
+                _ => value.trim().to_string(),
+            };
+            let ending = if content.contains("\r\n") { "\r" } else { "" };
+            let line = format!(
+                "{}{}: {rendered}{ending}",
+                " ".repeat(indent),
+                render_string(name, None)
+            );
+            lines.insert(at, line);
+            break;
+        };
+
+        let line = &lines[index];
+        let value_start = key_value_start(line, indent, name).ok_or_else(manual)?;
+        let end = block_end(&lines, index, indent);
+        if !leaf {
+            if !value_text(&line[value_start..]).0.is_empty() {
+                return Err(manual());
+            }
+            let children = index + 1..end;
+            indent = first_content_line(&lines, children.clone())
+                .map(|child| indent_of(&lines[child]))
+                .filter(|child| *child > indent)
+                .ok_or_else(manual)?;
+            range = children;
+            continue;
+        }
+
+        // Continuation lines mean a multi-line value.
+        if first_content_line(&lines, index + 1..end).is_some() {
+            return Err(manual());
+        }
+        let rest = &line[value_start..];
+        let (raw, tail) = value_text(rest);
+        if raw.starts_with(['|', '>', '&', '*', '!', '{', '[']) {
+            return Err(manual());
+        }
+        let rendered = match &expected {
+            Value::String(text) => render_string(text, Some(raw)),
+            _ => value.trim().to_string(),
+        };
+        let gap = &rest[..rest.len() - rest.trim_start().len()];
+        let gap = if gap.is_empty() || raw.is_empty() {
+            " "
+        } else {
+            gap
+        };
+        let tail = if raw.is_empty() && !tail.trim().is_empty() {
+            format!(" {}", tail.trim_start())
+        } else {
+            tail.to_string()
+        };
+        lines[index] = format!("{}{gap}{rendered}{tail}", &line[..value_start]);
+    }
+
+    // Re-read the result so a layout this editor misjudged can't slip through.
+    let updated = lines.join("\n");
+    let reparsed = parse_document(&updated, path)?;
+    match resolve(&reparsed, &keys, path) {
+        Ok((_, Some(found))) if *found == expected => {}
+        _ => return Err(manual()),
+    }
+    parse_yaml_str(&updated).map_err(|err| {
+        anyhow!(
+            "Setting `{key}` to `{value}` would make {} invalid ({err}); pick a different value or edit the file manually.",
+            path.display()
+        )
+    })?;
+    Ok(updated)
+}
+
+/// Split `install.postgres.version` into keys, checking it names a value
+/// inside one of the editable sections.
+fn parse_key(key: &str) -> Result<Vec<&str>> {
+    let keys: Vec<&str> = key.split('.').collect();
+    if keys.iter().any(|part| part.trim().is_empty()) {
+        bail!("`{key}` isn't a dotted key; use a form like `install.postgres.version`.");
+    }
+    if !EDITABLE_SECTIONS.contains(&keys[0]) {
+        bail!(
+            "`qbit config` only handles keys under `install` and `scripts`; edit `{}` in the config file manually.",
+            keys[0]
+        );
+    }
+    if keys.len() == 1 {
+        bail!("`{key}` is a whole section; name a single value inside it, like `{key}.<name>`.");
+    }
+    Ok(keys)
+}
+
+fn parse_document(content: &str, path: &Path) -> Result<Value> {
+    serde_yaml::from_str(content).map_err(|err| {
+        anyhow!(
+            "{} isn't valid YAML ({err}); fix it before using `qbit config`.",
+            path.display()
+        )
+    })
+}
+
+/// Walk `keys` through the document. Returns each key as it is spelled in the
+/// file (names match case-insensitively, like install targets) and the value
+/// at the end, or `None` when only the last key is missing.
+fn resolve<'a>(
+    doc: &'a Value,
+    keys: &[&str],
+    path: &Path,
+) -> Result<(Vec<String>, Option<&'a Value>)> {
+    let mut spelled = Vec::new();
+    let mut current = doc;
+    for (depth, key) in keys.iter().enumerate() {
+        let parent = keys[..depth].join(".");
+        let Value::Mapping(mapping) = current else {
+            bail!(
+                "`{parent}` in {} is a single value, not a section; turning it into one is a structural change, so edit the file manually.",
+                path.display()
+            );
+        };
+        let exact = mapping.iter().find(|(name, _)| name.as_str() == Some(key));
+        let found = match exact {
+            Some(entry) => Some(entry),
+            None => {
+                let matches: Vec<_> = mapping
+                    .iter()
+                    .filter(|(name, _)| {
+                        name.as_str()
+                            .is_some_and(|name| name.eq_ignore_ascii_case(key))
+                    })
+                    .collect();
+                if matches.len() > 1 {
+                    let names: Vec<&str> = matches
+                        .iter()
+                        .filter_map(|(name, _)| name.as_str())
+                        .collect();
+                    bail!(
+                        "`{key}` is ambiguous in {}: it matches `{}`. Spell the key exactly as the file does.",
+                        path.display(),
+                        names.join("`, `")
+                    );
+                }
+                matches.into_iter().next()
+            }
+        };
+        match found {
+            Some((name, value)) => {
+                spelled.push(name.as_str().unwrap_or(key).to_string());
+                current = value;
+            }
+            None if depth + 1 == keys.len() => {
+                spelled.push(key.to_string());
+                return Ok((spelled, None));
+            }
+            None => {
+                let missing = keys[..=depth].join(".");
+                bail!(
+                    "`{missing}` isn't defined in {}; add it manually, then set values inside it.",
+                    path.display()
+                );
+            }
+        }
+    }
+    Ok((spelled, Some(current)))
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Blank lines and comments don't affect a block's extent.
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+fn first_content_line(lines: &[String], range: Range<usize>) -> Option<usize> {
+    range.into_iter().find(|&index| is_content(&lines[index]))
+}
+
+fn last_content_line(lines: &[String], range: Range<usize>) -> Option<usize> {
+    range
+        .into_iter()
+        .rev()
+        .find(|&index| is_content(&lines[index]))
+}
+
+/// One past the last line that belongs to the key on line `index`.
+fn block_end(lines: &[String], index: usize, indent: usize) -> usize {
+    (index + 1..lines.len())
+        .find(|&next| is_content(&lines[next]) && indent_of(&lines[next]) <= indent)
+        .unwrap_or(lines.len())
+}
+
+fn find_key_line(lines: &[String], range: Range<usize>, indent: usize, key: &str) -> Option<usize> {
+    range
+        .into_iter()
+        .find(|&index| key_value_start(&lines[index], indent, key).is_some())
+}
+
+/// Byte offset just past `key:` when `line` holds `key` at `indent`, with
+/// the key written plain or quoted.
+fn key_value_start(line: &str, indent: usize, key: &str) -> Option<usize> {
+    if indent_of(line) != indent {
+        return None;
+    }
+    let body = &line[indent..];
+    [key.to_string(), format!("\"{key}\""), format!("'{key}'")]
+        .iter()
+        .find_map(|spelling| {
+            let rest = body.strip_prefix(spelling.as_str())?.strip_prefix(':')?;
+            (rest.is_empty() || rest.starts_with([' ', '\t', '\r']))
+                .then_some(line.len() - rest.len())
+        })
+}
+
+/// Split the text after `key:` into the value itself and whatever follows it
+/// (spacing, a `# comment`, the line's `\r`).
+fn value_text(rest: &str) -> (&str, &str) {
+    let text = rest.trim_start();
+    let len = if text.starts_with('#') {
+        0
+    } else if let Some(quoted) = text.strip_prefix('"') {
+        let mut escaped = false;
+        quoted
+            .char_indices()
+            .find(|&(_, ch)| {
+                let closes = ch == '"' && !escaped;
+                escaped = ch == '\\' && !escaped;
+                closes
+            })
+            .map_or(text.len(), |(at, _)| at + 2)
+    } else if text.starts_with('\'') {
+        let bytes = text.as_bytes();
+        let mut at = 1;
+        loop {
+            match bytes.get(at) {
+                Some(b'\'') if bytes.get(at + 1) == Some(&b'\'') => at += 2,
+                Some(b'\'') => break at + 1,
+                Some(_) => at += 1,
+                None => break text.len(),
+            }
+        }
+    } else {
+        let end = [" #", "\t#"]
+            .iter()
+            .filter_map(|marker| text.find(marker))
+            .min()
+            .unwrap_or(text.len());
+        text[..end].trim_end().len()
+    };
+    text.split_at(len)
+}
+
+/// `text` as a YAML scalar: in the quote style of the value it replaces,
+/// otherwise plain when YAML would read it back unchanged.
+fn render_string(text: &str, replacing: Option<&str>) -> String {
+    let double = || serde_json::to_string(text).unwrap_or_else(|_| format!("\"{text}\""));
+    match replacing {
+        Some(old) if old.starts_with('\'') && !text.contains('\n') => {
+            format!("'{}'", text.replace('\'', "''"))
+        }
+        Some(old) if old.starts_with('"') => double(),
+        _ => {
+            let plain = !text.is_empty()
+                && !text.contains(" #")
+                && serde_yaml::from_str::<Value>(text)
+                    .is_ok_and(|value| value.as_str() == Some(text));
+            if plain { text.to_string() } else { double() }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# project tools\ninstall:\n  manager: apt\n  postgres:\n    version: \"15\"  # LTS\n    identifiers:\n      apt: postgresql\n  git: git\nscripts:\n  build: cargo build\n  test:\n    run: cargo test\n    timeout: 30\n";
+
+    fn set(key: &str, value: &str) -> Result<String> {
+        set_value(CONFIG, Path::new("qbit.yml"), key, value)
+    }
+
+    #[test]
+    fn set_changes_only_the_value_and_get_reads_it_back() {
+        let updated = set("install.postgres.version", "16").expect("set version");
+        assert_eq!(
+            updated,
+            CONFIG.replace("version: \"15\"  # LTS", "version: \"16\"  # LTS")
+        );
+        let value = get_value(&updated, Path::new("qbit.yml"), "install.postgres.version");
+        assert_eq!(value.expect("get version"), "16");
+
+        let updated = set("scripts.build", "cargo build --release").expect("set script");
+        assert!(updated.contains("  build: cargo build --release\n"));
+        let updated = set("scripts.test.timeout", "60").expect("set timeout");
+        assert!(updated.contains("    timeout: 60\n"));
+        assert!(set("scripts.test.timeout", "soon").is_err());
+    }
+
+    #[test]
+    fn set_adds_a_missing_leaf_to_an_existing_section() {
+        let updated = set("install.postgres.note", "Needs: a running server").expect("add note");
+        assert!(
+            updated.contains(
+                "      apt: postgresql\n    note: \"Needs: a running server\"\n  git: git\n"
+            ),
+            "{updated}"
+        );
+        let updated = set("scripts.lint", "cargo clippy").expect("add script");
+        assert!(
+            updated.ends_with("    timeout: 30\n  lint: cargo clippy\n"),
+            "{updated}"
+        );
+    }
+
+    #[test]
+    fn structural_and_ambiguous_edits_are_rejected() {
+        for (key, expected) in [
+            ("install.postgres", "can't change safely"),
+            ("install.git.version", "structural change"),
+            ("install.node.version", "isn't defined"),
+            ("python.version", "only handles keys under"),
+            ("install", "whole section"),
+            ("install..version", "dotted key"),
+        ] {
+            let err = set(key, "1").expect_err(key).to_string();
+            assert!(err.contains(expected), "{key}: {err}");
+        }
+
+        let config = "install:\n  Node: nodejs\n  NODE: node\n";
+        let err = set_value(config, Path::new("qbit.yml"), "install.node", "x")
+            .expect_err("ambiguous key")
+            .to_string();
+        assert!(err.contains("ambiguous"), "{err}");
+    }
+}
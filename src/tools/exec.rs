@@ -0,0 +1,65 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+
+use crate::config::LoadedProjectConfig;
+use crate::utils::python::venv_env;
+use crate::utils::shell;
+
+/// Run an arbitrary command from the project root with the project's venv
+/// (if any) activated on PATH.
+pub fn exec(config: Option<&LoadedProjectConfig>, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        bail!("No command given. Usage: `qbit exec -- <command> [args...]`.");
+    }
+
+    let root = project_root(config)?;
+    let envs = project_env(&root)?;
+    shell::run_shell_in(&shell::join_args(args), &root, &envs)
+}
+
+/// The directory holding qbit.yml/qbit.toml, or the current directory when
+/// there is no config.
+fn project_root(config: Option<&LoadedProjectConfig>) -> Result<PathBuf> {
+    if let Some(cfg) = config {
+        if let Some(parent) = cfg.path.parent() {
+            return Ok(parent.to_path_buf());
+        }
+    }
+    std::env::current_dir().context("resolving current directory")
+}
+
+fn project_env(root: &Path) -> Result<Vec<(&'static str, OsString)>> {
+    let venv = root.join("venv");
+    if !venv.is_dir() {
+        return Ok(Vec::new());
+    }
+    venv_env(&venv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::python::venv_bin_dir;
+    use tempfile::tempdir;
+
+    #[test]
+    fn project_env_is_empty_without_venv() {
+        let tmp = tempdir().expect("tempdir");
+        assert!(project_env(tmp.path()).expect("env").is_empty());
+    }
+
+    #[test]
+    fn project_env_prepends_venv_bin_to_path() {
+        let tmp = tempdir().expect("tempdir");
+        std::fs::create_dir_all(tmp.path().join("venv")).expect("venv dir");
+        let envs = project_env(tmp.path()).expect("env");
+        let (_, path) = envs
+            .iter()
+            .find(|(key, _)| *key == "PATH")
+            .expect("PATH set");
+        let first = std::env::split_paths(path).next().expect("first entry");
+        assert_eq!(first, venv_bin_dir(&tmp.path().join("venv")));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+
+use crate::developers::common::{Ecosystem, ProjectContext};
+use crate::developers::{dart, js, py};
+
+/// Project kinds `qbit init` knows how to scaffold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProjectType {
+    Js,
+    Py,
+    Dart,
+    Go,
+}
+
+impl ProjectType {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Js => "js",
+            Self::Py => "py",
+            Self::Dart => "dart",
+            Self::Go => "go",
+        }
+    }
+
+    fn parse(input: &str) -> Option<Self> {
+        Self::from_str(input.trim(), true).ok()
+    }
+
+    /// Starter `qbit.yml` tailored to the project type.
+    fn starter_config(self) -> &'static str {
+        match self {
+            Self::Js => {
+                r#"scripts:
+  start: "qbit js run start"
+  build: "qbit js run build"
+"#
+            }
+            Self::Py => {
+                r#"scripts:
+  setup: "qbit py sync"
+  test: "qbit exec python -m pytest"
+"#
+            }
+            Self::Dart => {
+                r#"scripts:
+  get: "dart pub get"
+  start: "dart run"
+  test: "dart test"
+"#
+            }
+            Self::Go => {
+                r#"scripts:
+  build: "go build ./..."
+  start: "go run ."
+  test: "go test ./..."
+"#
+            }
+        }
+    }
+}
+
+/// Guess the project type from marker files in `dir`.
+pub fn detect_project_type(dir: &Path) -> Option<ProjectType> {
+    ProjectContext::new(dir)
+        .detect_project_types()
+        .into_iter()
+        .find_map(|ecosystem| match ecosystem {
+            Ecosystem::Js => Some(ProjectType::Js),
+            Ecosystem::Python => Some(ProjectType::Py),
+            Ecosystem::Dart => Some(ProjectType::Dart),
+            Ecosystem::Go => Some(ProjectType::Go),
+            Ecosystem::Ruby
+            | Ecosystem::Php
+            | Ecosystem::Java
+            | Ecosystem::Dotnet
+            | Ecosystem::Rust => None,
+        })
+}
+
+/// Scaffold a qbit project in the current directory: pick the project type
+/// (explicit, detected, or prompted), write a starter `qbit.yml`, then run the
+/// language-specific `init`.
+pub fn init_project(requested: Option<ProjectType>, non_interactive: bool) -> Result<()> {
+    let cwd = std::env::current_dir().context("resolving current directory")?;
+    let detected = detect_project_type(&cwd);
+    let kind = match (requested, detected) {
+        (Some(kind), _) => kind,
+        (None, Some(kind)) => {
+            println!("Detected {} project.", kind.name());
+            kind
+        }
+        (None, None) if non_interactive || !io::stdin().is_terminal() => {
+            bail!("Could not detect the project type. Pass `--type <js|py|dart|go>` to choose one.")
+        }
+        (None, None) => prompt_project_type()?,
+    };
+
+    write_starter_config(&cwd, kind)?;
+
+    match kind {
+        ProjectType::Js => js::init(),
+        ProjectType::Py => py::init(),
+        ProjectType::Dart => dart::init(),
+        ProjectType::Go => {
+            if !cwd.join("go.mod").exists() {
+                println!("No go.mod found. Run `go mod init <module>` to create one.");
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_starter_config(dir: &Path, kind: ProjectType) -> Result<()> {
+    if ["qbit.yml", "qbit.yaml", "qbit.toml"]
+        .iter()
+        .any(|name| dir.join(name).exists())
+    {
+        println!("qbit config already exists; leaving it unchanged.");
+        return Ok(());
+    }
+    fs::write(dir.join("qbit.yml"), kind.starter_config()).context("writing qbit.yml")?;
+    println!("Created qbit.yml for a {} project", kind.name());
+    Ok(())
+}
+
+fn prompt_project_type() -> Result<ProjectType> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("Project type [js/py/dart/go]: ");
+        io::stdout().flush().context("flushing prompt")?;
+        let Some(line) = lines.next() else {
+            bail!("No project type chosen. Pass `--type <js|py|dart|go>` to choose one.");
+        };
+        let line = line.context("reading project type")?;
+        match ProjectType::parse(&line) {
+            Some(kind) => return Ok(kind),
+            None => println!("Unknown project type `{}`.", line.trim()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_project_type_from_marker_files() {
+        let tmp = tempdir().expect("tempdir");
+        assert_eq!(detect_project_type(tmp.path()), None);
+
+        fs::write(tmp.path().join("go.mod"), "module example\n").expect("write go.mod");
+        assert_eq!(detect_project_type(tmp.path()), Some(ProjectType::Go));
+
+        fs::write(tmp.path().join("requirements.txt"), "").expect("write requirements.txt");
+        assert_eq!(detect_project_type(tmp.path()), Some(ProjectType::Py));
+
+        fs::write(tmp.path().join("package.json"), "{}").expect("write package.json");
+        assert_eq!(detect_project_type(tmp.path()), Some(ProjectType::Js));
+    }
+
+    #[test]
+    fn parses_prompt_answers_case_insensitively() {
+        assert_eq!(ProjectType::parse(" Dart\n"), Some(ProjectType::Dart));
+        assert_eq!(ProjectType::parse("rust"), None);
+    }
+}
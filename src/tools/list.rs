@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+
+use crate::config::{InstallSpec, LoadedProjectConfig};
+use crate::error::QbitError;
+use crate::utils::output::OutputMode;
+
+#[derive(Debug, Serialize)]
+struct ConfigSummary {
+    config: String,
+    scripts: BTreeMap<String, Vec<String>>,
+    install: BTreeMap<String, InstallSummary>,
+}
+
+#[derive(Debug, Serialize)]
+struct InstallSummary {
+    version: Option<String>,
+    identifiers: BTreeMap<String, String>,
+}
+
+impl From<&InstallSpec> for InstallSummary {
+    fn from(spec: &InstallSpec) -> Self {
+        match spec {
+            InstallSpec::Identifier(identifier) => Self {
+                version: None,
+                identifiers: [("*".to_string(), identifier.clone())]
+                    .into_iter()
+                    .collect(),
+            },
+            InstallSpec::Detailed {
+                version,
+                identifiers,
+                ..
+            } => Self {
+                version: version.clone(),
+                identifiers: identifiers
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// Print the scripts and install targets defined in the project config.
+pub fn list_config(config: Option<&LoadedProjectConfig>, output: OutputMode) -> Result<()> {
+    let Some(cfg) = config else {
+        bail!(QbitError::no_config());
+    };
+
+    let summary = ConfigSummary {
+        config: cfg.path.display().to_string(),
+        scripts: cfg
+            .data
+            .scripts
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.commands()))
+            .collect(),
+        install: cfg
+            .data
+            .install
+            .iter()
+            .map(|(name, spec)| (name.clone(), InstallSummary::from(spec)))
+            .collect(),
+    };
+
+    if output.is_json() {
+        return output.json(&summary);
+    }
+
+    println!("Config: {}", summary.config);
+    println!("Scripts:");
+    if summary.scripts.is_empty() {
+        println!("  (none)");
+    }
+    for (name, commands) in &summary.scripts {
+        println!("  {name}: {}", commands.join(" && "));
+    }
+    println!("Install targets:");
+    if summary.install.is_empty() {
+        println!("  (none)");
+    }
+    for (name, entry) in &summary.install {
+        match entry.version.as_deref() {
+            Some(version) => println!("  {name} (version {version})"),
+            None => println!("  {name}"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_summary_marks_global_identifier() {
+        let summary = InstallSummary::from(&InstallSpec::Identifier("OpenJS.NodeJS".to_string()));
+        assert_eq!(summary.version, None);
+        assert_eq!(
+            summary.identifiers.get("*").map(String::as_str),
+            Some("OpenJS.NodeJS")
+        );
+    }
+}
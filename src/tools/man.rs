@@ -0,0 +1,27 @@
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// Render roff man pages for `cmd`. Without `out_dir` the top-level page goes
+/// to stdout; with it, one page per (visible) subcommand is written there too,
+/// e.g. `qbit.1`, `qbit-install.1`.
+pub fn write_man_pages(cmd: clap::Command, out_dir: Option<&Path>) -> Result<()> {
+    match out_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("creating man page directory {}", dir.display()))?;
+            clap_mangen::generate_to(cmd, dir)
+                .with_context(|| format!("writing man pages to {}", dir.display()))?;
+            println!("Man pages written to {}", dir.display());
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            clap_mangen::Man::new(cmd)
+                .render(&mut stdout)
+                .context("rendering man page")?;
+            stdout.flush().context("flushing man page")?;
+        }
+    }
+    Ok(())
+}
@@ -1 +1,9 @@
+pub mod clean;
+pub mod config_edit;
+pub mod exec;
+pub mod init;
+pub mod list;
+pub mod man;
 pub mod runner;
+pub mod version;
+pub mod watch;
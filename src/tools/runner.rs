@@ -1,18 +1,122 @@
 use anyhow::{Result, bail};
 
-use crate::config::load_project_config;
-use crate::utils::shell;
+use crate::config::LoadedProjectConfig;
+use crate::developers::js;
+use crate::error::QbitError;
+use crate::utils::glob::glob_matches;
+use crate::utils::output::OutputMode;
+use crate::utils::shell::{self, RunOptions};
 
-pub fn run_named_script(name: &str) -> Result<()> {
-    let Some(cfg) = load_project_config()? else {
-        bail!("No qbit.yml/qbit.toml file found in the current directory.");
+/// Script run by a bare `qbit run`, like a Makefile's default target.
+pub const DEFAULT_SCRIPT: &str = "default";
+
+pub fn has_default_script(config: Option<&LoadedProjectConfig>) -> bool {
+    config.is_some_and(|cfg| cfg.script(DEFAULT_SCRIPT).is_some())
+}
+
+/// Run the script `name`, or every script matching it when it's a glob.
+/// Without a qbit config, a script defined in package.json runs through the
+/// JS package manager instead.
+pub fn run_named_script(
+    config: Option<&LoadedProjectConfig>,
+    name: &str,
+    output: OutputMode,
+    options: RunOptions,
+) -> Result<()> {
+    let Some(cfg) = config else {
+        if let Some((pm, step)) = js::package_script_step(name)? {
+            output.info(format!(
+                "No qbit.yml/qbit.toml found; running package.json script `{name}` via {pm}."
+            ));
+            shell::run_commands(&format!("package.json:{name}"), &[step], output, options)?;
+            return Ok(());
+        }
+        bail!(QbitError::no_config());
     };
+    cfg.validate()?;
 
-    let Some(entry) = cfg.script(name) else {
-        bail!("Script `{}` not found in {}", name, cfg.path.display());
+    let Some(steps) = cfg.script_steps(name) else {
+        if is_glob(name) {
+            return run_matching_scripts(cfg, name, output, options);
+        }
+        bail!(QbitError::ScriptNotFound {
+            name: name.to_string(),
+            path: cfg.path.clone(),
+        });
     };
 
-    let commands = entry.commands();
-    shell::run_commands(&format!("script:{name}"), &commands)?;
+    shell::run_commands(&format!("script:{name}"), &steps, output, options)?;
     Ok(())
 }
+
+/// Run every script whose name matches the glob `pattern`, in sorted order.
+fn run_matching_scripts(
+    cfg: &LoadedProjectConfig,
+    pattern: &str,
+    output: OutputMode,
+    options: RunOptions,
+) -> Result<()> {
+    let mut matches: Vec<&String> = cfg
+        .data
+        .scripts
+        .keys()
+        .filter(|name| glob_matches(pattern, name))
+        .collect();
+    if matches.is_empty() {
+        bail!(QbitError::ScriptNotFound {
+            name: pattern.to_string(),
+            path: cfg.path.clone(),
+        });
+    }
+    matches.sort();
+    for name in matches {
+        run_named_script(Some(cfg), name, output, options)?;
+    }
+    Ok(())
+}
+
+fn is_glob(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::config::load_project_config_from_dir;
+
+    #[test]
+    fn missing_config_and_script_are_typed_errors() {
+        let err = run_named_script(None, "build", OutputMode::Human, RunOptions::default())
+            .expect_err("no config");
+        assert!(matches!(
+            err.downcast_ref::<QbitError>(),
+            Some(QbitError::NoConfig { .. })
+        ));
+
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("qbit.yml"), "scripts:\n  build: echo hi\n").expect("write");
+        let config = load_project_config_from_dir(tmp.path())
+            .expect("load")
+            .expect("config");
+        for name in ["deploy", "test:*"] {
+            let err = run_named_script(
+                Some(&config),
+                name,
+                OutputMode::Human,
+                RunOptions::default(),
+            )
+            .expect_err("undefined script");
+            assert!(
+                matches!(
+                    err.downcast_ref::<QbitError>(),
+                    Some(QbitError::ScriptNotFound { name: missing, .. }) if missing == name
+                ),
+                "{err:#}"
+            );
+        }
+    }
+}
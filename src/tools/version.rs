@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::os::upgrade::parse_version;
+use crate::utils::output::OutputMode;
+
+/// Build metadata reported by `qbit version`.
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    version: String,
+    os: &'static str,
+    arch: &'static str,
+    commit: Option<&'static str>,
+}
+
+impl VersionInfo {
+    fn current() -> Result<Self> {
+        let version = parse_version(env!("CARGO_PKG_VERSION"))
+            .context("parsing current qbit version from build metadata")?;
+        Ok(Self {
+            version: version.to_string(),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            commit: option_env!("QBIT_GIT_HASH"),
+        })
+    }
+
+    fn render(&self) -> String {
+        let mut line = format!("qbit {} ({}-{}", self.version, self.os, self.arch);
+        if let Some(commit) = self.commit {
+            line.push_str(&format!(", commit {commit}"));
+        }
+        line.push(')');
+        line
+    }
+}
+
+/// Print the qbit version, target platform, and (when known) git commit.
+pub fn print_version(output: OutputMode) -> Result<()> {
+    let info = VersionInfo::current()?;
+    if output.is_json() {
+        return output.json(&info);
+    }
+    println!("{}", info.render());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_platform_and_optional_commit() {
+        let mut info = VersionInfo {
+            version: "1.2.3".to_string(),
+            os: "linux",
+            arch: "x86_64",
+            commit: None,
+        };
+        assert_eq!(info.render(), "qbit 1.2.3 (linux-x86_64)");
+
+        info.commit = Some("abc1234");
+        assert_eq!(info.render(), "qbit 1.2.3 (linux-x86_64, commit abc1234)");
+    }
+}
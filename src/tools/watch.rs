@@ -0,0 +1,220 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::config::LoadedProjectConfig;
+use crate::error::QbitError;
+use crate::tools::runner::run_named_script;
+use crate::utils::glob::IgnoreFilter;
+use crate::utils::interrupt;
+use crate::utils::output::OutputMode;
+use crate::utils::shell::RunOptions;
+
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(300);
+const IGNORED_DIRS: &[&str] = &["target", ".git", "node_modules", "venv"];
+
+/// Trailing-edge debounce: a burst of file events fires once, after no new
+/// event has arrived for `window`.
+#[derive(Debug)]
+struct Debouncer {
+    window: Duration,
+    last_event: Option<Instant>,
+}
+
+impl Debouncer {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_event: None,
+        }
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.last_event = Some(now);
+    }
+
+    /// How long to wait for more events before firing, or `None` when idle.
+    fn time_left(&self, now: Instant) -> Option<Duration> {
+        self.last_event
+            .map(|last| self.window.saturating_sub(now.duration_since(last)))
+    }
+
+    /// True (once) when the quiet period after the last event has elapsed.
+    fn fire(&mut self, now: Instant) -> bool {
+        if self.time_left(now) == Some(Duration::ZERO) {
+            self.last_event = None;
+            return true;
+        }
+        false
+    }
+}
+
+/// Run `name` now and again whenever project files change, until Ctrl-C.
+/// Failures are reported but don't stop the watch. Every re-run uses the
+/// config loaded when the command started; restart the watch after editing
+/// qbit.yml.
+pub fn watch_script(
+    config: Option<&LoadedProjectConfig>,
+    name: &str,
+    output: OutputMode,
+    options: RunOptions,
+) -> Result<()> {
+    let Some(cfg) = config else {
+        bail!(QbitError::no_config());
+    };
+    let root = cfg
+        .path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .canonicalize()
+        .context("resolving project root for --watch")?;
+
+    let (tx, rx) = mpsc::channel::<PathBuf>();
+    let watch_root = root.clone();
+    let mut ignore = IgnoreFilter::new(&root);
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            for path in forwarded_paths(event, &watch_root, &mut ignore) {
+                let _ = tx.send(path);
+            }
+        }
+    })
+    .context("starting file watcher")?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("watching {}", root.display()))?;
+
+    report_run(run_named_script(Some(cfg), name, output, options))?;
+    output.info(format!(
+        "Watching {} for changes (Ctrl-C to stop)...",
+        root.display()
+    ));
+
+    let mut debouncer = Debouncer::new(DEBOUNCE_WINDOW);
+    let mut changed: Option<PathBuf> = None;
+    loop {
+        let received = match debouncer.time_left(Instant::now()) {
+            Some(left) => rx.recv_timeout(left),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(path) => {
+                debouncer.record(Instant::now());
+                changed = Some(path);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => bail!("file watcher stopped unexpectedly"),
+        }
+
+        if debouncer.fire(Instant::now()) {
+            if let Some(path) = changed.take() {
+                let shown = path.strip_prefix(&root).unwrap_or(&path);
+                output.info(format!(
+                    "\nChange detected in {}; re-running `{name}`",
+                    shown.display()
+                ));
+            }
+            report_run(run_named_script(Some(cfg), name, output, options))?;
+        }
+    }
+}
+
+/// Print a failed run and keep watching; Ctrl-C during a run ends the watch.
+fn report_run(result: Result<()>) -> Result<()> {
+    if let Err(e) = result {
+        if interrupt::interrupted() {
+            return Err(e);
+        }
+        eprintln!("error (run): {e:#}");
+    }
+    Ok(())
+}
+
+/// Paths from `event` that should trigger a re-run: changes outside the
+/// built-in excludes and the project's `.qbitignore`.
+fn forwarded_paths(event: Event, root: &Path, ignore: &mut IgnoreFilter) -> Vec<PathBuf> {
+    if !matches!(
+        event.kind,
+        EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)
+    ) {
+        return Vec::new();
+    }
+    event
+        .paths
+        .into_iter()
+        .filter(|path| !is_ignored_path(path, root) && !ignore.should_ignore(path))
+        .collect()
+}
+
+/// Changes under build output, VCS metadata, dependencies, or the venv never
+/// trigger a re-run.
+fn is_ignored_path(path: &Path, root: &Path) -> bool {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::glob::IGNORE_FILE;
+
+    #[test]
+    fn debouncer_fires_once_after_quiet_window() {
+        let window = Duration::from_millis(300);
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(window);
+        assert_eq!(debouncer.time_left(start), None);
+        assert!(!debouncer.fire(start));
+
+        debouncer.record(start);
+        debouncer.record(start + Duration::from_millis(200));
+        assert!(!debouncer.fire(start + Duration::from_millis(400)));
+        assert_eq!(
+            debouncer.time_left(start + Duration::from_millis(400)),
+            Some(Duration::from_millis(100))
+        );
+
+        assert!(debouncer.fire(start + Duration::from_millis(500)));
+        assert!(!debouncer.fire(start + Duration::from_millis(900)));
+    }
+
+    #[test]
+    fn ignores_build_vcs_dependency_and_venv_dirs() {
+        let root = Path::new("/project");
+        for ignored in [
+            "/project/target/debug/app",
+            "/project/.git/index",
+            "/project/web/node_modules/x/index.js",
+            "/project/venv/bin/python",
+        ] {
+            assert!(is_ignored_path(Path::new(ignored), root), "{ignored}");
+        }
+        assert!(!is_ignored_path(Path::new("/project/src/main.rs"), root));
+        assert!(!is_ignored_path(Path::new("/project/qbit.yml"), root));
+    }
+
+    #[test]
+    fn events_under_qbitignore_patterns_are_not_forwarded() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = tmp.path();
+        std::fs::write(root.join(IGNORE_FILE), "coverage/\n*.tmp\n").expect("write .qbitignore");
+        let mut ignore = IgnoreFilter::new(root);
+        let event = |path: PathBuf| {
+            Event::new(EventKind::Create(notify::event::CreateKind::File)).add_path(path)
+        };
+
+        let ignored = forwarded_paths(event(root.join("coverage/lcov.info")), root, &mut ignore);
+        assert!(ignored.is_empty(), "{ignored:?}");
+        assert!(forwarded_paths(event(root.join("notes.tmp")), root, &mut ignore).is_empty());
+
+        let source = root.join("src/main.rs");
+        assert_eq!(
+            forwarded_paths(event(source.clone()), root, &mut ignore),
+            vec![source]
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+
+use anyhow::{Result, bail};
+
+/// Replace the subcommand token of `args` (the first argument after the
+/// program name that isn't a flag) with its alias expansion, repeatedly, so
+/// `up: "b --verbose"` and `b: "run build"` chain. Built-in subcommands are
+/// never shadowed, and only that first token is ever expanded.
+pub fn expand_aliases(
+    args: Vec<OsString>,
+    aliases: &HashMap<String, String>,
+    builtins: &[String],
+) -> Result<Vec<OsString>> {
+    let Some(index) = subcommand_index(&args) else {
+        return Ok(args);
+    };
+
+    let mut args = args;
+    let mut chain: Vec<String> = Vec::new();
+    loop {
+        let Some(token) = args[index].to_str().map(str::to_string) else {
+            return Ok(args);
+        };
+        if builtins.contains(&token) {
+            return Ok(args);
+        }
+        let Some(expansion) = aliases.get(&token) else {
+            return Ok(args);
+        };
+        if chain.contains(&token) {
+            chain.push(token);
+            bail!("Alias loop detected: {}", chain.join(" -> "));
+        }
+
+        let replacement: Vec<OsString> = expansion.split_whitespace().map(OsString::from).collect();
+        if replacement.is_empty() {
+            bail!("Alias `{token}` expands to nothing; give it a command such as `install`.");
+        }
+        if replacement[0]
+            .to_str()
+            .is_some_and(|first| first.starts_with('-'))
+        {
+            bail!("Alias `{token}` must start with a subcommand, not the flag `{expansion}`.");
+        }
+        chain.push(token);
+        args.splice(index..=index, replacement);
+    }
+}
+
+/// Position of the subcommand token, skipping the program name and any
+/// global flags in front of it.
+pub fn subcommand_index(args: &[OsString]) -> Option<usize> {
+    args.iter()
+        .enumerate()
+        .skip(1)
+        .find(|(_, arg)| !arg.to_string_lossy().starts_with('-'))
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<OsString> {
+        raw.iter().map(OsString::from).collect()
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+            .collect()
+    }
+
+    fn builtins() -> Vec<String> {
+        vec!["install".to_string(), "run".to_string()]
+    }
+
+    #[test]
+    fn expands_only_the_subcommand_token() {
+        let expanded = expand_aliases(
+            args(&["qbit", "--json", "up", "up", "--fast"]),
+            &aliases(&[("up", "run build")]),
+            &builtins(),
+        )
+        .expect("expand");
+        assert_eq!(
+            expanded,
+            args(&["qbit", "--json", "run", "build", "up", "--fast"])
+        );
+    }
+
+    #[test]
+    fn follows_alias_chains_but_never_shadows_builtins() {
+        let table = aliases(&[("i", "inst"), ("inst", "install"), ("install", "run x")]);
+        let expanded =
+            expand_aliases(args(&["qbit", "i", "foo"]), &table, &builtins()).expect("expand");
+        assert_eq!(expanded, args(&["qbit", "install", "foo"]));
+    }
+
+    #[test]
+    fn reports_alias_loops() {
+        let table = aliases(&[("a", "b"), ("b", "c --x"), ("c", "a")]);
+        let err = expand_aliases(args(&["qbit", "a"]), &table, &builtins()).unwrap_err();
+        assert_eq!(err.to_string(), "Alias loop detected: a -> b -> c -> a");
+    }
+
+    #[test]
+    fn rejects_empty_or_flag_expansions() {
+        assert!(
+            expand_aliases(args(&["qbit", "e"]), &aliases(&[("e", "  ")]), &builtins()).is_err()
+        );
+        assert!(
+            expand_aliases(
+                args(&["qbit", "f"]),
+                &aliases(&[("f", "--json")]),
+                &builtins()
+            )
+            .is_err()
+        );
+        let untouched =
+            expand_aliases(args(&["qbit", "--json"]), &aliases(&[]), &builtins()).expect("expand");
+        assert_eq!(untouched, args(&["qbit", "--json"]));
+    }
+}
@@ -0,0 +1,175 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{Error, Result, anyhow};
+
+use crate::utils::interrupt;
+
+/// Run `action` for every item. Without `keep_going` the first error is
+/// returned unchanged (so child exit codes still propagate); with it, every
+/// item runs and a summary of the failures is printed before erroring.
+pub fn for_each_item<F>(items: &[String], kind: &str, keep_going: bool, mut action: F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<()>,
+{
+    let mut failures = Vec::new();
+    for item in items {
+        match action(item) {
+            Ok(()) => {}
+            Err(e) if !keep_going || interrupt::interrupted() => return Err(e),
+            Err(e) => failures.push((item.as_str(), e)),
+        }
+    }
+    summarize(items, kind, failures)
+}
+
+/// [`for_each_item`] with up to `jobs` items in flight on worker threads.
+/// Without `keep_going` no new item starts after a failure, and the earliest
+/// failing item's error is returned.
+pub fn for_each_item_parallel<F>(
+    items: &[String],
+    kind: &str,
+    keep_going: bool,
+    jobs: usize,
+    action: F,
+) -> Result<()>
+where
+    F: Fn(&str) -> Result<()> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let failed: Mutex<Vec<(usize, Error)>> = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            scope.spawn(|| {
+                while !stop.load(Ordering::SeqCst) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    if let Err(e) = action(item) {
+                        if !keep_going || interrupt::interrupted() {
+                            stop.store(true, Ordering::SeqCst);
+                        }
+                        failed
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .push((index, e));
+                    }
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.into_inner().unwrap_or_else(PoisonError::into_inner);
+    failed.sort_by_key(|(index, _)| *index);
+    let failures: Vec<(&str, Error)> = failed
+        .into_iter()
+        .map(|(index, e)| (items[index].as_str(), e))
+        .collect();
+    if !keep_going {
+        return match failures.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(()),
+        };
+    }
+    summarize(items, kind, failures)
+}
+
+fn summarize(items: &[String], kind: &str, failures: Vec<(&str, Error)>) -> Result<()> {
+    if failures.is_empty() {
+        return Ok(());
+    }
+    eprintln!("{}", failure_summary(&failures, items.len()));
+    let names: Vec<&str> = failures.iter().map(|(item, _)| *item).collect();
+    Err(anyhow!(
+        "{} of {} {kind}s failed: {}",
+        failures.len(),
+        items.len(),
+        names.join(", ")
+    ))
+}
+
+fn failure_summary(failures: &[(&str, Error)], total: usize) -> String {
+    let mut summary = format!("\nSummary: {} of {total} failed", failures.len());
+    for (item, error) in failures {
+        summary.push_str(&format!("\n  - {item}: {error:#}"));
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn keep_going_runs_everything_and_lists_only_failures() {
+        let mut ran = Vec::new();
+        let err = for_each_item(&items(&["a", "b", "c"]), "script", true, |item| {
+            ran.push(item.to_string());
+            if item == "b" {
+                Ok(())
+            } else {
+                Err(anyhow!("{item} broke"))
+            }
+        })
+        .expect_err("two items fail");
+
+        assert_eq!(ran, items(&["a", "b", "c"]));
+        assert_eq!(err.to_string(), "2 of 3 scripts failed: a, c");
+    }
+
+    #[test]
+    fn without_keep_going_stops_at_first_failure() {
+        let mut ran = Vec::new();
+        let err = for_each_item(&items(&["a", "b"]), "script", false, |item| {
+            ran.push(item.to_string());
+            Err(anyhow!("{item} broke"))
+        })
+        .expect_err("first item fails");
+
+        assert_eq!(ran, items(&["a"]));
+        assert_eq!(err.to_string(), "a broke");
+    }
+
+    #[test]
+    fn parallel_runs_items_concurrently_and_summarizes_in_order() {
+        let barrier = std::sync::Barrier::new(2);
+        let err = for_each_item_parallel(&items(&["a", "b", "c"]), "target", true, 2, |item| {
+            if item != "c" {
+                // Deadlocks unless `a` and `b` run at the same time.
+                barrier.wait();
+            }
+            if item == "a" {
+                Ok(())
+            } else {
+                Err(anyhow!("{item} broke"))
+            }
+        })
+        .expect_err("two items fail");
+        assert_eq!(err.to_string(), "2 of 3 targets failed: b, c");
+    }
+
+    #[test]
+    fn parallel_without_keep_going_returns_first_failure() {
+        let err = for_each_item_parallel(&items(&["a", "b"]), "target", false, 1, |item| {
+            Err(anyhow!("{item} broke"))
+        })
+        .expect_err("first item fails");
+        assert_eq!(err.to_string(), "a broke");
+    }
+
+    #[test]
+    fn summary_names_each_failure_with_its_error() {
+        let failures = [("a", anyhow!("exit 1")), ("c", anyhow!("exit 2"))];
+        let summary = failure_summary(&failures, 3);
+        assert_eq!(
+            summary,
+            "\nSummary: 2 of 3 failed\n  - a: exit 1\n  - c: exit 2"
+        );
+    }
+}
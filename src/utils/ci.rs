@@ -0,0 +1,68 @@
+use std::env;
+
+use crate::utils::net::is_truthy;
+
+/// Variables CI services set on every job. `CI` covers most of them; the
+/// rest catch services that don't set it.
+const CI_VARS: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "TF_BUILD",
+    "JENKINS_URL",
+    "TEAMCITY_VERSION",
+];
+
+/// True when running under CI. `QBIT_CI=1`/`QBIT_CI=0` forces the answer
+/// either way; otherwise any of [`CI_VARS`] set to something other than an
+/// explicit false value (`0`, `false`, `no`, `off`) counts.
+pub fn is_ci() -> bool {
+    is_ci_with(|key| env::var(key).ok())
+}
+
+fn is_ci_with(var: impl Fn(&str) -> Option<String>) -> bool {
+    if let Some(forced) = var("QBIT_CI").filter(|value| !value.trim().is_empty()) {
+        return is_truthy(&forced);
+    }
+    CI_VARS.iter().any(|key| {
+        var(key).is_some_and(|value| {
+            let value = value.trim().to_ascii_lowercase();
+            !value.is_empty() && !matches!(value.as_str(), "0" | "false" | "no" | "off")
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn detect(vars: &[(&str, &str)]) -> bool {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        is_ci_with(|key| vars.get(key).cloned())
+    }
+
+    #[test]
+    fn detects_common_ci_services() {
+        assert!(detect(&[("CI", "true")]));
+        assert!(detect(&[("GITHUB_ACTIONS", "true")]));
+        assert!(detect(&[("JENKINS_URL", "https://ci.example.com")]));
+        assert!(!detect(&[]));
+        assert!(!detect(&[("CI", "false")]));
+        assert!(!detect(&[("CI", "")]));
+    }
+
+    #[test]
+    fn qbit_ci_overrides_detection() {
+        assert!(!detect(&[("CI", "true"), ("QBIT_CI", "0")]));
+        assert!(detect(&[("QBIT_CI", "1")]));
+        assert!(detect(&[("CI", "1"), ("QBIT_CI", " ")]));
+    }
+}
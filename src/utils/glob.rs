@@ -0,0 +1,226 @@
+//! Glob matching for script names and `.qbitignore` patterns. Self-contained,
+//! because the `qbit-dev` binary includes this file directly.
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Per-project file of gitignore-style patterns the file watchers skip.
+pub const IGNORE_FILE: &str = ".qbitignore";
+
+/// Minimal glob: `*` matches any run of characters, `?` exactly one.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    t = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// One `.qbitignore` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnorePattern {
+    /// Pattern split on `/`; a `**` segment spans any number of directories.
+    segments: Vec<String>,
+    /// `!pattern` re-includes what an earlier line ignored.
+    negated: bool,
+    /// `pattern/` only matches directories.
+    dir_only: bool,
+    /// Patterns containing a `/` match from the root; others match any name.
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            segments: line.split('/').map(str::to_string).collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the pattern covers `components` (a path relative to the root)
+    /// or one of the directories above it.
+    fn matches(&self, components: &[String], is_dir: bool) -> bool {
+        let covers = |len: usize| {
+            let hit_is_dir = len < components.len() || is_dir;
+            hit_is_dir || !self.dir_only
+        };
+        if !self.anchored {
+            return components
+                .iter()
+                .enumerate()
+                .any(|(index, name)| glob_matches(&self.segments[0], name) && covers(index + 1));
+        }
+        (1..=components.len())
+            .any(|len| segments_match(&self.segments, &components[..len]) && covers(len))
+    }
+}
+
+fn segments_match(segments: &[String], components: &[String]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=components.len()).any(|skip| segments_match(rest, &components[skip..]))
+        }
+        Some((first, rest)) => components
+            .split_first()
+            .is_some_and(|(name, tail)| glob_matches(first, name) && segments_match(rest, tail)),
+    }
+}
+
+/// Parsed `.qbitignore`. Later lines win, so `!keep.log` after `*.log`
+/// re-includes that file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreRules {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            patterns: content.lines().filter_map(IgnorePattern::parse).collect(),
+        }
+    }
+
+    /// Rules from `root/.qbitignore`; none when the file is missing or unreadable.
+    pub fn load(root: &Path) -> Self {
+        fs::read_to_string(root.join(IGNORE_FILE))
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    /// Whether `rel` (relative to the project root) is ignored.
+    pub fn is_ignored(&self, rel: &Path, is_dir: bool) -> bool {
+        let components: Vec<String> = rel
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        if components.is_empty() {
+            return false;
+        }
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(&components, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+}
+
+/// `.qbitignore` as seen by a file watcher: drops ignored paths and reloads
+/// the rules whenever the ignore file itself changes.
+#[derive(Debug, Clone)]
+pub struct IgnoreFilter {
+    root: PathBuf,
+    rules: IgnoreRules,
+}
+
+impl IgnoreFilter {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            rules: IgnoreRules::load(root),
+        }
+    }
+
+    /// True when a change at `path` shouldn't be acted on. A change to the
+    /// ignore file reloads the rules and is itself dropped.
+    pub fn should_ignore(&mut self, path: &Path) -> bool {
+        let rel = path.strip_prefix(&self.root).unwrap_or(path);
+        if rel == Path::new(IGNORE_FILE) {
+            self.rules = IgnoreRules::load(&self.root);
+            return true;
+        }
+        self.rules.is_ignored(rel, path.is_dir())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_star_and_question_mark() {
+        assert!(glob_matches("test:*", "test:unit"));
+        assert!(glob_matches("test:*", "test:"));
+        assert!(glob_matches("*:unit", "test:unit"));
+        assert!(glob_matches("t?st:*n", "test:integration"));
+        assert!(!glob_matches("test:*", "lint"));
+        assert!(!glob_matches("test:?", "test:unit"));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+        assert!(!glob_matches("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn ignore_rules_follow_gitignore_conventions() {
+        let rules = IgnoreRules::parse(
+            "# build output\n*.log\n!keep.log\ndist/\n/docs/generated\nassets/**/*.png\n",
+        );
+        let ignored = |path: &str, is_dir: bool| rules.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("server.log", false));
+        assert!(ignored("logs/deep/trace.log", false));
+        assert!(!ignored("keep.log", false));
+        assert!(ignored("web/dist/app.js", false));
+        assert!(ignored("dist", true));
+        assert!(!ignored("dist", false));
+        assert!(ignored("docs/generated/index.html", false));
+        assert!(!ignored("src/docs/generated/index.html", false));
+        assert!(ignored("assets/icons/small/a.png", false));
+        assert!(ignored("assets/a.png", false));
+        assert!(!ignored("assets/a.svg", false));
+        assert!(!ignored("src/main.rs", false));
+    }
+
+    #[test]
+    fn filter_reloads_rules_when_ignore_file_changes() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let root = tmp.path();
+        let mut filter = IgnoreFilter::new(root);
+        assert!(!filter.should_ignore(&root.join("out/app.js")));
+
+        fs::write(root.join(IGNORE_FILE), "out/\n").expect("write .qbitignore");
+        assert!(filter.should_ignore(&root.join(IGNORE_FILE)));
+        assert!(filter.should_ignore(&root.join("out/app.js")));
+    }
+}
@@ -0,0 +1,329 @@
+//! Ctrl-C handling while qbit waits on a child process: the child is stopped
+//! (SIGTERM to its process group on Unix, `taskkill /T` on Windows) instead
+//! of being left orphaned, the wait returns, and `cli::run` exits with
+//! [`EXIT_INTERRUPTED`] after the usual cleanup (temp scripts removed, the
+//! `--output` log flushed). A second Ctrl-C kills children that ignored the
+//! first. The handler is only installed while a child is tracked.
+//!
+//! It is written against libc and windows-sys directly because the `ctrlc`
+//! crate isn't among qbit's vendored dependencies.
+
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+/// Exit code after Ctrl-C: 128 + SIGINT, as shells report it.
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+static ACTIVE: ChildRegistry = ChildRegistry::new();
+
+/// Set by the first Ctrl-C that arrives while a child is tracked.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C stopped a child. Commands stop at the next step and the
+/// process exits with [`EXIT_INTERRUPTED`].
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Keeps `child` registered for termination on Ctrl-C until dropped.
+pub struct ActiveChild {
+    pid: u32,
+}
+
+impl ActiveChild {
+    pub fn track(child: &Child) -> Self {
+        let pid = child.id();
+        ACTIVE.add(pid, || platform::set_handler(true));
+        Self { pid }
+    }
+}
+
+impl Drop for ActiveChild {
+    fn drop(&mut self) {
+        ACTIVE.remove(self.pid, || platform::set_handler(false));
+    }
+}
+
+/// Process ids of the children currently being waited on.
+struct ChildRegistry {
+    pids: Mutex<Vec<u32>>,
+}
+
+impl ChildRegistry {
+    const fn new() -> Self {
+        Self {
+            pids: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Track `pid`, running `on_first` (under the lock) when it's the only one.
+    fn add(&self, pid: u32, on_first: impl FnOnce()) {
+        let mut pids = self.pids.lock().unwrap_or_else(PoisonError::into_inner);
+        pids.push(pid);
+        if pids.len() == 1 {
+            on_first();
+        }
+    }
+
+    /// Stop tracking `pid`, running `on_last` (under the lock) once none are left.
+    fn remove(&self, pid: u32, on_last: impl FnOnce()) {
+        let mut pids = self.pids.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(index) = pids.iter().position(|tracked| *tracked == pid) {
+            pids.remove(index);
+            if pids.is_empty() {
+                on_last();
+            }
+        }
+    }
+
+    /// Ask every tracked child and its process group to stop (`force` kills
+    /// them outright); returns how many were signalled.
+    fn terminate_all(&self, force: bool) -> usize {
+        let pids = self
+            .pids
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for pid in &pids {
+            platform::terminate(*pid, force);
+        }
+        pids.len()
+    }
+}
+
+/// Kill `child` and every process it started: its process group on Unix
+/// (when the child leads one), its process tree on Windows.
+pub fn kill_tree(child: &mut Child) {
+    platform::kill_tree(child.id());
+    // Also covers a child without its own group; fails only if it's gone.
+    let _ = child.kill();
+}
+
+/// What Ctrl-C does once the handler is installed. Only the children are
+/// signalled; qbit itself unwinds once their wait returns.
+fn on_interrupt() {
+    let again = INTERRUPTED.swap(true, Ordering::SeqCst);
+    ACTIVE.terminate_all(again);
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::io;
+    use std::sync::OnceLock;
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::thread;
+
+    /// Write end of the pipe the signal handler uses to wake the watcher thread.
+    static WAKE_FD: AtomicI32 = AtomicI32::new(-1);
+
+    /// Signal the process group `pid` leads, or just `pid` when it was
+    /// started in qbit's own group (then Ctrl-C at the terminal has already
+    /// reached the rest of its group).
+    pub fn terminate(pid: u32, force: bool) {
+        let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+        if let Ok(pid) = libc::pid_t::try_from(pid) {
+            // SAFETY: kill() has no memory-safety preconditions. A negative
+            // pid addresses the process group that `pid` leads.
+            if unsafe { libc::kill(-pid, signal) } != 0 {
+                // SAFETY: as above.
+                unsafe { libc::kill(pid, signal) };
+            }
+        }
+    }
+
+    pub fn kill_tree(pid: u32) {
+        if let Ok(pid) = libc::pid_t::try_from(pid) {
+            // SAFETY: kill() has no memory-safety preconditions. A negative
+            // pid addresses the process group that `pid` leads.
+            unsafe { libc::kill(-pid, libc::SIGKILL) };
+        }
+    }
+
+    /// Point SIGINT at [`on_sigint`], or back at the default action. Without
+    /// the watcher thread Ctrl-C keeps its default behavior.
+    pub fn set_handler(enabled: bool) {
+        if enabled && !start_watcher() {
+            return;
+        }
+        let handler = if enabled {
+            on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t
+        } else {
+            libc::SIG_DFL
+        };
+        // SAFETY: the action is fully initialized before it is installed, and
+        // the handler only performs an async-signal-safe write().
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = handler;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(libc::SIGINT, &action, std::ptr::null_mut());
+        }
+    }
+
+    extern "C" fn on_sigint(_signal: libc::c_int) {
+        let fd = WAKE_FD.load(Ordering::SeqCst);
+        if fd >= 0 {
+            // SAFETY: write() is async-signal-safe and the buffer outlives the call.
+            unsafe { libc::write(fd, [1u8].as_ptr().cast(), 1) };
+        }
+    }
+
+    /// Signal handlers can't take locks or exit cleanly, so the real work
+    /// happens on a thread woken through a pipe. Started once.
+    fn start_watcher() -> bool {
+        static STARTED: OnceLock<bool> = OnceLock::new();
+        *STARTED.get_or_init(|| {
+            let mut fds = [0; 2];
+            // SAFETY: `fds` has room for the two descriptors pipe() writes.
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return false;
+            }
+            for fd in fds {
+                // SAFETY: `fd` was just returned by pipe(); children must not inherit it.
+                unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+            }
+            let read_fd = fds[0];
+            WAKE_FD.store(fds[1], Ordering::SeqCst);
+            thread::spawn(move || {
+                loop {
+                    let mut byte = 0u8;
+                    // SAFETY: reads at most one byte into `byte`.
+                    let read = unsafe { libc::read(read_fd, (&raw mut byte).cast(), 1) };
+                    match read {
+                        1 => super::on_interrupt(),
+                        -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                        _ => return,
+                    }
+                }
+            });
+            true
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::process::{Command, Stdio};
+
+    use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+    use windows_sys::Win32::System::Console::{
+        CTRL_BREAK_EVENT, CTRL_C_EVENT, SetConsoleCtrlHandler,
+    };
+
+    /// `/T` takes the shell's own children down with it; `/F` is needed for
+    /// console programs, so every stop is forced.
+    pub fn terminate(pid: u32, _force: bool) {
+        let _ = Command::new("taskkill")
+            .args(["/PID", &pid.to_string(), "/T", "/F"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+
+    pub fn kill_tree(pid: u32) {
+        terminate(pid, true);
+    }
+
+    pub fn set_handler(enabled: bool) {
+        // SAFETY: `on_ctrl` matches PHANDLER_ROUTINE and lives for the whole process.
+        unsafe { SetConsoleCtrlHandler(Some(on_ctrl), if enabled { TRUE } else { FALSE }) };
+    }
+
+    /// Windows runs console handlers on their own thread, so the work can
+    /// happen right here. Returning TRUE keeps the default handler from
+    /// exiting qbit before it has cleaned up.
+    unsafe extern "system" fn on_ctrl(ctrl_type: u32) -> BOOL {
+        if ctrl_type == CTRL_C_EVENT || ctrl_type == CTRL_BREAK_EVENT {
+            super::on_interrupt();
+            return TRUE;
+        }
+        FALSE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn interrupt_terminates_tracked_child() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::Command;
+
+        let registry = ChildRegistry::new();
+        let mut child = Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .expect("spawn sleep");
+        let mut installed = false;
+        registry.add(child.id(), || installed = true);
+        assert!(installed);
+
+        assert_eq!(registry.terminate_all(false), 1);
+        let status = child.wait().expect("wait for child");
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+        let mut restored = false;
+        registry.remove(child.id(), || restored = true);
+        assert!(restored);
+        assert_eq!(registry.terminate_all(false), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn terminate_reaches_processes_the_child_started() {
+        use std::io::Read;
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        // `sleep` shares the shell's stdout, so the pipe only reaches EOF once
+        // both of them are gone.
+        let mut child = Command::new("sh")
+            .args(["-c", "sleep 30 & wait"])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .expect("spawn sh");
+        let mut stdout = child.stdout.take().expect("piped stdout");
+        let registry = ChildRegistry::new();
+        registry.add(child.id(), || {});
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(registry.terminate_all(false), 1);
+        child.wait().expect("wait for sh");
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut rest = Vec::new();
+            let _ = stdout.read_to_end(&mut rest);
+            let _ = tx.send(());
+        });
+        assert!(
+            rx.recv_timeout(Duration::from_secs(10)).is_ok(),
+            "grandchild survived terminate_all"
+        );
+        registry.remove(child.id(), || {});
+    }
+
+    #[test]
+    fn handler_stays_installed_until_last_child_finishes() {
+        let registry = ChildRegistry::new();
+        let mut installs = 0;
+        registry.add(1, || installs += 1);
+        registry.add(2, || installs += 1);
+        assert_eq!(installs, 1);
+
+        let mut restores = 0;
+        registry.remove(1, || restores += 1);
+        assert_eq!(restores, 0);
+        registry.remove(3, || restores += 1);
+        assert_eq!(restores, 0);
+        registry.remove(2, || restores += 1);
+        assert_eq!(restores, 1);
+    }
+}
@@ -1,2 +1,15 @@
+pub mod aliases;
+pub mod batch;
+pub mod ci;
+pub mod glob;
+pub mod interrupt;
+pub mod net;
+pub mod output;
+pub mod paths;
 pub mod python;
+pub mod retry;
+pub mod run_log;
+pub mod runtime;
 pub mod shell;
+#[cfg(test)]
+pub mod test_env;
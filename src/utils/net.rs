@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::Client;
+use reqwest::{NoProxy, Proxy};
+
+use crate::error::QbitError;
+
+const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 30;
+
+/// True when `QBIT_OFFLINE` is set to a truthy value (`1`, `true`, `yes`, `on`).
+pub fn offline_from_env() -> bool {
+    std::env::var("QBIT_OFFLINE")
+        .map(|value| is_truthy(&value))
+        .unwrap_or(false)
+}
+
+/// Bail before any network access when offline mode is active.
+pub fn ensure_online(offline: bool) -> Result<()> {
+    if offline || offline_from_env() {
+        bail!(QbitError::Offline);
+    }
+    Ok(())
+}
+
+/// Build the blocking HTTP client shared by every network path: offline mode is
+/// checked first, then `QBIT_HTTP_TIMEOUT` and the standard proxy variables are applied.
+pub fn http_client(offline: bool) -> Result<Client> {
+    ensure_online(offline)?;
+
+    let timeout = Duration::from_secs(parse_timeout_secs(
+        std::env::var("QBIT_HTTP_TIMEOUT").ok().as_deref(),
+    )?);
+    let mut builder = Client::builder().connect_timeout(timeout).timeout(timeout);
+
+    if let Some(url) = proxy_from_env(&["HTTPS_PROXY", "https_proxy"]) {
+        let proxy = Proxy::https(&url)
+            .with_context(|| format!("invalid HTTPS_PROXY value `{url}`"))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+    if let Some(url) = proxy_from_env(&["HTTP_PROXY", "http_proxy"]) {
+        let proxy = Proxy::http(&url)
+            .with_context(|| format!("invalid HTTP_PROXY value `{url}`"))?
+            .no_proxy(NoProxy::from_env());
+        builder = builder.proxy(proxy);
+    }
+
+    builder.build().context("building HTTP client")
+}
+
+/// Parse `QBIT_HTTP_TIMEOUT` (whole seconds), defaulting to 30 when unset or blank.
+fn parse_timeout_secs(raw: Option<&str>) -> Result<u64> {
+    let Some(raw) = raw.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(DEFAULT_HTTP_TIMEOUT_SECS);
+    };
+    match raw.parse::<u64>() {
+        Ok(0) | Err(_) => bail!(
+            "QBIT_HTTP_TIMEOUT must be a positive number of seconds, got `{raw}`. Unset it to use the default of {DEFAULT_HTTP_TIMEOUT_SECS}s."
+        ),
+        Ok(secs) => Ok(secs),
+    }
+}
+
+fn proxy_from_env(keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+/// Interpret an environment flag value (`1`, `true`, `yes`, `on`, case-insensitive).
+pub fn is_truthy(raw: &str) -> bool {
+    matches!(
+        raw.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truthy_values_enable_offline() {
+        assert!(is_truthy("1"));
+        assert!(is_truthy(" TRUE "));
+        assert!(is_truthy("yes"));
+        assert!(!is_truthy("0"));
+        assert!(!is_truthy(""));
+    }
+
+    #[test]
+    fn timeout_defaults_when_unset_or_blank() {
+        assert_eq!(parse_timeout_secs(None).expect("default"), 30);
+        assert_eq!(parse_timeout_secs(Some("  ")).expect("default"), 30);
+    }
+
+    #[test]
+    fn timeout_parses_positive_seconds() {
+        assert_eq!(parse_timeout_secs(Some(" 5 ")).expect("parse"), 5);
+    }
+
+    #[test]
+    fn timeout_rejects_zero_and_garbage() {
+        assert!(parse_timeout_secs(Some("0")).is_err());
+        let err = parse_timeout_secs(Some("soon")).expect_err("must fail");
+        assert!(err.to_string().contains("QBIT_HTTP_TIMEOUT"));
+    }
+
+    #[test]
+    fn ensure_online_rejects_explicit_offline() {
+        let err = ensure_online(true).expect_err("must fail");
+        assert!(matches!(
+            err.downcast_ref::<QbitError>(),
+            Some(QbitError::Offline)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+thread_local! {
+    /// Progress text and child output collected by [`OutputMode::buffered`].
+    static BUFFER: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Append `text` to this thread's buffer; `false` when nothing is buffering.
+fn append_to_buffer(text: &str) -> bool {
+    BUFFER.with(|buffer| match buffer.borrow_mut().as_mut() {
+        Some(buffer) => {
+            buffer.push_str(text);
+            true
+        }
+        None => false,
+    })
+}
+
+/// How command results are rendered on stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    #[default]
+    Human,
+    Json,
+}
+
+impl OutputMode {
+    pub fn from_json_flag(json: bool) -> Self {
+        if json { Self::Json } else { Self::Human }
+    }
+
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+
+    /// Print a human-readable progress line. In JSON mode it goes to stderr so
+    /// stdout stays a single parseable document.
+    pub fn info(self, message: impl Display) {
+        if append_to_buffer(&format!("{message}\n")) {
+            return;
+        }
+        match self {
+            Self::Human => println!("{message}"),
+            Self::Json => eprintln!("{message}"),
+        }
+    }
+
+    /// Emit a structured result. No-op in human mode, where callers have
+    /// already printed the equivalent text via [`OutputMode::info`].
+    pub fn json<T: Serialize>(self, value: &T) -> Result<()> {
+        if self.is_json() {
+            let rendered =
+                serde_json::to_string_pretty(value).context("serializing JSON output")?;
+            println!("{rendered}");
+        }
+        Ok(())
+    }
+
+    /// Where a spawned child's stdout should go: inherited for humans, routed
+    /// to stderr in JSON mode.
+    pub fn child_stdout(self) -> Stdio {
+        match self {
+            Self::Human => Stdio::inherit(),
+            Self::Json => std::io::stderr().into(),
+        }
+    }
+
+    /// Run `action` with this thread's progress text and child output held
+    /// back, then print it in one piece, so parallel jobs don't interleave.
+    pub fn buffered<R>(self, action: impl FnOnce() -> R) -> R {
+        BUFFER.with(|buffer| *buffer.borrow_mut() = Some(String::new()));
+        let result = action();
+        let text = BUFFER
+            .with(|buffer| buffer.borrow_mut().take())
+            .unwrap_or_default();
+        // A closed stdout/stderr only loses the log, never the result.
+        let _ = match self {
+            Self::Human => io::stdout().lock().write_all(text.as_bytes()),
+            Self::Json => io::stderr().lock().write_all(text.as_bytes()),
+        };
+        result
+    }
+
+    /// Run `command` to completion. Its stdout goes where [`Self::child_stdout`]
+    /// says, or, inside [`Self::buffered`], both streams are captured.
+    pub fn run_child(self, command: &mut Command) -> io::Result<ExitStatus> {
+        let buffering = BUFFER.with(|buffer| buffer.borrow().is_some());
+        if !buffering {
+            return command.stdout(self.child_stdout()).status();
+        }
+        let captured = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+        append_to_buffer(&String::from_utf8_lossy(&captured.stdout));
+        append_to_buffer(&String::from_utf8_lossy(&captured.stderr));
+        Ok(captured.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_flag_selects_mode() {
+        assert_eq!(OutputMode::from_json_flag(true), OutputMode::Json);
+        assert_eq!(OutputMode::from_json_flag(false), OutputMode::Human);
+        assert!(!OutputMode::default().is_json());
+    }
+
+    #[test]
+    fn buffered_collects_info_lines() {
+        let value = OutputMode::Human.buffered(|| {
+            OutputMode::Human.info("first");
+            append_to_buffer("second\n");
+            BUFFER.with(|buffer| buffer.borrow().clone())
+        });
+        assert_eq!(value.as_deref(), Some("first\nsecond\n"));
+        assert!(!append_to_buffer("after"));
+    }
+
+    #[test]
+    fn json_is_noop_in_human_mode() {
+        OutputMode::Human
+            .json(&serde_json::json!({ "ignored": true }))
+            .expect("human mode never fails");
+    }
+}
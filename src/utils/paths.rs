@@ -0,0 +1,245 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// Expand a leading `~`/`~user` and `$VAR`/`${VAR}` references (plus `%VAR%`
+/// on Windows) in a path taken from config or the environment. Unknown
+/// variables are left as written so the resulting error names them.
+pub fn expand_path(raw: &str) -> PathBuf {
+    PathBuf::from(expand_with(raw, |key| std::env::var(key).ok()))
+}
+
+fn expand_with(raw: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let tilde_expanded = expand_tilde(raw, &lookup);
+    expand_vars(&tilde_expanded, lookup)
+}
+
+/// Expand `$VAR`/`${VAR}` (plus `%VAR%` on Windows) using `lookup`, leaving
+/// unknown variables as written.
+pub fn expand_vars(raw: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let expanded = expand_dollar_vars(raw, &lookup);
+    if cfg!(windows) {
+        expand_percent_vars(&expanded, &lookup)
+    } else {
+        expanded
+    }
+}
+
+fn home_dir(lookup: &impl Fn(&str) -> Option<String>) -> Option<String> {
+    lookup("HOME")
+        .or_else(|| lookup("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+}
+
+/// `~` and `~/rest` map to the home directory. `~user` maps to a sibling of
+/// the current home directory (`/home/me` → `/home/user`), which covers the
+/// usual layout without a passwd lookup.
+fn expand_tilde(raw: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let Some(rest) = raw.strip_prefix('~') else {
+        return raw.to_string();
+    };
+    let split = rest.find(['/', '\\']).unwrap_or(rest.len());
+    let (user, tail) = rest.split_at(split);
+    let Some(home) = home_dir(lookup) else {
+        return raw.to_string();
+    };
+
+    let current_user = lookup("USER").or_else(|| lookup("USERNAME"));
+    if user.is_empty() || current_user.as_deref() == Some(user) {
+        return format!("{home}{tail}");
+    }
+    match PathBuf::from(&home).parent() {
+        Some(parent) => format!("{}{tail}", parent.join(user).display()),
+        None => raw.to_string(),
+    }
+}
+
+fn expand_dollar_vars(input: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, consumed) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+
+        match (!name.is_empty()).then(|| lookup(name)).flatten() {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[pos..pos + 1 + consumed]),
+        }
+        rest = &after[consumed..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn expand_percent_vars(input: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('%') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('%') else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let name = &after[..end];
+        match (!name.is_empty()).then(|| lookup(name)).flatten() {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + end + 2]),
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Locate `program` the way the OS would, without spawning it: a path is
+/// checked as-is, a bare name is looked up in each `PATH` entry (trying the
+/// `PATHEXT` extensions on Windows).
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let pathext = std::env::var_os("PATHEXT");
+    find_in_path(program, &path, pathext.as_deref())
+}
+
+fn find_in_path(program: &str, path: &OsStr, pathext: Option<&OsStr>) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return is_executable(candidate).then(|| candidate.to_path_buf());
+    }
+
+    let extensions: Vec<String> = if cfg!(windows) && candidate.extension().is_none() {
+        pathext
+            .and_then(OsStr::to_str)
+            .unwrap_or(".COM;.EXE;.BAT;.CMD")
+            .split(';')
+            .filter(|ext| !ext.is_empty())
+            .map(str::to_string)
+            .collect()
+    } else {
+        vec![String::new()]
+    };
+
+    std::env::split_paths(path)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| {
+            extensions
+                .iter()
+                .map(move |ext| dir.join(format!("{program}{ext}")))
+        })
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(key: &str) -> Option<String> {
+        match key {
+            "HOME" => Some("/home/me".to_string()),
+            "USER" => Some("me".to_string()),
+            "PROJECT" => Some("app".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expands_leading_tilde() {
+        assert_eq!(expand_with("~", env), "/home/me");
+        assert_eq!(
+            expand_with("~/scripts/deploy.sh", env),
+            "/home/me/scripts/deploy.sh"
+        );
+        assert_eq!(expand_with("~me/x", env), "/home/me/x");
+        assert_eq!(
+            PathBuf::from(expand_with("~alice/x", env)),
+            PathBuf::from("/home").join("alice").join("x")
+        );
+        assert_eq!(expand_with("a/~/b", env), "a/~/b");
+    }
+
+    #[test]
+    fn expands_dollar_variables() {
+        assert_eq!(expand_with("$HOME/x", env), "/home/me/x");
+        assert_eq!(
+            expand_with("${HOME}/work/${PROJECT}", env),
+            "/home/me/work/app"
+        );
+        assert_eq!(expand_with("$MISSING/x", env), "$MISSING/x");
+        assert_eq!(expand_with("${unterminated", env), "${unterminated");
+    }
+
+    #[test]
+    fn leaves_literal_paths_untouched() {
+        assert_eq!(expand_with("build/out.txt", env), "build/out.txt");
+        assert_eq!(expand_with("/opt/tools", env), "/opt/tools");
+    }
+
+    #[test]
+    fn expands_percent_variables() {
+        assert_eq!(expand_percent_vars("%HOME%\\x", &env), "/home/me\\x");
+        assert_eq!(expand_percent_vars("100%", &env), "100%");
+        assert_eq!(expand_percent_vars("%MISSING%", &env), "%MISSING%");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn finds_only_executable_files_on_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = tempfile::tempdir().expect("tempdir");
+        let second = tempfile::tempdir().expect("tempdir");
+        let tool = second.path().join("faketool");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(first.path().join("notes"), "plain file").unwrap();
+
+        let path = std::env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(find_in_path("faketool", &path, None), Some(tool.clone()));
+        assert_eq!(find_in_path("notes", &path, None), None);
+        assert_eq!(find_in_path("missing", &path, None), None);
+        assert_eq!(
+            find_in_path(tool.to_str().unwrap(), OsStr::new(""), None),
+            Some(tool)
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn finds_executables_through_pathext() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let tool = dir.path().join("faketool.CMD");
+        std::fs::write(&tool, "@echo off\r\n").unwrap();
+
+        let path = dir.path().as_os_str();
+        assert_eq!(
+            find_in_path("faketool", path, Some(OsStr::new(".EXE;.CMD"))),
+            Some(tool)
+        );
+        assert_eq!(
+            find_in_path("faketool", path, Some(OsStr::new(".EXE"))),
+            None
+        );
+    }
+}
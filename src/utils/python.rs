@@ -1,4 +1,8 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
 
 /// Candidate interpreters to try (ordered).
 #[cfg(windows)]
@@ -21,6 +25,29 @@ pub fn find_python() -> Option<String> {
         }
     }
     None
+}
+
+/// Directory holding a venv's executables (`bin/` on Unix, `Scripts\` on Windows).
+pub fn venv_bin_dir(venv: &Path) -> PathBuf {
+    if cfg!(windows) {
+        venv.join("Scripts")
+    } else {
+        venv.join("bin")
+    }
+}
+
+/// Variables that activate `venv` for a child process: its executables
+/// first on PATH and `VIRTUAL_ENV` pointing at it.
+pub fn venv_env(venv: &Path) -> Result<Vec<(&'static str, OsString)>> {
+    let mut paths = vec![venv_bin_dir(venv)];
+    if let Some(existing) = std::env::var_os("PATH") {
+        paths.extend(std::env::split_paths(&existing));
+    }
+    let path = std::env::join_paths(paths).context("building PATH with venv")?;
+    Ok(vec![
+        ("PATH", path),
+        ("VIRTUAL_ENV", venv.as_os_str().to_os_string()),
+    ])
 }
 
 fn check_version_ok(cmd: &str) -> bool {
@@ -60,6 +87,15 @@ mod tests {
         assert_eq!(rest, vec!["-3"]);
     }
 
+    #[test]
+    fn venv_bin_dir_is_platform_specific() {
+        let dir = venv_bin_dir(Path::new("venv"));
+        #[cfg(windows)]
+        assert!(dir.ends_with("Scripts"));
+        #[cfg(not(windows))]
+        assert!(dir.ends_with("bin"));
+    }
+
     #[test]
     fn split_first_handles_single_binary() {
         let (bin, rest) = split_first("python3");
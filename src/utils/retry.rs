@@ -0,0 +1,178 @@
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+
+/// Extra attempts after a failure that looks network-related.
+const DEFAULT_RETRIES: u32 = 2;
+
+/// How much of a failed run's stderr is kept for classification.
+const STDERR_TAIL_BYTES: usize = 64 * 1024;
+
+/// Lowercase stderr fragments npm, yarn, pnpm, and pip print when the
+/// registry is unreachable or flaky, as opposed to a package that doesn't
+/// exist (`404 Not Found`, `No matching distribution found`).
+const NETWORK_MARKERS: &[&str] = &[
+    "etimedout",
+    "econnreset",
+    "econnrefused",
+    "eai_again",
+    "getaddrinfo",
+    "socket hang up",
+    "network request",
+    "connection reset",
+    "connection timed out",
+    "newconnectionerror",
+    "timed out",
+    "temporary failure in name resolution",
+    "max retries exceeded",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway",
+];
+
+/// A package-manager run after any retries.
+#[derive(Debug)]
+pub struct RetriedRun {
+    pub status: ExitStatus,
+    pub attempts: u32,
+    /// The last failure looked network-related.
+    pub network_error: bool,
+}
+
+/// Whether stderr from a failed package-manager run points at a transient
+/// network problem worth retrying.
+pub fn is_transient_network_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_ascii_lowercase();
+    NETWORK_MARKERS.iter().any(|marker| stderr.contains(marker))
+}
+
+/// Run the command `build` returns, rerunning it up to `QBIT_NET_RETRIES`
+/// times (default 2) while it fails with network-looking stderr. stderr is
+/// still shown live; `what` names the command in the retry notice.
+pub fn run_with_retries(what: &str, mut build: impl FnMut() -> Command) -> Result<RetriedRun> {
+    let retries = parse_retries(std::env::var("QBIT_NET_RETRIES").ok().as_deref())?;
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let (status, stderr) =
+            run_teeing_stderr(&mut build()).with_context(|| format!("running {what}"))?;
+        let network_error = !status.success() && is_transient_network_failure(&stderr);
+        if !network_error || attempts > retries {
+            return Ok(RetriedRun {
+                status,
+                attempts,
+                network_error,
+            });
+        }
+        eprintln!(
+            "{what} failed with what looks like a network error; retrying ({attempts}/{retries})..."
+        );
+        thread::sleep(Duration::from_secs(u64::from(attempts)));
+    }
+}
+
+/// Parse `QBIT_NET_RETRIES`, defaulting to 2 when unset or blank. `0` turns
+/// retries off.
+fn parse_retries(raw: Option<&str>) -> Result<u32> {
+    let Some(raw) = raw.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(DEFAULT_RETRIES);
+    };
+    match raw.parse::<u32>() {
+        Ok(retries) => Ok(retries),
+        Err(_) => bail!(
+            "QBIT_NET_RETRIES must be a whole number of extra attempts, got `{raw}`. Unset it to use the default of {DEFAULT_RETRIES}."
+        ),
+    }
+}
+
+/// Run `command` with its stderr forwarded to ours as it arrives, returning
+/// the tail of that stderr alongside the exit status.
+fn run_teeing_stderr(command: &mut Command) -> io::Result<(ExitStatus, String)> {
+    let mut child = command.stderr(Stdio::piped()).spawn()?;
+    let mut stderr = child.stderr.take().expect("child stderr is piped");
+    let reader = thread::spawn(move || {
+        let mut tail = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            match stderr.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => {
+                    let _ = io::stderr().write_all(&chunk[..read]);
+                    tail.extend_from_slice(&chunk[..read]);
+                    if tail.len() > STDERR_TAIL_BYTES {
+                        tail.drain(..tail.len() - STDERR_TAIL_BYTES);
+                    }
+                }
+            }
+        }
+        tail
+    });
+    let status = child.wait();
+    let tail = reader.join().expect("stderr reader thread panicked");
+    Ok((status?, String::from_utf8_lossy(&tail).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_network_failures_apart_from_missing_packages() {
+        assert!(is_transient_network_failure(
+            "npm ERR! code ETIMEDOUT\nnpm ERR! network request to https://registry.npmjs.org/react failed"
+        ));
+        assert!(is_transient_network_failure(
+            "npm ERR! request to https://registry.npmjs.org/x failed, reason: getaddrinfo EAI_AGAIN registry.npmjs.org"
+        ));
+        assert!(is_transient_network_failure(
+            "WARNING: Retrying (Retry(total=4)) after connection broken by 'NewConnectionError'\nERROR: No matching distribution found for requests"
+        ));
+        assert!(is_transient_network_failure(
+            "pip._vendor.urllib3.exceptions.ReadTimeoutError: HTTPSConnectionPool(host='pypi.org', port=443): Read timed out."
+        ));
+
+        assert!(!is_transient_network_failure(
+            "npm ERR! code E404\nnpm ERR! 404 Not Found - GET https://registry.npmjs.org/no-such-pkg - Not found"
+        ));
+        assert!(!is_transient_network_failure(
+            "ERROR: Could not find a version that satisfies the requirement no-such-pkg (from versions: none)\nERROR: No matching distribution found for no-such-pkg"
+        ));
+        // A postinstall talking to a local service isn't a registry outage.
+        assert!(!is_transient_network_failure(
+            "npm ERR! command failed: node scripts/migrate.js\nError: database connection refused"
+        ));
+    }
+
+    #[test]
+    fn retries_default_to_two_and_reject_garbage() {
+        assert_eq!(parse_retries(None).expect("default"), 2);
+        assert_eq!(parse_retries(Some(" ")).expect("default"), 2);
+        assert_eq!(parse_retries(Some("0")).expect("off"), 0);
+        let err = parse_retries(Some("lots")).expect_err("must fail");
+        assert!(err.to_string().contains("QBIT_NET_RETRIES"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_network_failures_are_retried() {
+        let run = |script: &'static str| {
+            run_with_retries("test command", || {
+                let mut command = Command::new("sh");
+                command.args(["-c", script]).stdout(Stdio::null());
+                command
+            })
+            .expect("run")
+        };
+
+        let missing = run("echo 'npm ERR! 404 Not Found' >&2; exit 1");
+        assert_eq!(missing.attempts, 1);
+        assert!(!missing.network_error);
+
+        let ok = run("exit 0");
+        assert!(ok.status.success());
+        assert_eq!(ok.attempts, 1);
+    }
+}
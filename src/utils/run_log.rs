@@ -0,0 +1,152 @@
+//! `qbit run --output <file>`: every step's output is copied into a log file
+//! as it reaches the terminal, between timestamped start and finish lines.
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+
+/// Log file that step output is appended to. Writes go straight to the file
+/// without buffering, so nothing is lost when Ctrl-C exits qbit mid-step.
+#[derive(Debug)]
+pub struct RunLog {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl RunLog {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening log file {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn step_started(&self, label: &str, step: usize, command: &str) -> Result<()> {
+        self.line(&format!("{label} step {step} started: {command}"))
+    }
+
+    /// `outcome` reads after the step, e.g. `exited with code 0`.
+    pub fn step_finished(&self, label: &str, step: usize, outcome: &str) -> Result<()> {
+        self.line(&format!("{label} step {step} {outcome}"))
+    }
+
+    fn line(&self, text: &str) -> Result<()> {
+        let line = format!("[{}] {text}\n", utc_timestamp(SystemTime::now()));
+        self.write_all(line.as_bytes())
+            .with_context(|| format!("writing log file {}", self.path.display()))
+    }
+
+    fn write_all(&self, bytes: &[u8]) -> io::Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(bytes)?;
+        file.flush()
+    }
+}
+
+/// Writer that forwards bytes unchanged to `inner` and, when there is one,
+/// to the log.
+pub struct Tee<'a, W> {
+    inner: W,
+    log: Option<&'a RunLog>,
+}
+
+impl<'a, W: Write> Tee<'a, W> {
+    pub fn new(inner: W, log: Option<&'a RunLog>) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl<W: Write> Write for Tee<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if let Some(log) = self.log {
+            log.write_all(&buf[..written])?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// `2026-10-15T09:30:00Z`: `time` in UTC, to the second.
+pub(crate) fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Calendar date of a day count since 1970-01-01 (Howard Hinnant's
+/// `civil_from_days`).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let shifted = days + 719_468;
+    let era = shifted.div_euclid(146_097);
+    let day_of_era = shifted.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn timestamps_are_utc_calendar_times() {
+        let at = |secs| utc_timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(at(1_700_000_000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn tee_copies_bytes_to_both_writers_and_appends() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let path = tmp.path().join("run.log");
+        fs::write(&path, "earlier run\n").expect("seed log");
+
+        let log = RunLog::open(&path).expect("open log");
+        let mut shown = Vec::new();
+        let bytes = b"caf\xc3\xa9 \x00\xff\n";
+        Tee::new(&mut shown, Some(&log))
+            .write_all(bytes)
+            .expect("tee write");
+        assert_eq!(shown, bytes);
+
+        let logged = fs::read(&path).expect("read log");
+        assert!(logged.starts_with(b"earlier run\n"));
+        assert!(logged.ends_with(bytes));
+    }
+}
@@ -0,0 +1,102 @@
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Env var pinning the `node` binary (e.g. one managed by nvm or asdf).
+pub const NODE_OVERRIDE: &str = "QBIT_NODE";
+/// Env var pinning the `dart` binary from a specific SDK.
+pub const DART_OVERRIDE: &str = "QBIT_DART";
+
+/// The runtime named by `var`, if set and it answers `--version`. A set but
+/// broken override is reported so the fallback isn't silent.
+pub fn runtime_override(var: &str) -> Option<String> {
+    let explicit = std::env::var(var)
+        .ok()
+        .filter(|value| !value.trim().is_empty())?;
+    if check_version_ok(&explicit) {
+        return Some(explicit);
+    }
+    eprintln!("warning: {var}=`{explicit}` did not respond to `--version`; ignoring it");
+    None
+}
+
+/// Resolve a runtime: the `var` override first, then `default` from PATH.
+pub fn find_runtime(var: &str, default: &str) -> Option<String> {
+    runtime_override(var).or_else(|| check_version_ok(default).then(|| default.to_string()))
+}
+
+/// `base_path` with `runtime`'s directory in front, so tools that spawn the
+/// runtime by name (like `node` inside package.json scripts) get the pinned one.
+pub fn path_with_runtime_first(runtime: &str, base_path: &OsStr) -> Option<OsString> {
+    let dir = Path::new(runtime)
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())?;
+    let paths = std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(base_path));
+    std::env::join_paths(paths).ok()
+}
+
+fn check_version_ok(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_env::EnvGuard;
+    use serial_test::serial;
+
+    #[cfg(unix)]
+    fn fake_runtime(dir: &Path, name: &str, exit_code: i32) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\nexit {exit_code}\n")).expect("write runtime");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+            .expect("chmod runtime");
+        path.to_string_lossy().into_owned()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn prefers_valid_override_over_default() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let pinned = fake_runtime(tmp.path(), "node-pinned", 0);
+        let default = fake_runtime(tmp.path(), "node-default", 0);
+        let _node = EnvGuard::set(NODE_OVERRIDE, &pinned);
+
+        assert_eq!(find_runtime(NODE_OVERRIDE, &default), Some(pinned));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn falls_back_to_default_when_override_is_broken() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let broken = fake_runtime(tmp.path(), "dart-broken", 1);
+        let default = fake_runtime(tmp.path(), "dart-default", 0);
+        let _dart = EnvGuard::set(DART_OVERRIDE, &broken);
+
+        assert_eq!(runtime_override(DART_OVERRIDE), None);
+        assert_eq!(find_runtime(DART_OVERRIDE, &default), Some(default));
+    }
+
+    #[test]
+    fn puts_runtime_dir_first_on_path() {
+        let base = std::env::join_paths(["/usr/bin"]).unwrap();
+        let runtime = Path::new("/opt/node18/bin").join("node");
+        let path = path_with_runtime_first(&runtime.to_string_lossy(), &base).expect("joined PATH");
+        let entries: Vec<_> = std::env::split_paths(&path).collect();
+        assert_eq!(
+            entries,
+            [Path::new("/opt/node18/bin"), Path::new("/usr/bin")]
+        );
+        assert_eq!(path_with_runtime_first("node", &base), None);
+    }
+}
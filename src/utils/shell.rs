@@ -1,62 +1,798 @@
 use anyhow::{Context, Result, bail};
-use std::process::{Command, Stdio};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStderr, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn run_shell(command: &str) -> Result<()> {
-    let mut cmd = shell_command(command);
-    cmd.stdin(Stdio::inherit())
+use crate::error::QbitError;
+use crate::utils::interrupt::{self, ActiveChild};
+use crate::utils::output::OutputMode;
+use crate::utils::paths::{expand_vars, find_executable};
+use crate::utils::python::find_python;
+use crate::utils::run_log::{RunLog, Tee};
+
+/// How often a step with a timeout is checked for exit.
+const TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Interpreter of `{ python: ... }` blocks. It resolves to the same
+/// interpreter as `qbit py` (`QBIT_PY`, then `python3`, then `python`).
+pub const PYTHON_INTERPRETER: &str = "python";
+
+const SHELL_HINT: &str = "Install a POSIX shell or set QBIT_SHELL.";
+
+/// Outcome of a single script step, as reported in JSON output.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepReport {
+    pub step: usize,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// Wall-clock time the step took.
+    pub duration_ms: u64,
+}
+
+/// Outcome of a whole script, for callers that fold it into their own JSON
+/// output instead of printing a report per script.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptReport {
+    pub success: bool,
+    pub steps: Vec<StepReport>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct RunReport<'a> {
+    label: &'a str,
+    #[serde(flatten)]
+    report: &'a ScriptReport,
+}
+
+/// One element of the JSON plan printed by `qbit run --dry-run --json`:
+/// everything a CI runner needs to execute the step itself.
+#[derive(Debug, Serialize)]
+struct PlannedStep {
+    label: String,
+    step: usize,
+    command: String,
+    cwd: PathBuf,
+    env: BTreeMap<String, String>,
+    timeout_secs: Option<u64>,
+    /// Set for interpreter blocks, whose `command` is the script itself.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interpreter: Option<String>,
+}
+
+/// Dry-run steps gathered across every script of one `qbit run`, so `--json`
+/// prints a single array instead of one document per script.
+#[derive(Debug, Default)]
+pub struct DryRunPlan {
+    steps: Mutex<Vec<PlannedStep>>,
+}
+
+impl DryRunPlan {
+    fn extend(&self, steps: Vec<PlannedStep>) {
+        self.steps
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .extend(steps);
+    }
+
+    /// Emit the collected steps as one JSON array (nothing in human mode).
+    pub fn print(&self, output: OutputMode) -> Result<()> {
+        let steps = self
+            .steps
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        output.json(&*steps)
+    }
+}
+
+/// Flags from `qbit run` that change how script steps execute.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunOptions<'a> {
+    /// Tag every output line with `[label:step N]`.
+    pub prefix_lines: bool,
+    /// Print each step with its variables expanded instead of running it.
+    pub dry_run: bool,
+    /// List each step's duration in the closing summary.
+    pub verbose: bool,
+    /// Also copy every step's output into this log (`--output <file>`).
+    pub log: Option<&'a RunLog>,
+    /// With `dry_run`, collect the planned steps here instead of printing
+    /// them per script; the caller prints the whole plan once.
+    pub plan: Option<&'a DryRunPlan>,
+}
+
+/// One command of a script and the settings it runs with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandStep {
+    pub command: String,
+    /// Layered over the inherited environment.
+    pub env: BTreeMap<String, String>,
+    /// Working directory; the current one when `None`.
+    pub cwd: Option<PathBuf>,
+    /// The step is killed and fails once it runs this long.
+    pub timeout: Option<Duration>,
+    /// Run `command` as a script file with this interpreter (`bash`,
+    /// `python`, ...) instead of passing it to the shell.
+    pub interpreter: Option<String>,
+}
+
+impl CommandStep {
+    /// The step as shown in progress lines, reports, and errors: the command
+    /// itself, or `bash script (3 lines)` for an interpreter block.
+    pub fn describe(&self) -> String {
+        match &self.interpreter {
+            None => self.command.clone(),
+            Some(interpreter) => match self.command.trim_end().lines().count() {
+                1 => format!("{interpreter} script (1 line)"),
+                lines => format!("{interpreter} script ({lines} lines)"),
+            },
+        }
+    }
+}
+
+/// Run each step in order, stopping at the first failure.
+pub fn run_commands(
+    label: &str,
+    commands: &[CommandStep],
+    output: OutputMode,
+    options: RunOptions,
+) -> Result<()> {
+    if commands.is_empty() {
+        bail!("no commands defined for {label}");
+    }
+
+    if options.dry_run {
+        return print_dry_run(label, commands, output, options.plan);
+    }
+
+    let (report, outcome) = run_commands_reported(label, commands, output, options);
+    output.json(&RunReport {
+        label,
+        report: &report,
+    })?;
+    outcome
+}
+
+/// [`run_commands`] for a caller that prints the JSON report itself, as part
+/// of its own: the report comes back next to the run's result. `commands`
+/// must not be empty, and `options.dry_run` is ignored.
+pub fn run_commands_reported(
+    label: &str,
+    commands: &[CommandStep],
+    output: OutputMode,
+    options: RunOptions,
+) -> (ScriptReport, Result<()>) {
+    let started = Instant::now();
+    let mut steps = Vec::with_capacity(commands.len());
+    for (idx, step) in commands.iter().enumerate() {
+        let cmd = &step.describe();
+        output.info(format!("[{label}] step {} -> {}", idx + 1, cmd));
+        let step_started = Instant::now();
+        let logged = options
+            .log
+            .map_or(Ok(()), |log| log.step_started(label, idx + 1, cmd));
+        if let Err(err) = logged {
+            let report = finish_run(
+                label,
+                steps,
+                commands.len(),
+                started,
+                false,
+                output,
+                options,
+            );
+            return (report, Err(err));
+        }
+        let result = if options.prefix_lines {
+            spawn_shell_prefixed(step, &step_prefix(label, idx + 1), output, options.log)
+        } else {
+            spawn_shell(step, output, options.log)
+        };
+        let logged = options.log.map_or(Ok(()), |log| {
+            log.step_finished(label, idx + 1, &describe_outcome(&result))
+        });
+        steps.push(StepReport {
+            step: idx + 1,
+            command: cmd.clone(),
+            exit_code: result.as_ref().ok().and_then(ExitStatus::code),
+            duration_ms: millis(step_started.elapsed()),
+        });
+        let outcome = logged.and(match result {
+            Ok(status) => ensure_success(cmd, status),
+            Err(err) => Err(err),
+        });
+        if outcome.is_err() {
+            let report = finish_run(
+                label,
+                steps,
+                commands.len(),
+                started,
+                false,
+                output,
+                options,
+            );
+            return (report, outcome);
+        }
+    }
+
+    let report = finish_run(label, steps, commands.len(), started, true, output, options);
+    (report, Ok(()))
+}
+
+/// Print the timing summary for a finished run and build its report.
+fn finish_run(
+    label: &str,
+    steps: Vec<StepReport>,
+    total_steps: usize,
+    started: Instant,
+    success: bool,
+    output: OutputMode,
+    options: RunOptions,
+) -> ScriptReport {
+    let elapsed = started.elapsed();
+    for line in summary_lines(
+        label,
+        &steps,
+        total_steps,
+        elapsed,
+        success,
+        options.verbose,
+    ) {
+        output.info(line);
+    }
+    ScriptReport {
+        success,
+        steps,
+        duration_ms: millis(elapsed),
+    }
+}
+
+/// `script:build completed in 4.2s (3 steps)`, preceded by one line per step
+/// when `verbose`.
+fn summary_lines(
+    label: &str,
+    steps: &[StepReport],
+    total_steps: usize,
+    elapsed: Duration,
+    success: bool,
+    verbose: bool,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    if verbose {
+        for step in steps {
+            let took = Duration::from_millis(step.duration_ms);
+            lines.push(format!(
+                "  step {}: {} -> {}",
+                step.step,
+                format_seconds(took),
+                step.command
+            ));
+        }
+    }
+    let count = match total_steps {
+        1 => "1 step".to_string(),
+        n => format!("{n} steps"),
+    };
+    lines.push(if success {
+        format!("{label} completed in {} ({count})", format_seconds(elapsed))
+    } else {
+        format!(
+            "{label} failed after {} (step {} of {count})",
+            format_seconds(elapsed),
+            steps.len()
+        )
+    });
+    lines
+}
+
+fn format_seconds(duration: Duration) -> String {
+    format!("{:.1}s", duration.as_secs_f64())
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Show what each step would run, with `$VAR`/`${VAR}` references resolved
+/// from the step's env and then the process env. Nothing is spawned. In JSON
+/// mode the steps are printed as an array, with each step's working directory
+/// made explicit, or added to `collect` when the caller prints the plan.
+fn print_dry_run(
+    label: &str,
+    commands: &[CommandStep],
+    output: OutputMode,
+    collect: Option<&DryRunPlan>,
+) -> Result<()> {
+    let current_dir = std::env::current_dir().context("resolving current directory")?;
+    let mut plan = Vec::with_capacity(commands.len());
+    for (idx, step) in commands.iter().enumerate() {
+        let lookup = |key: &str| {
+            step.env
+                .get(key)
+                .cloned()
+                .or_else(|| std::env::var(key).ok())
+        };
+        // Interpreter blocks are shown as written: the interpreter expands them.
+        let expanded = match step.interpreter {
+            Some(_) => step.command.clone(),
+            None => expand_vars(&step.command, lookup),
+        };
+        let shown = match step.interpreter {
+            Some(_) => step.describe(),
+            None => expanded.clone(),
+        };
+        let cwd = step
+            .cwd
+            .as_ref()
+            .map(|cwd| format!(" (in {})", cwd.display()))
+            .unwrap_or_default();
+        output.info(format!(
+            "[{label}] step {} (dry-run) -> {shown}{cwd}",
+            idx + 1
+        ));
+        plan.push(PlannedStep {
+            label: label.to_string(),
+            step: idx + 1,
+            command: expanded,
+            cwd: step.cwd.clone().unwrap_or_else(|| current_dir.clone()),
+            env: step.env.clone(),
+            timeout_secs: step.timeout.map(|timeout| timeout.as_secs()),
+            interpreter: step.interpreter.clone(),
+        });
+    }
+    match collect {
+        Some(collect) => {
+            collect.extend(plan);
+            Ok(())
+        }
+        None => output.json(&plan),
+    }
+}
+
+/// Run a single command through the platform shell from `cwd`, with extra
+/// environment variables layered over the inherited environment.
+pub fn run_shell_in(command: &str, cwd: &Path, envs: &[(&str, OsString)]) -> Result<()> {
+    let shell = shell_program();
+    let mut cmd = shell_command_with(&shell, command);
+    cmd.current_dir(cwd)
+        .envs(envs.iter().map(|(key, value)| (*key, value)))
+        .stdin(Stdio::inherit())
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit());
 
     let status = cmd
         .status()
-        .with_context(|| format!("running shell command: {command}"))?;
+        .map_err(|err| spawn_error(err, &shell, command, SHELL_HINT))?;
+    ensure_success(command, status)
+}
 
-    if !status.success() {
-        bail!(
-            "command `{}` exited with code {}",
-            command,
-            status.code().unwrap_or_default()
+/// Join argv into one shell command line, quoting each argument for the
+/// platform shell so spaces and metacharacters survive intact.
+pub fn join_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_shell_safe(arg: &str) -> bool {
+    !arg.is_empty()
+        && arg.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | '@' | ',' | '+')
+                || (cfg!(windows) && c == '\\')
+        })
+}
+
+#[cfg(windows)]
+fn quote_arg(arg: &str) -> String {
+    if is_shell_safe(arg) {
+        return arg.to_string();
+    }
+    format!("\"{}\"", arg.replace('"', "\\\""))
+}
+
+#[cfg(not(windows))]
+fn quote_arg(arg: &str) -> String {
+    if is_shell_safe(arg) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Run the step with its output going straight to the terminal or, with a
+/// `log`, copied there byte for byte while it is also logged.
+fn spawn_shell(step: &CommandStep, output: OutputMode, log: Option<&RunLog>) -> Result<ExitStatus> {
+    if log.is_some() {
+        return spawn_piped(
+            step,
+            |stdout| {
+                if output.is_json() {
+                    copy_through(stdout, io::stderr(), log)
+                } else {
+                    copy_through(stdout, io::stdout(), log)
+                }
+            },
+            |stderr| copy_through(stderr, io::stderr(), log),
         );
     }
-    Ok(())
+
+    let mut prepared = prepare_step(step)?;
+    prepared
+        .command
+        .stdin(Stdio::inherit())
+        .stdout(output.child_stdout())
+        .stderr(Stdio::inherit());
+
+    let mut child = prepared.spawn(step)?;
+    wait_for_step(&mut child, step)
 }
 
-pub fn run_commands(label: &str, commands: &[String]) -> Result<()> {
-    if commands.is_empty() {
-        bail!("no commands defined for {label}");
+/// Like [`spawn_shell`], but pipes the child's stdout/stderr through reader
+/// threads that prefix each line before forwarding it.
+fn spawn_shell_prefixed(
+    step: &CommandStep,
+    prefix: &str,
+    output: OutputMode,
+    log: Option<&RunLog>,
+) -> Result<ExitStatus> {
+    spawn_piped(
+        step,
+        |stdout| {
+            if output.is_json() {
+                forward_prefixed(stdout, Tee::new(io::stderr(), log), prefix)
+            } else {
+                forward_prefixed(stdout, Tee::new(io::stdout(), log), prefix)
+            }
+        },
+        |stderr| forward_prefixed(stderr, Tee::new(io::stderr(), log), prefix),
+    )
+}
+
+/// Spawn the step with stdout and stderr piped, hand each to its forwarder
+/// on a reader thread, and wait for the step and both readers.
+fn spawn_piped(
+    step: &CommandStep,
+    forward_stdout: impl FnOnce(ChildStdout) -> io::Result<()> + Send,
+    forward_stderr: impl FnOnce(ChildStderr) -> io::Result<()> + Send,
+) -> Result<ExitStatus> {
+    let mut prepared = prepare_step(step)?;
+    prepared
+        .command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = prepared.spawn(step)?;
+    let stdout = child.stdout.take().expect("child stdout is piped");
+    let stderr = child.stderr.take().expect("child stderr is piped");
+
+    thread::scope(|scope| {
+        let readers = [
+            scope.spawn(|| forward_stdout(stdout)),
+            scope.spawn(|| forward_stderr(stderr)),
+        ];
+        let status = wait_for_step(&mut child, step);
+        for reader in readers {
+            reader
+                .join()
+                .expect("output reader thread panicked")
+                .with_context(|| format!("forwarding output of: {}", step.describe()))?;
+        }
+        status
+    })
+}
+
+/// Copy `reader` to `writer` unchanged, logging the same bytes.
+fn copy_through(mut reader: impl Read, writer: impl Write, log: Option<&RunLog>) -> io::Result<()> {
+    let mut tee = Tee::new(writer, log);
+    io::copy(&mut reader, &mut tee)?;
+    tee.flush()
+}
+
+/// How a step ended, as written to the log.
+fn describe_outcome(result: &Result<ExitStatus>) -> String {
+    match result {
+        Ok(status) => match status.code() {
+            Some(code) => format!("exited with code {code}"),
+            None => "was terminated by a signal".to_string(),
+        },
+        Err(err) => format!("failed: {err:#}"),
     }
+}
 
-    for (idx, cmd) in commands.iter().enumerate() {
-        println!("[{label}] step {} -> {}", idx + 1, cmd);
-        run_shell(cmd)?;
+/// A step's process, not yet spawned. An interpreter block's temp file lives
+/// as long as this does.
+struct PreparedStep {
+    command: Command,
+    program: String,
+    hint: &'static str,
+    _script: Option<TempScript>,
+}
+
+impl PreparedStep {
+    fn spawn(&mut self, step: &CommandStep) -> Result<Child> {
+        self.command
+            .spawn()
+            .map_err(|err| spawn_error(err, &self.program, &step.describe(), self.hint))
     }
+}
 
-    Ok(())
+/// `<shell> <flag> <command>`, or `<interpreter> <script file>` for an
+/// interpreter block, with the step's env and working directory.
+fn prepare_step(step: &CommandStep) -> Result<PreparedStep> {
+    let mut prepared = match &step.interpreter {
+        None => {
+            let shell = shell_program();
+            PreparedStep {
+                command: shell_command_with(&shell, &step.command),
+                program: shell,
+                hint: SHELL_HINT,
+                _script: None,
+            }
+        }
+        Some(interpreter) => {
+            let is_python = interpreter.eq_ignore_ascii_case(PYTHON_INTERPRETER);
+            let program = if is_python {
+                find_python().unwrap_or_else(|| interpreter.clone())
+            } else {
+                interpreter.clone()
+            };
+            let script = TempScript::write(&step.command, script_extension(&program))?;
+            PreparedStep {
+                command: interpreter_command(&program, script.path()),
+                program,
+                hint: if is_python {
+                    "Install Python 3 or set QBIT_PY."
+                } else {
+                    "Install it or change the script's `shell`."
+                },
+                _script: Some(script),
+            }
+        }
+    };
+    prepared.command.envs(&step.env);
+    if let Some(cwd) = &step.cwd {
+        prepared.command.current_dir(cwd);
+    }
+    if step.timeout.is_some() || !io::stdin().is_terminal() {
+        isolate_process_group(&mut prepared.command);
+    }
+    Ok(prepared)
 }
 
-#[cfg(windows)]
-fn shell_command(command: &str) -> Command {
-    let mut cmd = Command::new("cmd");
-    cmd.arg("/C");
-    cmd.arg(command);
+/// Start the step as the leader of its own process group, so that killing
+/// the group also stops whatever the shell started. Steps without a timeout
+/// stay in qbit's group while stdin is a terminal: a background group is
+/// stopped as soon as it reads from the terminal, and Ctrl-C there already
+/// reaches every process in the foreground group.
+#[cfg(unix)]
+fn isolate_process_group(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    command.process_group(0);
+}
+
+/// Windows kills whole process trees by pid, so there is nothing to set up.
+#[cfg(not(unix))]
+fn isolate_process_group(_command: &mut Command) {}
+
+/// An interpreter block saved for its interpreter to run; removed on drop.
+struct TempScript {
+    path: PathBuf,
+}
+
+impl TempScript {
+    fn write(body: &str, extension: &str) -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "qbit-script-{}-{}{extension}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        // `create_new` refuses to follow a file (or symlink) already there.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("creating script file {}", path.display()))?;
+        let script = Self { path };
+        file.write_all(body.as_bytes())
+            .with_context(|| format!("writing script file {}", script.path.display()))?;
+        Ok(script)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempScript {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// `cmd` and PowerShell only run script files with their own extension.
+fn script_extension(program: &str) -> &'static str {
+    match program_stem(program).as_str() {
+        "cmd" => ".cmd",
+        "pwsh" | "powershell" => ".ps1",
+        _ => "",
+    }
+}
+
+fn interpreter_command(program: &str, script: &Path) -> Command {
+    let mut cmd = Command::new(program);
+    match program_stem(program).as_str() {
+        "cmd" => {
+            cmd.arg("/C");
+        }
+        "pwsh" | "powershell" => {
+            cmd.arg("-File");
+        }
+        _ => {}
+    }
+    cmd.arg(script);
     cmd
 }
 
+/// Wait for the step to exit, killing it once its timeout (if any) elapses.
+/// Ctrl-C meanwhile stops the step (see [`ActiveChild`]) and fails it with
+/// [`QbitError::Interrupted`], whatever its exit status.
+fn wait_for_step(child: &mut Child, step: &CommandStep) -> Result<ExitStatus> {
+    let _active = ActiveChild::track(child);
+    let status = wait_until_exit(child, step);
+    if interrupt::interrupted() {
+        return Err(QbitError::Interrupted.into());
+    }
+    status
+}
+
+fn wait_until_exit(child: &mut Child, step: &CommandStep) -> Result<ExitStatus> {
+    let waiting = || format!("waiting for shell command: {}", step.describe());
+    let Some(timeout) = step.timeout else {
+        return child.wait().with_context(waiting);
+    };
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().with_context(waiting)? {
+            return Ok(status);
+        }
+        if Instant::now() >= deadline {
+            // Killing only the shell would leave its children holding the
+            // output pipes, and a prefixed or logged step would keep running.
+            interrupt::kill_tree(child);
+            child.wait().with_context(waiting)?;
+            return Err(QbitError::StepTimedOut {
+                command: step.describe(),
+                timeout,
+            }
+            .into());
+        }
+        thread::sleep(TIMEOUT_POLL_INTERVAL);
+    }
+}
+
+fn step_prefix(label: &str, step: usize) -> String {
+    format!("[{label}:step {step}]")
+}
+
+/// Copy `reader` to `writer` line by line, writing `prefix` before each line.
+/// Each line goes out in a single write so concurrent readers don't interleave
+/// mid-line.
+fn forward_prefixed(reader: impl Read, mut writer: impl Write, prefix: &str) -> io::Result<()> {
+    for line in BufReader::new(reader).split(b'\n') {
+        let mut line = line?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let mut tagged = Vec::with_capacity(prefix.len() + line.len() + 2);
+        tagged.extend_from_slice(prefix.as_bytes());
+        tagged.push(b' ');
+        tagged.extend_from_slice(&line);
+        tagged.push(b'\n');
+        writer.write_all(&tagged)?;
+    }
+    writer.flush()
+}
+
+/// A missing shell or interpreter gets an actionable message (`hint`); other
+/// spawn failures keep the command as context.
+fn spawn_error(err: io::Error, program: &str, command: &str, hint: &str) -> anyhow::Error {
+    if err.kind() == io::ErrorKind::NotFound {
+        return QbitError::ToolNotFound {
+            tool: program.to_string(),
+            hint: hint.to_string(),
+        }
+        .into();
+    }
+    anyhow::Error::new(err).context(format!("running shell command: {command}"))
+}
+
+fn ensure_success(command: &str, status: ExitStatus) -> Result<()> {
+    if !status.success() {
+        return Err(QbitError::CommandFailed {
+            command: command.to_string(),
+            code: status.code(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+const DEFAULT_SHELL: &str = "cmd";
+
 #[cfg(not(windows))]
-fn shell_command(command: &str) -> Command {
-    let mut cmd = Command::new("sh");
-    cmd.arg("-c");
+const DEFAULT_SHELL: &str = "sh";
+
+/// Shell used for scripts: `QBIT_SHELL` when set (e.g. `bash`, `zsh`,
+/// `pwsh`), otherwise `sh` on Unix and `cmd` on Windows.
+fn shell_program() -> String {
+    resolve_shell(std::env::var("QBIT_SHELL").ok(), |shell| {
+        find_executable(shell).is_some()
+    })
+}
+
+/// A configured shell that isn't installed falls back to the default with a
+/// warning rather than failing every script.
+fn resolve_shell(configured: Option<String>, is_available: impl Fn(&str) -> bool) -> String {
+    let Some(shell) = configured
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+    else {
+        return DEFAULT_SHELL.to_string();
+    };
+    if is_available(&shell) {
+        return shell;
+    }
+    eprintln!("warning: QBIT_SHELL=`{shell}` was not found; falling back to `{DEFAULT_SHELL}`");
+    DEFAULT_SHELL.to_string()
+}
+
+/// Build `<shell> <flag> <command>`, picking the flag the shell understands.
+fn shell_command_with(shell: &str, command: &str) -> Command {
+    let mut cmd = Command::new(shell);
+    cmd.arg(command_flag(shell));
     cmd.arg(command);
     cmd
 }
 
+fn command_flag(shell: &str) -> &'static str {
+    match program_stem(shell).as_str() {
+        "cmd" => "/C",
+        "pwsh" | "powershell" => "-Command",
+        _ => "-c",
+    }
+}
+
+/// Lowercase file name of `program` without its extension (`bash`, `pwsh`).
+fn program_stem(program: &str) -> String {
+    Path::new(program)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::test_env::EnvGuard;
+    use serial_test::serial;
 
     #[test]
     fn shell_command_uses_platform_shell() {
-        let cmd = shell_command("echo hi");
+        let cmd = shell_command_with(DEFAULT_SHELL, "echo hi");
         let program = cmd.get_program().to_string_lossy().to_string();
         let args: Vec<String> = cmd
             .get_args()
@@ -79,9 +815,207 @@ mod tests {
         }
     }
 
+    #[test]
+    fn qbit_shell_overrides_default_and_blank_falls_back() {
+        let available = |_: &str| true;
+        assert_eq!(resolve_shell(None, available), DEFAULT_SHELL);
+        assert_eq!(
+            resolve_shell(Some("  ".to_string()), available),
+            DEFAULT_SHELL
+        );
+        assert_eq!(resolve_shell(Some("bash".to_string()), available), "bash");
+    }
+
+    #[test]
+    fn unavailable_qbit_shell_falls_back_to_default() {
+        let shell = resolve_shell(Some("qbit-no-such-shell".to_string()), |_| false);
+        assert_eq!(shell, DEFAULT_SHELL);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn qbit_shell_env_changes_command_program() {
+        let _guard = EnvGuard::set("QBIT_SHELL", "bash");
+        let cmd = shell_command_with(&shell_program(), "echo hi");
+        assert_eq!(cmd.get_program(), "bash");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["-c", "echo hi"]);
+    }
+
+    #[test]
+    fn command_flag_matches_shell_family() {
+        assert_eq!(command_flag("/bin/dash"), "-c");
+        assert_eq!(command_flag("bash"), "-c");
+        assert_eq!(command_flag("pwsh"), "-Command");
+        assert_eq!(command_flag("cmd.exe"), "/C");
+    }
+
+    #[test]
+    fn missing_shell_reports_actionable_error() {
+        let shell = "qbit-no-such-shell";
+        let err = shell_command_with(shell, "echo hi")
+            .stdout(Stdio::null())
+            .status()
+            .map_err(|err| spawn_error(err, shell, "echo hi", SHELL_HINT))
+            .expect_err("shell does not exist");
+        assert!(
+            matches!(
+                err.downcast_ref::<QbitError>(),
+                Some(QbitError::ToolNotFound { tool, .. }) if tool == shell
+            ),
+            "{err:#}"
+        );
+        assert!(err.to_string().contains("set QBIT_SHELL"), "{err}");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn join_args_quotes_spaces_and_quotes() {
+        let args = vec![
+            "python".to_string(),
+            "-c".to_string(),
+            "import sys;print(sys.prefix)".to_string(),
+            "it's".to_string(),
+        ];
+        assert_eq!(
+            join_args(&args),
+            "python -c 'import sys;print(sys.prefix)' 'it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn prefixed_lines_carry_label_and_step() {
+        let mut forwarded = Vec::new();
+        forward_prefixed(
+            "foo\r\nbar\n".as_bytes(),
+            &mut forwarded,
+            &step_prefix("script:hello", 2),
+        )
+        .expect("forward lines");
+        assert_eq!(
+            String::from_utf8(forwarded).expect("utf8"),
+            "[script:hello:step 2] foo\n[script:hello:step 2] bar\n"
+        );
+    }
+
     #[test]
     fn run_commands_rejects_empty_command_list() {
-        let err = run_commands("demo", &[]).expect_err("must fail");
+        let err = run_commands("demo", &[], OutputMode::Human, RunOptions::default())
+            .expect_err("must fail");
         assert!(err.to_string().contains("no commands defined"));
     }
+
+    #[test]
+    fn summary_reports_total_and_verbose_step_timings() {
+        let step = |step: usize, ms: u64| StepReport {
+            step,
+            command: format!("make part{step}"),
+            exit_code: Some(0),
+            duration_ms: ms,
+        };
+        let steps = [step(1, 1200), step(2, 3000)];
+        let total = Duration::from_millis(4250);
+
+        assert_eq!(
+            summary_lines("script:build", &steps, 2, total, true, false),
+            ["script:build completed in 4.2s (2 steps)"]
+        );
+        assert_eq!(
+            summary_lines("script:build", &steps, 2, total, true, true),
+            [
+                "  step 1: 1.2s -> make part1",
+                "  step 2: 3.0s -> make part2",
+                "script:build completed in 4.2s (2 steps)",
+            ]
+        );
+        assert_eq!(
+            summary_lines("script:build", &steps[..1], 3, total, false, false),
+            ["script:build failed after 4.2s (step 1 of 3 steps)"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn step_runs_in_its_cwd() {
+        let tmp = tempfile::tempdir().expect("tempdir");
+        let step = CommandStep {
+            command: "touch marker".to_string(),
+            cwd: Some(tmp.path().to_path_buf()),
+            ..CommandStep::default()
+        };
+        run_commands("demo", &[step], OutputMode::Human, RunOptions::default()).expect("step runs");
+        assert!(tmp.path().join("marker").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn step_past_its_timeout_is_killed() {
+        let step = CommandStep {
+            command: "sleep 5".to_string(),
+            timeout: Some(Duration::from_millis(100)),
+            ..CommandStep::default()
+        };
+        let started = Instant::now();
+        let err = run_commands("demo", &[step], OutputMode::Human, RunOptions::default())
+            .expect_err("must time out");
+        assert!(
+            matches!(
+                err.downcast_ref::<QbitError>(),
+                Some(QbitError::StepTimedOut { .. })
+            ),
+            "{err:#}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_processes_the_step_started() {
+        let step = CommandStep {
+            command: "sleep 600; true".to_string(),
+            timeout: Some(Duration::from_secs(1)),
+            ..CommandStep::default()
+        };
+        let options = RunOptions {
+            prefix_lines: true,
+            ..RunOptions::default()
+        };
+        let started = Instant::now();
+        let err =
+            run_commands("demo", &[step], OutputMode::Human, options).expect_err("must time out");
+        assert!(
+            matches!(
+                err.downcast_ref::<QbitError>(),
+                Some(QbitError::StepTimedOut { .. })
+            ),
+            "{err:#}"
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn temp_script_is_removed_when_dropped() {
+        let script = TempScript::write("echo hi\n", ".cmd").expect("write script");
+        let path = script.path().to_path_buf();
+        assert_eq!(path.extension().and_then(|ext| ext.to_str()), Some("cmd"));
+        assert_eq!(fs::read_to_string(&path).expect("read script"), "echo hi\n");
+        drop(script);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn interpreters_get_the_flags_their_script_files_need() {
+        let args = |program: &str| {
+            interpreter_command(program, Path::new("s"))
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(args("bash"), ["s"]);
+        assert_eq!(args("cmd"), ["/C", "s"]);
+        assert_eq!(args("pwsh"), ["-File", "s"]);
+        assert_eq!(script_extension("pwsh"), ".ps1");
+        assert_eq!(script_extension("python3"), "");
+    }
 }
@@ -0,0 +1,58 @@
+//! Guards for tests that change process-wide state. Tests using them must be
+//! `#[serial]`, since the state is shared by every test thread.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Sets or removes an environment variable until dropped.
+pub struct EnvGuard {
+    key: &'static str,
+    original: Option<OsString>,
+}
+
+impl EnvGuard {
+    pub fn set(key: &'static str, value: impl AsRef<OsStr>) -> Self {
+        let original = std::env::var_os(key);
+        // SAFETY: tests that mutate env vars use `#[serial]`, so there is no
+        // concurrent mutation in this process.
+        unsafe { std::env::set_var(key, value) };
+        Self { key, original }
+    }
+
+    pub fn remove(key: &'static str) -> Self {
+        let original = std::env::var_os(key);
+        // SAFETY: see `EnvGuard::set`.
+        unsafe { std::env::remove_var(key) };
+        Self { key, original }
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        match &self.original {
+            // SAFETY: restoration runs in the same serial test.
+            Some(value) => unsafe { std::env::set_var(self.key, value) },
+            // SAFETY: restoration runs in the same serial test.
+            None => unsafe { std::env::remove_var(self.key) },
+        }
+    }
+}
+
+/// Switches the current directory until dropped.
+pub struct CwdGuard {
+    original: PathBuf,
+}
+
+impl CwdGuard {
+    pub fn set(path: &Path) -> Self {
+        let original = std::env::current_dir().expect("current dir");
+        std::env::set_current_dir(path).expect("set current dir");
+        Self { original }
+    }
+}
+
+impl Drop for CwdGuard {
+    fn drop(&mut self) {
+        let _ = std::env::set_current_dir(&self.original);
+    }
+}
@@ -0,0 +1,37 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+#[test]
+fn clean_yes_removes_venv() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("requirements.txt"), "").expect("write requirements.txt");
+    fs::create_dir_all(tmp.path().join("venv").join("bin")).expect("create venv");
+    fs::create_dir(tmp.path().join("src")).expect("create src");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["clean", "--yes"])
+        .assert()
+        .success();
+
+    assert!(!tmp.path().join("venv").exists());
+    assert!(tmp.path().join("src").is_dir());
+    assert!(tmp.path().join("requirements.txt").is_file());
+}
+
+#[test]
+fn clean_without_yes_refuses_when_not_interactive() {
+    let tmp = tempdir().expect("tempdir");
+    fs::create_dir(tmp.path().join("venv")).expect("create venv");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .arg("clean")
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("--yes"));
+    assert!(tmp.path().join("venv").is_dir());
+}
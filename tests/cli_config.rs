@@ -0,0 +1,44 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+#[test]
+fn config_set_updates_value_and_get_reads_it_back() {
+    let tmp = tempdir().expect("tempdir");
+    let config = "# tools for local dev\ninstall:\n  postgres:\n    version: \"15\"\n    identifiers:\n      apt: postgresql\nscripts:\n  build: cargo build\n";
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["config", "set", "install.postgres.version", "16"])
+        .assert()
+        .success();
+
+    let written = fs::read_to_string(tmp.path().join("qbit.yml")).expect("read qbit.yml");
+    assert_eq!(written, config.replace("\"15\"", "\"16\""));
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["config", "get", "install.postgres.version"])
+        .assert()
+        .success()
+        .stdout("16\n");
+}
+
+#[test]
+fn config_set_refuses_structural_edits() {
+    let tmp = tempdir().expect("tempdir");
+    let config = "install:\n  postgres: postgresql\n";
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["config", "set", "install.postgres.version", "16"])
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("edit the file manually"), "{stderr}");
+    let written = fs::read_to_string(tmp.path().join("qbit.yml")).expect("read qbit.yml");
+    assert_eq!(written, config);
+}
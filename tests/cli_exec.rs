@@ -0,0 +1,48 @@
+use std::process::Command;
+
+use tempfile::tempdir;
+
+fn normalize_output(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).replace("\r\n", "\n")
+}
+
+fn system_python() -> &'static str {
+    if cfg!(windows) { "python" } else { "python3" }
+}
+
+#[test]
+fn exec_runs_with_project_venv_on_path() {
+    let tmp = tempdir().expect("tempdir");
+    let created = Command::new(system_python())
+        .args(["-m", "venv", "venv"])
+        .current_dir(tmp.path())
+        .status();
+    if !matches!(created, Ok(status) if status.success()) {
+        eprintln!("skipping: no Python with venv support available");
+        return;
+    }
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["exec", "--", "python", "-c", "import sys;print(sys.prefix)"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let prefix = std::path::PathBuf::from(stdout.trim());
+    let expected = tmp.path().join("venv");
+    assert_eq!(
+        prefix.canonicalize().expect("prefix exists"),
+        expected.canonicalize().expect("venv exists")
+    );
+}
+
+#[test]
+fn exec_propagates_command_exit_code() {
+    let tmp = tempdir().expect("tempdir");
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["exec", "--", "exit", "4"])
+        .assert()
+        .code(4);
+}
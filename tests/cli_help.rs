@@ -15,3 +15,36 @@ fn cli_help_prints_expected_banner() {
             || stdout.to_ascii_lowercase().contains("qbit")
     );
 }
+
+#[test]
+fn man_renders_roff_page_with_subcommands() {
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .arg("man")
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(stdout.contains(".TH qbit 1"));
+    assert!(stdout.contains("install"));
+}
+
+#[test]
+fn version_prints_semver_and_platform() {
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .arg("version")
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let version = stdout
+        .strip_prefix("qbit ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("version after `qbit `");
+    let parts: Vec<&str> = version.split('.').collect();
+    assert_eq!(parts.len(), 3, "{stdout}");
+    assert!(
+        parts.iter().all(|part| part.parse::<u64>().is_ok()),
+        "{stdout}"
+    );
+    assert!(stdout.contains(std::env::consts::OS));
+}
@@ -0,0 +1,59 @@
+use std::fs;
+
+use tempfile::tempdir;
+
+#[test]
+fn init_js_non_interactive_scaffolds_project() {
+    let tmp = tempdir().expect("tempdir");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["init", "--type", "js", "--non-interactive"])
+        .assert()
+        .success();
+
+    let package = fs::read_to_string(tmp.path().join("package.json")).expect("read package.json");
+    let config = fs::read_to_string(tmp.path().join("qbit.yml")).expect("read qbit.yml");
+    // Every starter script must call an npm script the template defines.
+    for script in ["start", "build"] {
+        assert!(
+            config.contains(&format!("qbit js run {script}")),
+            "{config}"
+        );
+        assert!(package.contains(&format!("\"{script}\":")), "{package}");
+    }
+}
+
+#[test]
+fn init_non_interactive_without_markers_asks_for_type() {
+    let tmp = tempdir().expect("tempdir");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["init", "--non-interactive"])
+        .assert()
+        .code(1);
+
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(stderr.contains("--type"));
+    assert!(!tmp.path().join("qbit.yml").exists());
+}
+
+#[test]
+fn java_init_gradle_scaffolds_build_gradle() {
+    let tmp = tempdir().expect("tempdir");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["java", "init", "--gradle"])
+        .assert()
+        .success();
+
+    let build = fs::read_to_string(tmp.path().join("build.gradle")).expect("read build.gradle");
+    assert!(build.contains("mainClass = 'app.App'"), "{build}");
+    assert!(tmp.path().join("settings.gradle").is_file());
+    assert!(tmp.path().join("src/main/java/app/App.java").is_file());
+    assert!(!tmp.path().join("pom.xml").exists());
+    let config = fs::read_to_string(tmp.path().join("qbit.yml")).expect("read qbit.yml");
+    assert!(config.contains("qbit java run build"), "{config}");
+}
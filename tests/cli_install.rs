@@ -0,0 +1,606 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+fn normalize_output(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).replace("\r\n", "\n")
+}
+
+/// Put a no-op `apt-get` first on PATH so the manager override resolves on
+/// any Unix host; returns the PATH value to use.
+fn path_with_fake_apt(dir: &Path) -> String {
+    path_with_fake_tools(dir, &["apt-get"])
+}
+
+/// Put no-op executables named `tools` first on PATH.
+fn path_with_fake_tools(dir: &Path, tools: &[&str]) -> String {
+    let fakebin = dir.join("fakebin");
+    fs::create_dir_all(&fakebin).expect("create fakebin");
+    for tool in tools {
+        let path = fakebin.join(tool);
+        fs::write(&path, "#!/bin/sh\nexit 0\n").expect("write fake tool");
+        let mut perms = fs::metadata(&path).expect("metadata").permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&path, perms).expect("set executable bit");
+    }
+
+    format!(
+        "{}:{}",
+        fakebin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    )
+}
+
+#[test]
+fn install_json_dry_run_emits_resolved_plan() {
+    let tmp = tempdir().expect("tempdir");
+    let path = path_with_fake_apt(tmp.path());
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path)
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .args(["install", "foo", "--json", "--dry-run"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let plan: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(plan["target"], "foo");
+    assert_eq!(plan["manager"], "apt-get");
+    assert!(
+        plan["command"]
+            .as_str()
+            .is_some_and(|command| command.contains("install foo"))
+    );
+}
+
+#[test]
+fn install_list_managers_shows_every_known_manager() {
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .args(["install", "--list-managers"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let row = |name: &str| stdout.lines().find(|line| line.starts_with(name));
+    assert!(row("apt-get").is_some_and(|line| line.contains("apt-get, apt")));
+    assert!(row("brew").is_some_and(|line| line.contains("brew, homebrew")));
+}
+
+#[test]
+fn install_dry_run_prints_configured_note() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    note: \"Restart your shell afterwards.\"\n",
+    )
+    .expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .args(["install", "foo", "--dry-run"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(
+        stdout.contains("Note: Restart your shell afterwards."),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn install_print_plan_shows_configured_version_and_identifier() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    version: \"1.2.3\"\n    identifiers:\n      apt: libfoo\n",
+    )
+    .expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .args(["install", "foo", "--print-plan"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(stdout.contains("Version: 1.2.3"), "{stdout}");
+    let apt_line = stdout
+        .lines()
+        .find(|line| line.trim_start().starts_with("apt-get (selected):"))
+        .expect("apt-get row");
+    assert!(apt_line.contains("libfoo -> "), "{stdout}");
+    assert!(apt_line.contains("install libfoo=1.2.3"), "{stdout}");
+}
+
+#[test]
+fn install_routes_pinned_target_to_asdf_when_tool_versions_present() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join(".tool-versions"), "python 3.11.9\n").expect("write .tool-versions");
+    let path = path_with_fake_tools(tmp.path(), &["apt-get", "asdf"]);
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env_remove("QBIT_VERSION_MANAGER")
+        .args(["install", "python:3.12.4", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[dry-run] asdf install python 3.12.4",
+        ));
+
+    // Opting out hands the target back to the OS package manager.
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_VERSION_MANAGER", "none")
+        .args(["install", "python:3.12.4", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("apt-get"));
+}
+
+#[test]
+fn explicit_manager_wins_over_detected_asdf() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join(".tool-versions"), "python 3.11.9\n").expect("write .tool-versions");
+    let path = path_with_fake_tools(tmp.path(), &["apt-get", "asdf"]);
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env_remove("QBIT_PACKAGE_MANAGER")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install", "python:3.12.4", "--dry-run", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&["--manager", "apt-get"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("apt-get").and(predicate::str::contains("asdf install").not()),
+        );
+
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  manager: apt-get\n",
+    )
+    .expect("write qbit.yml");
+    install(&[]).assert().success().stdout(
+        predicate::str::contains("apt-get").and(predicate::str::contains("asdf install").not()),
+    );
+}
+
+#[test]
+fn asdf_installs_are_locked_and_frozen_rejects_unlocked_ones() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("qbit.yml"), "scripts: {}\n").expect("write qbit.yml");
+    fs::write(tmp.path().join(".tool-versions"), "python 3.11.9\n").expect("write .tool-versions");
+    let path = path_with_fake_tools(tmp.path(), &["apt-get", "asdf"]);
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install"])
+            .args(args);
+        cmd
+    };
+
+    install(&["python:3.12.4", "--frozen"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("qbit.lock"));
+
+    install(&["python:3.12.4"]).assert().success();
+    let lock = fs::read_to_string(tmp.path().join("qbit.lock")).expect("read qbit.lock");
+    assert!(lock.contains("asdf install python 3.12.4"), "{lock}");
+
+    install(&["python:3.12.4", "--frozen"]).assert().success();
+    install(&["nodejs:20.11.0", "--frozen"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`nodejs` has no entry in qbit.lock",
+        ));
+    install(&["python:3.13.0", "--frozen"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("qbit.lock is stale for `python`"));
+}
+
+#[test]
+fn config_alias_expands_to_install() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("qbit.yml"), "aliases:\n  i: install\n").expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .args(["i", "foo", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run]").and(predicate::str::contains("install foo")));
+}
+
+#[test]
+fn config_alias_loop_is_a_usage_error() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("qbit.yml"), "aliases:\n  a: b\n  b: a\n").expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["a"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Alias loop detected: a -> b -> a"));
+}
+
+#[test]
+fn install_writes_lock_and_frozen_run_reads_it() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    version: \"1.2\"\n",
+    )
+    .expect("write qbit.yml");
+    let path = path_with_fake_apt(tmp.path());
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install", "foo", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&["--frozen"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("qbit.lock"));
+
+    install(&[]).assert().success();
+    let lock = fs::read_to_string(tmp.path().join("qbit.lock")).expect("read qbit.lock");
+    assert!(lock.contains("[targets.foo]"), "{lock}");
+    assert!(lock.contains("manager = \"apt-get\""), "{lock}");
+    assert!(lock.contains("version = \"1.2\""), "{lock}");
+    assert!(
+        lock.contains("command = \"apt-get install foo=1.2"),
+        "{lock}"
+    );
+
+    install(&["--frozen", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[dry-run] apt-get install foo=1.2",
+        ));
+
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    version: \"1.3\"\n",
+    )
+    .expect("rewrite qbit.yml");
+    install(&["--frozen", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("qbit.lock is stale for `foo`"));
+}
+
+#[test]
+fn unpinned_install_locks_the_version_the_manager_installed() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("qbit.yml"), "scripts: {}\n").expect("write qbit.yml");
+    let path = path_with_fake_apt(tmp.path());
+    let dpkg_query = tmp.path().join("fakebin").join("dpkg-query");
+    fs::write(
+        &dpkg_query,
+        "#!/bin/sh\ncase \"$2\" in -f=*Version*) printf '2.1-3' ;; *) exit 1 ;; esac\n",
+    )
+    .expect("write fake dpkg-query");
+    fs::set_permissions(&dpkg_query, fs::Permissions::from_mode(0o755))
+        .expect("set executable bit");
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install", "foo", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&[]).assert().success();
+    let lock = fs::read_to_string(tmp.path().join("qbit.lock")).expect("read qbit.lock");
+    assert!(lock.contains("version = \"2.1-3\""), "{lock}");
+
+    install(&["--frozen", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[dry-run] apt-get install foo=2.1-3",
+        ));
+}
+
+#[test]
+fn install_does_not_skip_packages_left_with_only_config_files() {
+    let tmp = tempdir().expect("tempdir");
+    let path = path_with_fake_apt(tmp.path());
+    let dpkg_query = tmp.path().join("fakebin").join("dpkg-query");
+    fs::write(
+        &dpkg_query,
+        "#!/bin/sh\ncase \"$2\" in -f=*Status*) printf 'deinstall ok config-files' ;; esac\n",
+    )
+    .expect("write fake dpkg-query");
+    fs::set_permissions(&dpkg_query, fs::Permissions::from_mode(0o755))
+        .expect("set executable bit");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .env_remove("QBIT_VERSION_MANAGER")
+        .args(["install", "foo", "--no-sudo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Executing: apt-get install foo"))
+        .stdout(predicate::str::contains("already installed").not());
+}
+
+#[test]
+fn install_outside_a_project_writes_no_lock() {
+    let tmp = tempdir().expect("tempdir");
+    let path = path_with_fake_apt(tmp.path());
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .env_remove("QBIT_VERSION_MANAGER")
+        .args(["install", "foo", "--no-sudo"])
+        .assert()
+        .success();
+    assert!(!tmp.path().join("qbit.lock").exists());
+}
+
+#[test]
+fn install_runs_postinstall_after_the_installer() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    postinstall:\n      - \"echo postinstall done\"\n",
+    )
+    .expect("write qbit.yml");
+    let path = path_with_fake_apt(tmp.path());
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install", "foo", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&["--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[dry-run] postinstall: echo postinstall done",
+        ))
+        .stdout(predicate::str::contains("\npostinstall done").not());
+
+    let assert = install(&[]).assert().success();
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let installed = stdout
+        .find("Executing: apt-get install foo")
+        .expect("installer ran");
+    let hook = stdout.find("\npostinstall done").expect("postinstall ran");
+    assert!(installed < hook, "{stdout}");
+}
+
+#[test]
+fn install_json_folds_postinstall_into_one_document() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  foo:\n    postinstall:\n      - \"echo postinstall done\"\n",
+    )
+    .expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .env_remove("QBIT_VERSION_MANAGER")
+        .args(["--json", "install", "foo", "--no-sudo"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let report: serde_json::Value = serde_json::from_str(&stdout).expect("a single JSON document");
+    assert_eq!(report["target"], "foo");
+    assert_eq!(report["postinstall"]["success"], true);
+    assert_eq!(
+        report["postinstall"]["steps"][0]["command"],
+        "echo postinstall done"
+    );
+}
+
+#[test]
+fn install_manager_flag_overrides_env_for_one_run() {
+    let tmp = tempdir().expect("tempdir");
+    let path = path_with_fake_tools(tmp.path(), &["apt-get", "dnf"]);
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(["install", "foo", "--dry-run", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&["--manager", "dnf"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run] dnf install foo"));
+    install(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run] apt-get install foo"));
+    install(&["--manager", "snap"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown package manager `snap` in --manager",
+        ));
+}
+
+#[test]
+fn install_caches_auto_detected_manager_in_lock() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("qbit.yml"), "scripts: {}\n").expect("write qbit.yml");
+    let path = path_with_fake_apt(tmp.path());
+    let qbit = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_CI", "0")
+            .env_remove("QBIT_PACKAGE_MANAGER")
+            .env_remove("QBIT_VERSION_MANAGER")
+            .args(args);
+        cmd
+    };
+
+    qbit(&["install", "foo", "--no-sudo"]).assert().success();
+    let lock = fs::read_to_string(tmp.path().join("qbit.lock")).expect("read qbit.lock");
+    assert!(lock.contains("\nmanager = \"apt-get\""), "{lock}");
+
+    qbit(&["why", "foo"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "auto-detected on an earlier run and cached in qbit.lock",
+        ));
+}
+
+#[test]
+fn install_jobs_falls_back_to_one_for_apt() {
+    let tmp = tempdir().expect("tempdir");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .args([
+            "install",
+            "foo",
+            "bar",
+            "--jobs",
+            "4",
+            "--dry-run",
+            "--no-sudo",
+        ])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "apt-get takes a global lock, so targets are installed one at a time (--jobs 1)",
+        ));
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let foo = stdout.find("install foo").expect("foo installed");
+    let bar = stdout.find("install bar").expect("bar installed");
+    assert!(foo < bar, "{stdout}");
+}
+
+#[test]
+fn install_under_ci_assumes_yes_unless_no_ci() {
+    let tmp = tempdir().expect("tempdir");
+    let path = path_with_fake_apt(tmp.path());
+    let install = |args: &[&str]| {
+        let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli");
+        cmd.current_dir(tmp.path())
+            .env("PATH", &path)
+            .env("QBIT_PACKAGE_MANAGER", "apt-get")
+            .env_remove("QBIT_CI")
+            .env("CI", "true")
+            .args(["install", "foo", "--dry-run", "--no-sudo"])
+            .args(args);
+        cmd
+    };
+
+    install(&[])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run] apt-get install -y foo"));
+    install(&["--no-ci"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[dry-run] apt-get install foo"));
+}
+
+#[test]
+fn why_explains_identifier_key_and_manager() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "install:\n  postgres:\n    version: \"15\"\n    identifiers:\n      apt: postgresql\n      brew: postgresql@15\n      default: postgres\n",
+    )
+    .expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", path_with_fake_apt(tmp.path()))
+        .env("QBIT_PACKAGE_MANAGER", "apt-get")
+        .env("QBIT_CI", "0")
+        .env_remove("QBIT_VERSION_MANAGER")
+        .args(["why", "postgres"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(stdout.contains("Package manager: apt-get"), "{stdout}");
+    assert!(
+        stdout.contains("QBIT_PACKAGE_MANAGER is set to `apt-get`"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Identifier: postgresql"), "{stdout}");
+    assert!(stdout.contains("the entry's `apt` key"), "{stdout}");
+    assert!(stdout.contains("install postgresql=15"), "{stdout}");
+    assert!(!tmp.path().join("qbit.lock").exists());
+}
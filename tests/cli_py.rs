@@ -0,0 +1,98 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+/// Stand-in venv interpreter that answers `-m pip freeze` with fixed pins and
+/// fails everything else.
+fn write_fake_venv_python(project: &Path) {
+    let bin = project.join("venv/bin");
+    fs::create_dir_all(&bin).expect("create venv/bin");
+    let python = bin.join("python");
+    fs::write(
+        &python,
+        "#!/bin/sh\nif [ \"$*\" = \"-m pip freeze\" ]; then\n  printf 'flask==3.0.3\\nrequests==2.32.3\\n'\n  exit 0\nfi\nexit 1\n",
+    )
+    .expect("write fake python");
+    fs::set_permissions(&python, fs::Permissions::from_mode(0o755)).expect("chmod fake python");
+}
+
+#[test]
+fn py_freeze_prints_packages_without_touching_requirements() {
+    let tmp = tempdir().expect("tempdir");
+    write_fake_venv_python(tmp.path());
+    let requirements = "# hand-edited\nflask\n";
+    fs::write(tmp.path().join("requirements.txt"), requirements).expect("write requirements");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["py", "freeze"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("flask==3.0.3\nrequests==2.32.3\n"));
+
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("requirements.txt")).expect("read requirements"),
+        requirements
+    );
+    assert!(!tmp.path().join("requirements.lock").exists());
+}
+
+#[test]
+fn py_lock_writes_separate_lock_file() {
+    let tmp = tempdir().expect("tempdir");
+    write_fake_venv_python(tmp.path());
+    let requirements = "flask\n";
+    fs::write(tmp.path().join("requirements.txt"), requirements).expect("write requirements");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["py", "lock"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("without hashes"));
+
+    let lock = fs::read_to_string(tmp.path().join("requirements.lock")).expect("read lock");
+    assert!(lock.contains("flask==3.0.3\nrequests==2.32.3\n"), "{lock}");
+    assert_eq!(
+        fs::read_to_string(tmp.path().join("requirements.txt")).expect("read requirements"),
+        requirements
+    );
+}
+
+#[test]
+fn py_shell_starts_shell_with_venv_activated() {
+    let tmp = tempdir().expect("tempdir");
+    write_fake_venv_python(tmp.path());
+    let shell = tmp.path().join("fake-shell");
+    fs::write(
+        &shell,
+        "#!/bin/sh\necho \"VIRTUAL_ENV=$VIRTUAL_ENV\"\necho \"PATH=$PATH\"\n",
+    )
+    .expect("write fake shell");
+    fs::set_permissions(&shell, fs::Permissions::from_mode(0o755)).expect("chmod fake shell");
+    let venv = tmp
+        .path()
+        .canonicalize()
+        .expect("canonical tmp")
+        .join("venv");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("SHELL", &shell)
+        .args(["py", "shell"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "VIRTUAL_ENV={}\n",
+            venv.display()
+        )))
+        .stdout(predicate::str::contains(format!(
+            "PATH={}:",
+            venv.join("bin").display()
+        )));
+}
@@ -35,9 +35,591 @@ fn run_missing_script_returns_actionable_error() {
         .current_dir(tmp.path())
         .args(["run", "missing_script"])
         .assert()
-        .code(1);
+        .code(4);
 
     let stderr = normalize_output(&assert.get_output().stderr);
     assert!(stderr.contains("Script"));
     assert!(stderr.contains("not found"));
 }
+
+#[test]
+fn run_without_config_exits_with_code_3() {
+    let tmp = tempdir().expect("tempdir");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "x"])
+        .assert()
+        .code(3);
+
+    let stderr = normalize_output(&assert.get_output().stderr);
+    assert!(stderr.contains("No qbit.yml/qbit.toml"));
+}
+
+#[test]
+fn run_without_config_suggests_init_for_detected_node_project() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("package.json"), "{}").expect("write package.json");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "x"])
+        .assert()
+        .code(3)
+        .stderr(predicate::str::contains(
+            "Detected a Node project; try `qbit js init`.",
+        ));
+}
+
+#[test]
+fn run_failing_script_propagates_child_exit_code() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  fail: "exit 3"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "fail"])
+        .assert()
+        .code(3);
+
+    let stderr = normalize_output(&assert.get_output().stderr);
+    assert!(stderr.contains("exited with code 3"));
+}
+
+#[test]
+fn unknown_subcommand_is_a_usage_error() {
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .arg("definitely-not-a-command")
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn run_json_reports_steps_and_exit_codes() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  hello: "echo hello-qbit"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["--json", "run", "hello"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let report: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(report["success"], true);
+    assert_eq!(report["steps"][0]["command"], "echo hello-qbit");
+    assert_eq!(report["steps"][0]["exit_code"], 0);
+}
+
+#[test]
+fn run_script_from_included_task_library() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "include: [\"qbit.tasks.yml\"]\nscripts:\n  hello: \"echo hello-qbit\"\n",
+    )
+    .expect("write qbit.yml");
+    fs::write(
+        tmp.path().join("qbit.tasks.yml"),
+        "scripts:\n  lint: \"echo lint-from-include\"\n",
+    )
+    .expect("write qbit.tasks.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "lint"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("lint-from-include"));
+}
+
+#[test]
+fn run_keep_going_summarizes_only_failed_scripts() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  first: "exit 3"
+  second: "echo second-ran"
+  third: "exit 4"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "first", "second", "third", "--keep-going"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("second-ran"));
+
+    let stderr = normalize_output(&assert.get_output().stderr);
+    let summary = stderr.split("Summary: ").nth(1).expect("summary section");
+    assert!(summary.starts_with("2 of 3 failed"), "{summary}");
+    assert!(summary.contains("- first: "));
+    assert!(summary.contains("- third: "));
+    assert!(!summary.contains("- second"));
+}
+
+#[test]
+fn bare_run_executes_default_script() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  default: "echo default-ran"
+  other: "echo other-ran"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .arg("run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("default-ran"))
+        .stdout(predicate::str::contains("other-ran").not());
+}
+
+#[test]
+fn bare_run_without_default_lists_scripts() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "scripts:\n  hello: \"echo hi\"\n",
+    )
+    .expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .arg("run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello: echo hi"));
+}
+
+#[test]
+fn run_prefix_tags_each_output_line_with_step_label() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  hello: "echo foo"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "hello", "--prefix"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(stdout.contains("[script:hello:step 1] foo\n"), "{stdout}");
+}
+
+#[cfg(unix)]
+#[test]
+fn run_applies_project_env_with_script_overrides() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"env:
+  GREETING: from-project
+  TARGET: world
+scripts:
+  plain: 'echo "$GREETING $TARGET"'
+  custom:
+    run: 'echo "$GREETING $TARGET"'
+    env:
+      GREETING: from-script
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("GREETING", "from-process")
+        .args(["run", "plain"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from-project world"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "custom"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from-script world"));
+}
+
+#[test]
+fn run_glob_runs_matching_scripts_in_sorted_order() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  "test:unit": "echo ran-unit"
+  "test:integration": "echo ran-integration"
+  lint: "echo ran-lint"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "test:*"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let integration = stdout.find("ran-integration").expect("integration ran");
+    let unit = stdout.find("ran-unit").expect("unit ran");
+    assert!(integration < unit, "{stdout}");
+    assert!(!stdout.contains("ran-lint"), "{stdout}");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "deploy:*"])
+        .assert()
+        .code(4);
+}
+
+#[test]
+fn run_dry_run_prints_steps_without_running_them() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  setup:
+    run: "touch ${TARGET_FILE}"
+    env:
+      TARGET_FILE: created.txt
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "setup", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "[script:setup] step 1 (dry-run) -> touch created.txt",
+        ));
+
+    assert!(!tmp.path().join("created.txt").exists());
+}
+
+#[test]
+fn run_dry_run_json_exports_resolved_step_plan() {
+    let tmp = tempdir().expect("tempdir");
+    fs::create_dir(tmp.path().join("web")).expect("create web dir");
+    let config = r#"scripts:
+  build:
+    env:
+      MODE: release
+    run:
+      - "cargo build --${MODE}"
+      - cmd: "npm run build -- --mode $MODE"
+        cwd: web
+        timeout: 60
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["--json", "run", "build", "--dry-run"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let plan: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    let steps = plan.as_array().expect("JSON array of steps");
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0]["command"], "cargo build --release");
+    assert_eq!(steps[1]["command"], "npm run build -- --mode release");
+    assert_eq!(steps[1]["env"]["MODE"], "release");
+    assert_eq!(steps[1]["timeout_secs"], 60);
+    assert!(
+        steps[1]["cwd"]
+            .as_str()
+            .is_some_and(|cwd| cwd.ends_with("web")),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn run_dry_run_json_prints_one_plan_for_several_scripts() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  "test:unit": "cargo test --lib"
+  "test:integration":
+    - "cargo build"
+    - "cargo test --test '*'"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["--json", "run", "test:*", "--dry-run"])
+        .assert()
+        .success();
+
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let plan: serde_json::Value = serde_json::from_str(&stdout).expect("a single JSON document");
+    let steps = plan.as_array().expect("JSON array of steps");
+    let labels: Vec<_> = steps
+        .iter()
+        .map(|step| step["label"].as_str().expect("label"))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            "script:test:integration",
+            "script:test:integration",
+            "script:test:unit"
+        ]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_detailed_toml_script_applies_step_env_and_cwd() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.toml"),
+        include_str!("fixtures/detailed_scripts.toml"),
+    )
+    .expect("write qbit.toml");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "build"])
+        .assert()
+        .success();
+
+    let greeting = fs::read_to_string(tmp.path().join("out/greeting.txt")).expect("greeting");
+    assert_eq!(greeting.trim(), "hello toml");
+}
+
+#[test]
+fn run_prints_timing_summary_with_step_count() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  build:
+    - "echo one"
+    - "echo two"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "build"])
+        .assert()
+        .success();
+    let stdout = normalize_output(&assert.get_output().stdout);
+    let summary = stdout.lines().last().expect("summary line");
+    assert!(
+        summary.starts_with("script:build completed in "),
+        "{stdout}"
+    );
+    assert!(summary.ends_with("s (2 steps)"), "{stdout}");
+    assert!(!stdout.contains("  step 1: "), "{stdout}");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "build", "-v"])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::contains("  step 2: ").and(predicate::str::contains("-> echo two")),
+        );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_without_config_falls_back_to_package_json_script() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("package.json"),
+        r#"{"scripts": {"start": "node index.js"}}"#,
+    )
+    .expect("write package.json");
+    let fakebin = tmp.path().join("fakebin");
+    fs::create_dir_all(&fakebin).expect("create fakebin");
+    let npm = fakebin.join("npm");
+    fs::write(&npm, "#!/bin/sh\necho \"fake-npm $*\"\n").expect("write fake npm");
+    fs::set_permissions(&npm, fs::Permissions::from_mode(0o755)).expect("chmod fake npm");
+    let path = format!(
+        "{}:{}",
+        fakebin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .env_remove("QBIT_JS_PM")
+        .args(["run", "start"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "running package.json script `start` via npm",
+        ))
+        .stdout(predicate::str::contains("fake-npm run start"));
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .env("PATH", &path)
+        .args(["run", "build"])
+        .assert()
+        .code(3);
+}
+
+#[cfg(unix)]
+#[test]
+fn run_interpreter_block_executes_multi_line_bash_script() {
+    let tmp = tempdir().expect("tempdir");
+    fs::write(tmp.path().join("a.txt"), "").expect("write a.txt");
+    fs::write(tmp.path().join("b.txt"), "").expect("write b.txt");
+    let config = r#"scripts:
+  list:
+    shell: bash
+    run: |
+      set -e
+      for f in *.txt; do
+        echo "found $f"
+      done
+  fail:
+    shell: bash
+    run: |
+      echo before
+      exit 3
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "list"])
+        .assert()
+        .success();
+    let stdout = normalize_output(&assert.get_output().stdout);
+    assert!(stdout.contains("found a.txt\nfound b.txt\n"), "{stdout}");
+    assert!(
+        stdout.contains("step 1 -> bash script (4 lines)"),
+        "{stdout}"
+    );
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "fail"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("before"))
+        .stderr(predicate::str::contains("bash script (2 lines)"));
+}
+
+#[test]
+fn run_output_tees_step_output_into_log_file() {
+    let tmp = tempdir().expect("tempdir");
+    let config = r#"scripts:
+  deploy:
+    - "echo deploying-app"
+    - "echo deploy-warning 1>&2"
+  broken: "exit 2"
+"#;
+    fs::write(tmp.path().join("qbit.yml"), config).expect("write qbit.yml");
+    let log_path = tmp.path().join("deploy.log");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "deploy", "--output", "deploy.log"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("deploying-app"))
+        .stderr(predicate::str::contains("deploy-warning"));
+
+    let log = normalize_output(&fs::read(&log_path).expect("read log"));
+    assert!(
+        log.contains("script:deploy step 1 started: echo deploying-app"),
+        "{log}"
+    );
+    assert!(log.contains("\ndeploying-app\n"), "{log}");
+    assert!(log.contains("\ndeploy-warning"), "{log}");
+    assert!(
+        log.contains("script:deploy step 2 exited with code 0"),
+        "{log}"
+    );
+    assert!(log.starts_with('['), "{log}");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .current_dir(tmp.path())
+        .args(["run", "broken", "--output", "deploy.log"])
+        .assert()
+        .code(2);
+    let log = normalize_output(&fs::read(&log_path).expect("read log"));
+    assert!(
+        log.contains("deploying-app"),
+        "earlier runs are kept: {log}"
+    );
+    assert!(
+        log.contains("script:broken step 1 exited with code 2"),
+        "{log}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn run_interrupted_with_ctrl_c_stops_step_and_exits_130() {
+    use std::process::{Command, Stdio};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    let tmp = tempdir().expect("tempdir");
+    fs::write(
+        tmp.path().join("qbit.yml"),
+        "scripts:\n  build: \"echo $$ > step.pid; exec sleep 30\"\n",
+    )
+    .expect("write qbit.yml");
+
+    let mut qbit = Command::new(assert_cmd::cargo::cargo_bin!("qbit-cli"))
+        .current_dir(tmp.path())
+        .args(["run", "build"])
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("spawn qbit");
+    let pid_file = tmp.path().join("step.pid");
+    let started = Instant::now();
+    let step_pid = loop {
+        if let Some(pid) = fs::read_to_string(&pid_file)
+            .ok()
+            .filter(|pid| pid.ends_with('\n'))
+        {
+            break pid.trim().to_string();
+        }
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "step never started"
+        );
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    // Only qbit gets the signal, so the step exits only if qbit forwards it.
+    Command::new("kill")
+        .args(["-INT", &qbit.id().to_string()])
+        .status()
+        .expect("send SIGINT");
+    let status = qbit.wait().expect("wait for qbit");
+    assert_eq!(status.code(), Some(130));
+    // The orphaned step may linger as a zombie until it is reaped.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let state = Command::new("ps")
+            .args(["-o", "stat=", "-p", &step_pid])
+            .output()
+            .expect("probe step");
+        let state = String::from_utf8_lossy(&state.stdout).trim().to_string();
+        if state.is_empty() || state.starts_with('Z') {
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "step {step_pid} is still running ({state})"
+        );
+        thread::sleep(Duration::from_millis(20));
+    }
+}
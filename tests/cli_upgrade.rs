@@ -0,0 +1,45 @@
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+/// `qbit upgrade --check` answers from a fresh cached lookup, so seeding the
+/// cache stands in for GitHub.
+#[cfg(target_os = "linux")]
+#[test]
+fn upgrade_check_prints_release_notes_for_newer_version() {
+    let tmp = tempdir().expect("tempdir");
+    let cache = tmp.path().join("qbit");
+    fs::create_dir_all(&cache).expect("create cache dir");
+    let fetched_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock")
+        .as_secs();
+    let cached = serde_json::json!({
+        "fetched_at": fetched_at,
+        "release": {
+            "tag_name": "v99.0.0",
+            "assets": [],
+            "body": "- Faster installs\r\n- New `qbit java` commands",
+            "html_url": "https://github.com/qbit-click/qbit-cli/releases/tag/v99.0.0",
+        },
+    });
+    fs::write(
+        cache.join("latest-release-qbit_click_qbit_cli.json"),
+        cached.to_string(),
+    )
+    .expect("seed release cache");
+
+    assert_cmd::cargo::cargo_bin_cmd!("qbit-cli")
+        .env("XDG_CACHE_HOME", tmp.path())
+        .env_remove("QBIT_UPGRADE_REPO")
+        .env_remove("QBIT_UPGRADE_PRERELEASE")
+        .args(["upgrade", "--check"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Release notes for 99.0.0:\n  - Faster installs\n  - New `qbit java` commands\n",
+        ))
+        .stdout(predicate::str::contains("A newer qbit is available"));
+}